@@ -0,0 +1,144 @@
+//! Counting the allocations made while timing, with `CountingAllocator`.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Whether a `CountingAllocator` has allocated anything, i.e. whether it's
+/// the global allocator.
+static IN_USE: AtomicBool = AtomicBool::new(false);
+
+// How many allocations this thread has made through a `CountingAllocator`,
+// and of how many bytes.
+thread_local!(static ALLOCATED: Cell<Allocations> = const { Cell::new(Allocations { count: 0, bytes: 0 }) });
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+/// A number of allocations, and their total size.
+pub(crate) struct Allocations {
+  pub count: u64,
+  pub bytes: u64,
+}
+
+impl Allocations {
+  #[inline]
+  /// Returns how much this thread has allocated so far.
+  pub fn current() -> Allocations {
+    ALLOCATED.try_with(|allocated| allocated.get()).unwrap_or_default()
+  }
+
+  #[inline]
+  /// Returns what was allocated between reading `earlier` and this.
+  pub fn since(self, earlier: Allocations) -> Allocations {
+    Allocations {
+      count: self.count.wrapping_sub(earlier.count),
+      bytes: self.bytes.wrapping_sub(earlier.bytes),
+    }
+  }
+}
+
+/// Returns whether allocations are being counted at all.
+pub(crate) fn in_use() -> bool {
+  IN_USE.load(Ordering::Relaxed)
+}
+
+/// A global allocator that counts each thread's allocations, so timers can
+/// report how many allocations, and bytes, each timed window made:
+///
+/// ```
+/// use stopwatch::CountingAllocator;
+///
+/// #[global_allocator]
+/// static ALLOCATOR: CountingAllocator = CountingAllocator::new();
+/// # fn main() {}
+/// ```
+///
+/// A reallocation counts as allocating its new size. Frees aren't counted.
+pub struct CountingAllocator<A = System> {
+  inner: A,
+}
+
+impl CountingAllocator {
+  /// Creates an allocator that counts allocations made with `System`.
+  pub const fn new() -> CountingAllocator {
+    CountingAllocator::with_allocator(System)
+  }
+}
+
+impl Default for CountingAllocator {
+  fn default() -> CountingAllocator {
+    CountingAllocator::new()
+  }
+}
+
+impl<A> CountingAllocator<A> {
+  /// Creates an allocator that counts allocations made with `inner`.
+  pub const fn with_allocator(inner: A) -> CountingAllocator<A> {
+    CountingAllocator {
+      inner,
+    }
+  }
+
+  #[inline]
+  fn count(&self, bytes: usize) {
+    if !IN_USE.load(Ordering::Relaxed) {
+      IN_USE.store(true, Ordering::Relaxed);
+    }
+    // This fails while the thread is being torn down, and then the
+    // allocation just isn't counted.
+    let _ = ALLOCATED.try_with(|allocated| {
+      let Allocations { count, bytes: total } = allocated.get();
+      allocated.set(Allocations {
+        count: count + 1,
+        bytes: total + bytes as u64,
+      });
+    });
+  }
+}
+
+unsafe impl<A: GlobalAlloc> GlobalAlloc for CountingAllocator<A> {
+  #[inline]
+  unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+    self.count(layout.size());
+    self.inner.alloc(layout)
+  }
+
+  #[inline]
+  unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+    self.count(layout.size());
+    self.inner.alloc_zeroed(layout)
+  }
+
+  #[inline]
+  unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+    self.count(new_size);
+    self.inner.realloc(ptr, layout, new_size)
+  }
+
+  #[inline]
+  unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+    self.inner.dealloc(ptr, layout)
+  }
+}
+
+#[cfg(not(feature = "disable"))]
+#[test]
+fn test_counting_allocator() {
+  use TimerSet;
+
+  // Allocate through an allocator of our own, rather than the global one,
+  // so only what this test allocates is counted.
+  let allocator = CountingAllocator::new();
+  let layout = Layout::from_size_align(100, 8).unwrap();
+  let ts = TimerSet::new();
+  ts.time("a", || unsafe {
+    for _ in 0..2 {
+      allocator.dealloc(allocator.alloc(layout), layout);
+    }
+  });
+  ts.time("b", || {});
+  let timers = ts.merged();
+  assert_eq!((timers["a"].allocations(), timers["a"].allocated_bytes()), (2, 200));
+  assert_eq!(timers["b"].allocations(), 0);
+  assert!(in_use());
+  assert!(timers["a"].summary(::DurationFormat::Adaptive).ends_with(" with 2 allocs of 200B"));
+}
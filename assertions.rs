@@ -0,0 +1,49 @@
+//! Helpers for failing tests when timed code gets slower.
+
+use std::time::Duration;
+
+use {Stopwatch, TimerSet};
+
+impl<K> TimerSet<K> {
+  /// Returns the named timer, panicking if it has never run.
+  fn expect_ran(&self, name: &str) -> Stopwatch {
+    match self.merged().remove(name) {
+      Some(ref sw) if sw.number_of_windows > 0 => sw.clone(),
+      _ => panic!("timer {:?} never ran", name),
+    }
+  }
+
+  /// Panics unless the named timer has run, and its mean window is shorter
+  /// than `limit`.
+  pub fn assert_avg_below(&self, name: &str, limit: Duration) {
+    let sw = self.expect_ran(name);
+    let avg = Duration::from_nanos(sw.total_time / sw.number_of_windows);
+    assert!(avg < limit, "timer {:?} averaged {:?} over {} windows, not below {:?}", name, avg, sw.number_of_windows, limit);
+  }
+
+  /// Panics unless the named timer has run, and its total time is shorter
+  /// than `limit`.
+  pub fn assert_total_below(&self, name: &str, limit: Duration) {
+    let sw = self.expect_ran(name);
+    let total = Duration::from_nanos(sw.total_time);
+    assert!(total < limit, "timer {:?} totalled {:?}, not below {:?}", name, total, limit);
+  }
+}
+
+#[cfg(not(feature = "disable"))]
+#[test]
+fn test_assert_below() {
+  let ts = TimerSet::new();
+  ts.time("fast", || {});
+  ts.assert_avg_below("fast", Duration::from_secs(1));
+  ts.assert_total_below("fast", Duration::from_secs(1));
+}
+
+#[cfg(not(feature = "disable"))]
+#[test]
+#[should_panic(expected = "not below")]
+fn test_assert_avg_below_fails() {
+  let ts = TimerSet::new();
+  ts.time("slow", || ::std::thread::sleep(Duration::from_millis(2)));
+  ts.assert_avg_below("slow", Duration::from_millis(1));
+}
@@ -0,0 +1,121 @@
+//! Reporting from an async task, rather than a thread of its own.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Weak};
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use {Reporter, TimerSet};
+
+/// A future that reports on a set every interval, as `spawn_reporter`'s
+/// thread does, made by `TimerSet::async_reporter`. It finishes once the set
+/// is dropped, or can be dropped itself to stop reporting.
+pub struct AsyncReporter<R, S, F> {
+  timers: Weak<TimerSet>,
+  interval: Duration,
+  reporter: R,
+  sleep: S,
+  /// The sleep until the next report.
+  sleeping: Option<Pin<Box<F>>>,
+}
+
+// Only the sleep is ever pinned, and it's boxed.
+impl<R, S, F> Unpin for AsyncReporter<R, S, F> {}
+
+impl<R, S, F> Future for AsyncReporter<R, S, F>
+where
+  R: Reporter,
+  S: FnMut(Duration) -> F,
+  F: Future,
+{
+  type Output = ();
+
+  fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<()> {
+    let this = self.get_mut();
+    loop {
+      let interval = this.interval;
+      let sleep = &mut this.sleep;
+      let sleeping = this.sleeping.get_or_insert_with(|| Box::pin(sleep(interval)));
+      if sleeping.as_mut().poll(cx).is_pending() {
+        return Poll::Pending;
+      }
+      this.sleeping = None;
+      match this.timers.upgrade() {
+        None => return Poll::Ready(()),
+        Some(timers) => timers.report_interval(&this.reporter),
+      }
+    }
+  }
+}
+
+impl TimerSet {
+  /// Returns a future that, every `interval`, reports on this set like
+  /// `spawn_reporter`'s thread does, for spawning on an async runtime so it
+  /// needn't have a thread of its own. `sleep` is the runtime's, e.g.
+  /// `tokio::time::sleep` or `async_std::task::sleep`.
+  pub fn async_reporter<R, S, F>(self: &Arc<Self>, interval: Duration, reporter: R, sleep: S) -> AsyncReporter<R, S, F>
+  where
+    R: Reporter,
+    S: FnMut(Duration) -> F,
+    F: Future,
+  {
+    AsyncReporter {
+      timers: Arc::downgrade(self),
+      interval,
+      reporter,
+      sleep,
+      sleeping: None,
+    }
+  }
+}
+
+#[cfg(not(feature = "disable"))]
+#[test]
+fn test_async_reporter() {
+  use std::cell::RefCell;
+  use std::rc::Rc;
+  use std::task::Waker;
+
+  // Sleeps until polled again.
+  struct Nap(bool);
+  impl Future for Nap {
+    type Output = ();
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<()> {
+      if self.0 {
+        return Poll::Ready(());
+      }
+      self.0 = true;
+      cx.waker().wake_by_ref();
+      Poll::Pending
+    }
+  }
+
+  let ts = Arc::new(TimerSet::new());
+  let lines = Rc::new(RefCell::new(Vec::new()));
+  let naps = Rc::new(RefCell::new(Vec::new()));
+  let mut reporter = {
+    let lines = lines.clone();
+    let naps = naps.clone();
+    ts.async_reporter(Duration::from_secs(1), move |line: &str| lines.borrow_mut().push(line.to_string()), move |d| {
+      naps.borrow_mut().push(d);
+      Nap(false)
+    })
+  };
+  let mut cx = Context::from_waker(Waker::noop());
+  let mut poll = || Pin::new(&mut reporter).poll(&mut cx);
+
+  ts.time("a", || {});
+  assert!(poll().is_pending());
+  assert!(lines.borrow().is_empty());
+  assert!(poll().is_pending());
+  assert_eq!(lines.borrow().len(), 1);
+  assert!(lines.borrow()[0].starts_with("a: "), "{}", lines.borrow()[0]);
+  // Each report covers just its interval.
+  assert!(poll().is_pending());
+  assert_eq!(lines.borrow().len(), 1);
+  assert_eq!(*naps.borrow(), vec![Duration::from_secs(1); 3]);
+
+  drop(ts);
+  assert!(poll().is_ready());
+}
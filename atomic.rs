@@ -0,0 +1,115 @@
+//! A stopwatch that can be shared without a lock.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+#[cfg(not(feature = "disable"))]
+use {to_nanos, Instant};
+use {Stopwatch, StopwatchConfig};
+
+/// A stopwatch whose stats are kept in atomic counters, so it can time
+/// events through a shared reference, e.g. as a `static`:
+///
+/// ```
+/// use stopwatch::AtomicStopwatch;
+///
+/// static PARSE: AtomicStopwatch = AtomicStopwatch::new();
+///
+/// PARSE.timed(|| {});
+/// # if stopwatch::DISABLED { return; }
+/// assert_eq!(PARSE.to_stopwatch().number_of_windows, 1);
+/// ```
+///
+/// It doesn't keep a histogram, or track the variance or throughput.
+pub struct AtomicStopwatch {
+  total_time: AtomicU64,
+  number_of_windows: AtomicU64,
+  min_time: AtomicU64,
+  max_time: AtomicU64,
+}
+
+impl Default for AtomicStopwatch {
+  fn default() -> AtomicStopwatch {
+    AtomicStopwatch::new()
+  }
+}
+
+impl AtomicStopwatch {
+  /// Creates a new stopwatch.
+  pub const fn new() -> AtomicStopwatch {
+    AtomicStopwatch {
+      total_time: AtomicU64::new(0),
+      number_of_windows: AtomicU64::new(0),
+      min_time: AtomicU64::new(u64::MAX),
+      max_time: AtomicU64::new(0),
+    }
+  }
+
+  #[inline]
+  /// Times a function, updating stats as necessary.
+  pub fn timed<T, F: FnOnce() -> T>(&self, event: F) -> T {
+    #[cfg(feature = "disable")]
+    {
+      event()
+    }
+    #[cfg(not(feature = "disable"))]
+    {
+      let then = Instant::now();
+      let ret = event();
+      self.add_window(to_nanos(then.elapsed()));
+      ret
+    }
+  }
+
+  #[cfg_attr(feature = "disable", allow(dead_code))]
+  #[inline]
+  /// Folds a single time window, in nanoseconds, into the stats.
+  pub(crate) fn add_window(&self, dt: u64) {
+    self.total_time.fetch_add(dt, Ordering::Relaxed);
+    self.number_of_windows.fetch_add(1, Ordering::Relaxed);
+    self.min_time.fetch_min(dt, Ordering::Relaxed);
+    self.max_time.fetch_max(dt, Ordering::Relaxed);
+  }
+
+  /// Copies the stats out into a plain `Stopwatch`.
+  ///
+  /// The counters are read one at a time, so windows being recorded
+  /// concurrently may be only partly reflected.
+  pub fn to_stopwatch(&self) -> Stopwatch {
+    let mut sw = Stopwatch::with_config(StopwatchConfig { histogram: false, ..StopwatchConfig::default() });
+    sw.total_time = self.total_time.load(Ordering::Relaxed);
+    sw.number_of_windows = self.number_of_windows.load(Ordering::Relaxed);
+    sw.min_time = self.min_time.load(Ordering::Relaxed);
+    sw.max_time = self.max_time.load(Ordering::Relaxed);
+    if sw.number_of_windows > 0 {
+      sw.mean = sw.total_time as f64 / sw.number_of_windows as f64;
+      // Nor which windows were recent.
+      sw.ewma = sw.mean;
+    }
+    // We don't track the variance.
+    sw.m2 = f64::NAN;
+    sw
+  }
+
+  /// Forgets every time window clocked so far.
+  pub fn reset(&self) {
+    self.total_time.store(0, Ordering::Relaxed);
+    self.number_of_windows.store(0, Ordering::Relaxed);
+    self.min_time.store(u64::MAX, Ordering::Relaxed);
+    self.max_time.store(0, Ordering::Relaxed);
+  }
+}
+
+#[test]
+fn test_atomic_stopwatch() {
+  let sw = AtomicStopwatch::new();
+  sw.add_window(3);
+  sw.add_window(7);
+  let stopwatch = sw.to_stopwatch();
+  assert_eq!(stopwatch.total_time, 10);
+  assert_eq!(stopwatch.number_of_windows, 2);
+  assert_eq!(stopwatch.min_time, 3);
+  assert_eq!(stopwatch.max_time, 7);
+  assert_eq!(stopwatch.variance(), None);
+  sw.reset();
+  assert_eq!(sw.to_stopwatch().min_time(), None);
+}
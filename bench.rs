@@ -0,0 +1,113 @@
+//! Quick benchmarks, repeating a closure and summarizing its samples.
+
+use std::hint::black_box;
+use std::time::Duration;
+
+use {DurationFormat, Reporter, TimerSet};
+
+#[derive(Debug, Clone, PartialEq)]
+/// What `TimerSet::bench` measured. Everything but `iterations` and
+/// `outliers` leaves the outliers out.
+pub struct BenchResult {
+  /// The name the samples were logged under.
+  pub name: String,
+  /// The number of times the closure ran.
+  pub iterations: u64,
+  /// The number of samples rejected as outliers, for being more than 1.5
+  /// interquartile ranges outside the middle half of the samples.
+  pub outliers: u64,
+  /// The mean sample.
+  pub mean: Duration,
+  /// The median sample.
+  pub median: Duration,
+  /// The shortest sample.
+  pub min: Duration,
+  /// The longest sample.
+  pub max: Duration,
+}
+
+impl BenchResult {
+  /// Sends a one-line summary to `reporter`.
+  pub fn report(&self, reporter: &dyn Reporter) {
+    let format = DurationFormat::Adaptive;
+    let ns = |d: Duration| format.format(d.as_nanos() as u64);
+    reporter.line(&format!(
+      "{}: mean {}, median {}, min {}, max {} ({} iterations, {} outliers)",
+      self.name,
+      ns(self.mean),
+      ns(self.median),
+      ns(self.min),
+      ns(self.max),
+      self.iterations,
+      self.outliers
+    ));
+  }
+}
+
+/// The sample a fraction `q` of the way through `sorted`, interpolating
+/// between its neighbours.
+fn quantile(sorted: &[u64], q: f64) -> f64 {
+  let rank = q * (sorted.len() - 1) as f64;
+  let (lo, hi) = (sorted[rank.floor() as usize], sorted[rank.ceil() as usize]);
+  lo as f64 + (hi as f64 - lo as f64) * rank.fract()
+}
+
+impl<K> TimerSet<K> {
+  /// Runs `f` `iterations` times, logging each run under the timer with the
+  /// given name, and summarizes the runs with their outliers left out. This
+  /// is meant for quick benchmarks; it doesn't warm up or pick an iteration
+  /// count by itself.
+  ///
+  /// Runs are measured even if timing is turned off, but then aren't
+  /// logged. Panics if `iterations` is zero.
+  pub fn bench<T, F: FnMut() -> T>(&self, name: &str, iterations: u64, mut f: F) -> BenchResult {
+    assert!(iterations > 0, "bench needs at least one iteration");
+    let mut samples: Vec<u64> =
+      (0..iterations)
+        .map(|_| {
+          let (ret, dt) = self.time_with_result(name, &mut f);
+          black_box(ret);
+          dt.as_nanos() as u64
+        })
+        .collect();
+    samples.sort();
+
+    let (q1, q3) = (quantile(&samples, 0.25), quantile(&samples, 0.75));
+    let fence = 1.5 * (q3 - q1);
+    let kept: Vec<u64> =
+      samples
+        .iter()
+        .cloned()
+        .filter(|&dt| dt as f64 >= q1 - fence && dt as f64 <= q3 + fence)
+        .collect();
+    BenchResult {
+      name: name.to_string(),
+      iterations,
+      outliers: iterations - kept.len() as u64,
+      mean: Duration::from_nanos(kept.iter().sum::<u64>() / kept.len() as u64),
+      median: Duration::from_nanos(quantile(&kept, 0.5).round() as u64),
+      min: Duration::from_nanos(kept[0]),
+      max: Duration::from_nanos(kept[kept.len() - 1]),
+    }
+  }
+}
+
+#[cfg(not(feature = "disable"))]
+#[test]
+fn test_bench() {
+  let ts = TimerSet::new();
+  let mut i = 0;
+  let result = ts.bench("sleepy", 20, || {
+    i += 1;
+    // One run takes far longer than the rest.
+    if i == 7 {
+      ::std::thread::sleep(Duration::from_millis(20));
+    }
+  });
+  assert_eq!(result.iterations, 20);
+  assert!(result.outliers >= 1);
+  assert!(result.max < Duration::from_millis(20));
+  assert!(result.min <= result.median && result.median <= result.max);
+  assert_eq!(ts.merged()["sleepy"].number_of_windows, 20);
+  assert_eq!(quantile(&[1, 2, 3, 4], 0.5), 2.5);
+}
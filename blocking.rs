@@ -0,0 +1,65 @@
+//! Timing how long threads block on locks and channels, with the
+//! thread-local `TimerSet`, to find contention.
+
+use std::sync::mpsc::{Receiver, RecvError};
+use std::sync::{Condvar, LockResult, Mutex, MutexGuard, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use time;
+
+/// Locks `mutex`, timing how long that blocked for under `name`.
+pub fn timed_lock<'a, T: ?Sized>(mutex: &'a Mutex<T>, name: &str) -> LockResult<MutexGuard<'a, T>> {
+  time(name, || mutex.lock())
+}
+
+/// Locks `lock` for reading, timing how long that blocked for under `name`.
+pub fn timed_read<'a, T: ?Sized>(lock: &'a RwLock<T>, name: &str) -> LockResult<RwLockReadGuard<'a, T>> {
+  time(name, || lock.read())
+}
+
+/// Locks `lock` for writing, timing how long that blocked for under `name`.
+pub fn timed_write<'a, T: ?Sized>(lock: &'a RwLock<T>, name: &str) -> LockResult<RwLockWriteGuard<'a, T>> {
+  time(name, || lock.write())
+}
+
+/// Waits on `condvar`, timing how long until it's woken up and has the lock
+/// back under `name`.
+pub fn timed_wait<'a, T>(condvar: &Condvar, guard: MutexGuard<'a, T>, name: &str) -> LockResult<MutexGuard<'a, T>> {
+  time(name, || condvar.wait(guard))
+}
+
+/// Receives from `receiver`, timing how long that blocked for under `name`.
+pub fn timed_recv<T>(receiver: &Receiver<T>, name: &str) -> Result<T, RecvError> {
+  time(name, || receiver.recv())
+}
+
+#[cfg(not(feature = "disable"))]
+#[test]
+fn test_timed_blocking() {
+  use std::sync::mpsc::channel;
+  use std::time::Duration;
+
+  let mutex = Mutex::new(1);
+  let lock = RwLock::new(2);
+  let (sender, receiver) = channel();
+  // On a thread of its own, to start with an empty thread-local set.
+  let (mutex, lock) = (&mutex, &lock);
+  ::std::thread::scope(|s| {
+    s.spawn(move || {
+      *timed_lock(mutex, "test_blocking.lock").unwrap() += 1;
+      assert_eq!(*timed_read(lock, "test_blocking.read").unwrap(), 2);
+      *timed_write(lock, "test_blocking.write").unwrap() += 1;
+      s.spawn(move || {
+        ::std::thread::sleep(Duration::from_millis(5));
+        sender.send(3).unwrap();
+      });
+      assert_eq!(timed_recv(&receiver, "test_blocking.recv"), Ok(3));
+      assert!(timed_recv(&receiver, "test_blocking.recv").is_err());
+
+      let merged = ::clone().merged();
+      assert_eq!(merged["test_blocking.lock"].number_of_windows, 1);
+      assert_eq!(merged["test_blocking.recv"].number_of_windows, 2);
+      assert!(merged["test_blocking.recv"].max_time().unwrap() >= Duration::from_millis(5));
+    });
+  });
+  assert_eq!(*mutex.lock().unwrap(), 2);
+}
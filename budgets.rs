@@ -0,0 +1,70 @@
+//! Comparing timers against an expected budget in reports.
+
+use std::time::Duration;
+
+use {lock, to_nanos, Stopwatch, TimerSet};
+
+impl<K> TimerSet<K> {
+  /// Sets how long a window of the named timer is expected to take on
+  /// average, e.g. 16ms for a frame at 60fps. Reports then follow the
+  /// timer's stats with its budget and whether its mean window was over or
+  /// under it, e.g. `budget 16.0ms [OVER by 2.1ms]`. `name` is the full
+  /// name the timer reports under. Budgets can be set and cleared while
+  /// the set is in use, e.g. through an `Arc`.
+  pub fn set_budget(&self, name: &str, budget: Duration) {
+    lock(&self.budgets).insert(name.to_string(), to_nanos(budget));
+  }
+
+  /// Stops comparing the named timer against a budget in reports.
+  pub fn clear_budget(&self, name: &str) {
+    lock(&self.budgets).remove(name);
+  }
+
+  /// Describes how the named timer's mean window compares with its budget,
+  /// for a report, or nothing if it hasn't got one.
+  pub(crate) fn budget_note(&self, name: &str, timer: &Stopwatch) -> String {
+    let budget =
+      match lock(&self.budgets).get(name) {
+        Some(&budget) if timer.number_of_windows > 0 => budget,
+        _ => return String::new(),
+      };
+    let mean = timer.total_time / timer.number_of_windows;
+    if mean > budget {
+      format!(", budget {} [OVER by {}]", self.format.format(budget), self.format.format(mean - budget))
+    } else {
+      format!(", budget {} [under by {}]", self.format.format(budget), self.format.format(budget - mean))
+    }
+  }
+}
+
+#[cfg(not(feature = "disable"))]
+#[test]
+fn test_set_budget() {
+  use std::cell::RefCell;
+  use {DurationFormat, TimeUnit};
+
+  let mut ts = TimerSet::new();
+  ts.set_duration_format(DurationFormat::Fixed(TimeUnit::Millis));
+  ts.set_budget("frame", Duration::from_millis(16));
+  ts.set_budget("input", Duration::from_millis(2));
+  ts.record("frame", Duration::from_millis(14));
+  ts.record("frame", Duration::from_millis(22));
+  ts.record("input", Duration::from_millis(1));
+  ts.record("other", Duration::from_millis(1));
+
+  let lines = RefCell::new(Vec::new());
+  ts.report(&|line: &str| lines.borrow_mut().push(line.to_string()));
+  let lines = lines.into_inner();
+  assert!(lines[0].starts_with("frame: ") && lines[0].ends_with(", budget 16ms [OVER by 2ms]"), "{}", lines[0]);
+  assert!(lines[1].ends_with(", budget 2ms [under by 1ms]"), "{}", lines[1]);
+  assert!(!lines[2].contains("budget"), "{}", lines[2]);
+
+  ts.set_tree_report(true);
+  let ts = ::std::sync::Arc::new(ts);
+  ts.clear_budget("frame");
+  let lines = RefCell::new(Vec::new());
+  ts.report(&|line: &str| lines.borrow_mut().push(line.to_string()));
+  let lines = lines.into_inner();
+  assert!(!lines[0].contains("budget"), "{}", lines[0]);
+  assert!(lines[1].ends_with(", budget 2ms [under by 1ms]"), "{}", lines[1]);
+}
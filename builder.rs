@@ -0,0 +1,155 @@
+//! Configuring a `TimerSet` up front.
+
+use std::sync::Arc;
+
+use {Clock, InstantClock, SortOrder, StopwatchConfig, TimerSet};
+
+/// Builds a `TimerSet`, made by `TimerSet::builder`, e.g.
+///
+/// ```
+/// use stopwatch::{SortBy, SortOrder, TimerSet};
+///
+/// let timers = TimerSet::builder().histogram(false).max_timers(1000).report_order(SortOrder::descending(SortBy::TotalTime)).build();
+/// timers.time("a", || {});
+/// ```
+pub struct TimerSetBuilder {
+  config: StopwatchConfig,
+  clock: Arc<dyn Clock>,
+  sample_rate: u64,
+  max_timers: Option<usize>,
+  intern_names: bool,
+  order: SortOrder,
+}
+
+impl TimerSet {
+  /// Starts configuring a new set. Without any options, `build` makes the
+  /// same set as `TimerSet::new`.
+  pub fn builder() -> TimerSetBuilder {
+    TimerSetBuilder {
+      config: StopwatchConfig::default(),
+      clock: Arc::new(InstantClock::new()),
+      sample_rate: 1,
+      max_timers: None,
+      intern_names: true,
+      order: SortOrder::default(),
+    }
+  }
+}
+
+impl TimerSetBuilder {
+  /// Times windows with `clock`, as `TimerSet::set_clock` does.
+  pub fn clock<C: Clock + 'static>(mut self, clock: C) -> TimerSetBuilder {
+    self.clock = Arc::new(clock);
+    self
+  }
+
+  /// Whether timers keep a histogram, for percentiles.
+  pub fn histogram(mut self, histogram: bool) -> TimerSetBuilder {
+    self.config.histogram = histogram;
+    self
+  }
+
+  /// Keeps the stats specified by `config`.
+  pub fn config(mut self, config: StopwatchConfig) -> TimerSetBuilder {
+    self.config = config;
+    self
+  }
+
+  /// Only times one in every `n` calls of each timer that doesn't have a
+  /// rate of its own from `TimerSet::set_sample_rate`.
+  pub fn sample_rate(mut self, n: u64) -> TimerSetBuilder {
+    self.sample_rate = n.max(1);
+    self
+  }
+
+  /// Keeps at most `n` distinct timers, as `TimerSet::set_max_timers` does.
+  pub fn max_timers(mut self, n: usize) -> TimerSetBuilder {
+    self.max_timers = Some(n);
+    self
+  }
+
+  /// Whether to intern timers', counters' and gauges' names, so every
+  /// thread's shard, and every set, shares one copy of each. That's the
+  /// default. Without it, each shard copies a name the first time it's
+  /// used, which takes more memory but no process-wide lock.
+  pub fn intern_names(mut self, intern: bool) -> TimerSetBuilder {
+    self.intern_names = intern;
+    self
+  }
+
+  /// Lists timers in `order` in `report` and `print`.
+  pub fn report_order(mut self, order: SortOrder) -> TimerSetBuilder {
+    self.order = order;
+    self
+  }
+
+  /// Makes the set.
+  pub fn build(self) -> TimerSet {
+    self.build_keyed()
+  }
+
+  /// Makes a set keyed by `K`, to time with `TimerSet::time_key`.
+  pub fn build_keyed<K>(self) -> TimerSet<K> {
+    let mut set = TimerSet::from_shards(self.config, false, Vec::new());
+    set.clock = self.clock;
+    set.default_sample_rate = self.sample_rate;
+    set.max_timers = self.max_timers;
+    set.intern_names = self.intern_names;
+    set.order = self.order;
+    set
+  }
+}
+
+#[cfg(not(feature = "disable"))]
+#[test]
+fn test_builder() {
+  use std::cell::RefCell;
+  use SortBy;
+
+  let timers = TimerSet::builder()
+    .histogram(false)
+    .sample_rate(2)
+    .max_timers(2)
+    .report_order(SortOrder::descending(SortBy::Name))
+    .build();
+  for name in ["a", "b", "c", "d"].iter() {
+    timers.time(name, || {});
+    timers.time(name, || {});
+  }
+
+  let merged = timers.merged();
+  assert_eq!(merged.len(), 3);
+  assert_eq!(merged["a"].number_of_windows, 2);
+  assert_eq!(merged["a"].quantile(0.5), None);
+  assert_eq!(merged["__other"].number_of_windows, 4);
+
+  let lines = RefCell::new(Vec::new());
+  timers.report(&|line: &str| lines.borrow_mut().push(line.to_string()));
+  assert!(lines.borrow()[0].starts_with("b: "), "{:?}", lines.borrow());
+}
+
+#[cfg(not(feature = "disable"))]
+#[test]
+fn test_intern_names() {
+  use Name;
+
+  let name = String::from("test_intern_names");
+  let timers = TimerSet::builder().intern_names(false).build();
+  ::std::thread::scope(|s| {
+    for _ in 0..2 {
+      s.spawn(|| {
+        timers.time(&name, || {});
+        timers.count(&name, 1);
+      });
+    }
+  });
+  // Each thread's shard has a copy of its own.
+  let keys: Vec<Name> = ::lock(&timers.shards).iter().map(|shard| ::lock(shard).timers.get_key_value(name.as_str()).unwrap().0.clone()).collect();
+  match (&keys[0], &keys[1]) {
+    (Name::Copied(a), Name::Copied(b)) => assert!(!Arc::ptr_eq(a, b)),
+    keys => panic!("{:?}", keys),
+  }
+  assert_eq!(timers.merged()[name.as_str()].number_of_windows, 2);
+  assert_eq!(timers.counter(&name), 2);
+  assert!(matches!(TimerSet::builder().build().new_name(&name), Name::Interned(_)));
+}
@@ -0,0 +1,73 @@
+//! Capping how many distinct timers a set keeps, so a bug that makes up
+//! names, e.g. with an ID in them, can't grow it without bound.
+
+use std::sync::atomic::Ordering;
+use std::sync::PoisonError;
+
+use {lock, TimerSet};
+
+/// The timer that windows of timers past a set's cap are logged under.
+pub const OVERFLOW_TIMER: &str = "__other";
+
+impl<K> TimerSet<K> {
+  /// Keeps at most `max` distinct timers, or any number if it's `None`.
+  /// Once there are that many, windows of any other timer are logged under
+  /// `__other` instead, and a warning is logged the first time. Timers
+  /// merged in from other sets aren't counted.
+  pub fn set_max_timers(&mut self, max: Option<usize>) {
+    // Count the timers there already are.
+    let names =
+      match max {
+        None => Default::default(),
+        Some(_) => self.merged().into_keys().filter(|name| name != OVERFLOW_TIMER).map(|name| name.to_string()).collect(),
+      };
+    self.max_timers = max;
+    *self.timer_names.get_mut().unwrap_or_else(PoisonError::into_inner) = names;
+  }
+
+  /// Whether a new timer called `name` fits under the set's cap, counting
+  /// it if it does.
+  pub(crate) fn admit(&self, name: &str) -> bool {
+    let max =
+      match self.max_timers {
+        None => return true,
+        Some(max) => max,
+      };
+    let mut names = lock(&self.timer_names);
+    if names.contains(name) || (names.len() < max && names.insert(name.to_string())) {
+      return true;
+    }
+    if !self.overflowed.swap(true, Ordering::Relaxed) {
+      warn!("More than {} distinct timers; logging {:?}, and any others, under {:?}", max, name, OVERFLOW_TIMER);
+    }
+    false
+  }
+}
+
+#[cfg(not(feature = "disable"))]
+#[test]
+fn test_set_max_timers() {
+  let mut ts = TimerSet::new();
+  ts.time("a", || {});
+  ts.set_max_timers(Some(2));
+  for id in 0..10 {
+    ts.time(&format!("request.{}", id), || {});
+  }
+  ts.time("a", || {});
+
+  let merged = ts.merged();
+  assert_eq!(merged.len(), 3);
+  assert_eq!(merged["a"].number_of_windows, 2);
+  assert_eq!(merged["request.0"].number_of_windows, 1);
+  assert_eq!(merged[OVERFLOW_TIMER].number_of_windows, 9);
+  assert!(ts.overflowed.load(Ordering::Relaxed));
+
+  // Removing a timer makes room for another.
+  ts.remove("request.0");
+  ts.time("b", || {});
+  assert_eq!(ts.merged()["b"].number_of_windows, 1);
+
+  ts.set_max_timers(None);
+  ts.time("c", || {});
+  assert!(ts.merged().contains_key("c"));
+}
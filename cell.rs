@@ -0,0 +1,99 @@
+//! A full stopwatch behind a lock, for timing through a shared reference.
+
+use std::sync::Mutex;
+use std::time::Duration;
+
+#[cfg(not(feature = "disable"))]
+use Instant;
+use {lock, to_nanos, Stopwatch};
+
+/// A stopwatch that can time events through a shared reference, e.g. as a
+/// `static`, without a `TimerSet` or looking timers up by name:
+///
+/// ```
+/// use stopwatch::StopwatchCell;
+///
+/// static PARSE: StopwatchCell = StopwatchCell::new();
+///
+/// PARSE.timed(|| {});
+/// # if stopwatch::DISABLED { return; }
+/// assert_eq!(PARSE.get().number_of_windows, 1);
+/// ```
+///
+/// Unlike `AtomicStopwatch`, it keeps everything a `Stopwatch` does, the
+/// variance and histogram included, at the cost of a lock per window. The
+/// lock isn't held while timing.
+pub struct StopwatchCell {
+  /// The stopwatch, made by the first window, since `Stopwatch::new` can't
+  /// be used in a `static`.
+  stopwatch: Mutex<Option<Stopwatch>>,
+}
+
+impl Default for StopwatchCell {
+  fn default() -> StopwatchCell {
+    StopwatchCell::new()
+  }
+}
+
+impl StopwatchCell {
+  /// Creates a new stopwatch.
+  pub const fn new() -> StopwatchCell {
+    StopwatchCell {
+      stopwatch: Mutex::new(None),
+    }
+  }
+
+  #[inline]
+  /// Times a function, updating stats as necessary.
+  pub fn timed<T, F: FnOnce() -> T>(&self, event: F) -> T {
+    #[cfg(feature = "disable")]
+    {
+      event()
+    }
+    #[cfg(not(feature = "disable"))]
+    {
+      let then = Instant::now();
+      let ret = event();
+      self.add_window(to_nanos(then.elapsed()));
+      ret
+    }
+  }
+
+  /// Folds a window timed some other way into the stats.
+  pub fn record(&self, duration: Duration) {
+    self.add_window(to_nanos(duration));
+  }
+
+  /// Folds a single time window, in nanoseconds, into the stats.
+  fn add_window(&self, dt: u64) {
+    lock(&self.stopwatch).get_or_insert_with(Stopwatch::new).add_window(dt);
+  }
+
+  /// Copies the stats out into a plain `Stopwatch`.
+  pub fn get(&self) -> Stopwatch {
+    lock(&self.stopwatch).clone().unwrap_or_default()
+  }
+
+  /// Forgets every time window clocked so far.
+  pub fn reset(&self) {
+    *lock(&self.stopwatch) = None;
+  }
+}
+
+#[cfg(not(feature = "disable"))]
+#[test]
+fn test_stopwatch_cell() {
+  static CELL: StopwatchCell = StopwatchCell::new();
+  assert_eq!(CELL.get().number_of_windows, 0);
+  CELL.record(Duration::from_nanos(3));
+  ::std::thread::scope(|s| {
+    s.spawn(|| CELL.record(Duration::from_nanos(7)));
+  });
+  assert_eq!(CELL.timed(|| 5), 5);
+  let sw = CELL.get();
+  assert_eq!(sw.number_of_windows, 3);
+  assert_eq!(sw.min_time(), Some(Duration::from_nanos(3)));
+  assert!(sw.variance().is_some());
+  CELL.reset();
+  assert_eq!(CELL.get().number_of_windows, 0);
+}
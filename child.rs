@@ -0,0 +1,116 @@
+//! Handles that record into a set under a prefix, for handing to libraries.
+
+use std::borrow::Cow;
+use std::time::Duration;
+
+use {TimerGuard, TimerSet};
+
+#[derive(Clone)]
+/// Records into a `TimerSet` with every name prefixed, created by
+/// `TimerSet::child`.
+///
+/// A library can take one of these and time what it does, without knowing
+/// where the caller keeps its timers or what else they time.
+pub struct TimerSetHandle<'a> {
+  timers: &'a TimerSet,
+  prefix: String,
+}
+
+impl TimerSet {
+  /// Returns a handle that records into this set with every name prefixed by
+  /// `prefix` and a dot, e.g. `db.query` for `query` in a child `db`. Scopes
+  /// still apply, outside the prefix.
+  pub fn child(&self, prefix: &str) -> TimerSetHandle<'_> {
+    TimerSetHandle {
+      timers: self,
+      prefix: prefix.to_string(),
+    }
+  }
+}
+
+impl<'a> TimerSetHandle<'a> {
+  /// Returns the set this records into.
+  pub fn timers(&self) -> &'a TimerSet {
+    self.timers
+  }
+
+  /// Returns the prefix of every name this records under, without the
+  /// trailing dot.
+  pub fn prefix(&self) -> &str {
+    &self.prefix
+  }
+
+  /// Returns a handle that records into the same set, under `prefix` within
+  /// this handle's, e.g. `db.pool.wait` for `wait` in a child `pool` of `db`.
+  pub fn child(&self, prefix: &str) -> TimerSetHandle<'a> {
+    TimerSetHandle {
+      timers: self.timers,
+      prefix: self.name(prefix),
+    }
+  }
+
+  fn name(&self, name: &str) -> String {
+    format!("{}.{}", self.prefix, name)
+  }
+
+  /// Like `TimerSet::time`, under the prefixed name.
+  pub fn time<T, F: FnOnce() -> T>(&self, name: &str, f: F) -> T {
+    if !self.timers.is_enabled() {
+      return f();
+    }
+    self.timers.time(&self.name(name), f)
+  }
+
+  /// Like `TimerSet::record`, under the prefixed name.
+  pub fn record(&self, name: &str, d: Duration) {
+    if !self.timers.is_enabled() {
+      return;
+    }
+    self.timers.record(&self.name(name), d);
+  }
+
+  /// Like `TimerSet::start`, under the prefixed name.
+  pub fn start(&self, name: &str) -> TimerGuard<'a> {
+    self.timers.start_named(Cow::Owned(self.name(name)))
+  }
+
+  /// Like `TimerSet::count`, under the prefixed name.
+  pub fn count(&self, name: &str, n: u64) {
+    if !self.timers.is_enabled() {
+      return;
+    }
+    self.timers.count(&self.name(name), n);
+  }
+
+  /// Like `TimerSet::gauge`, under the prefixed name.
+  pub fn gauge(&self, name: &str, value: f64) {
+    if !self.timers.is_enabled() {
+      return;
+    }
+    self.timers.gauge(&self.name(name), value);
+  }
+}
+
+#[cfg(not(feature = "disable"))]
+#[test]
+fn test_child() {
+  // Stands in for a library that's handed somewhere to record.
+  fn query(timers: &TimerSetHandle) {
+    let _guard = timers.start("query");
+    timers.child("pool").record("wait", Duration::from_millis(1));
+    timers.count("rows", 3);
+  }
+
+  let ts = TimerSet::new();
+  let db = ts.child("db");
+  query(&db);
+  {
+    let _scope = ts.push_scope("app");
+    query(&db);
+  }
+  let mut names: Vec<_> = ts.merged().into_keys().collect();
+  names.sort();
+  assert_eq!(names, vec!["app.db.pool.wait", "app.db.query", "db.pool.wait", "db.query"]);
+  assert_eq!(ts.counter("db.rows"), 3);
+  assert_eq!(db.prefix(), "db");
+}
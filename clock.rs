@@ -0,0 +1,167 @@
+//! Pluggable sources of timestamps.
+
+#[cfg(all(feature = "cputime", any(unix, windows)))]
+use cputime;
+#[cfg(feature = "std")]
+use {to_nanos, Instant};
+#[cfg(all(feature = "std", any(target_arch = "x86", target_arch = "x86_64")))]
+use tsc;
+
+/// A source of timestamps for a `TimerSet` to time windows with.
+pub trait Clock: Send + Sync {
+  /// Reads the clock, in its own units.
+  fn now(&self) -> u64;
+  /// Converts the difference between two readings to nanoseconds.
+  fn to_nanos(&self, ticks: u64) -> u64;
+}
+
+#[cfg(feature = "std")]
+/// A clock backed by `std::time::Instant`, or by `performance.now()` in
+/// browsers with the `wasm` feature. This is the default.
+pub struct InstantClock {
+  epoch: Instant,
+}
+
+#[cfg(feature = "std")]
+impl Default for InstantClock {
+  fn default() -> InstantClock {
+    InstantClock::new()
+  }
+}
+
+#[cfg(feature = "std")]
+impl InstantClock {
+  /// Creates a new clock.
+  pub fn new() -> InstantClock {
+    InstantClock {
+      epoch: Instant::now(),
+    }
+  }
+}
+
+#[cfg(feature = "std")]
+impl Clock for InstantClock {
+  #[inline]
+  fn now(&self) -> u64 {
+    to_nanos(self.epoch.elapsed())
+  }
+
+  #[inline]
+  fn to_nanos(&self, ticks: u64) -> u64 {
+    ticks
+  }
+}
+
+#[cfg(all(feature = "std", any(target_arch = "x86", target_arch = "x86_64")))]
+/// A clock backed by the x86 time stamp counter, which is much cheaper to
+/// read than `Instant`. It assumes an invariant TSC, which is synchronized
+/// across cores and ticks at a constant rate.
+///
+/// The rate is measured once per process and cached, so converting ticks to
+/// nanoseconds is a single multiply.
+pub struct TscClock {
+  _private: (),
+}
+
+#[cfg(all(feature = "std", any(target_arch = "x86", target_arch = "x86_64")))]
+impl Default for TscClock {
+  fn default() -> TscClock {
+    TscClock::new()
+  }
+}
+
+#[cfg(all(feature = "std", any(target_arch = "x86", target_arch = "x86_64")))]
+impl TscClock {
+  /// Creates a new clock, measuring the rate the TSC ticks at if that
+  /// hasn't been done yet.
+  pub fn new() -> TscClock {
+    tsc::calibrated_ticks_per_second();
+    TscClock {
+      _private: (),
+    }
+  }
+
+  /// Returns the measured rate the TSC ticks at, unrounded, as readings are
+  /// converted with it.
+  pub fn ticks_per_second() -> u64 {
+    tsc::calibrated_ticks_per_second()
+  }
+
+  /// Measures the rate the TSC ticks at again, e.g. after a frequency
+  /// change, and returns it. This affects every `TscClock`.
+  pub fn recalibrate() -> u64 {
+    tsc::recalibrate()
+  }
+}
+
+#[cfg(all(feature = "std", any(target_arch = "x86", target_arch = "x86_64")))]
+impl Clock for TscClock {
+  #[inline]
+  fn now(&self) -> u64 {
+    tsc::read()
+  }
+
+  #[inline]
+  fn to_nanos(&self, ticks: u64) -> u64 {
+    tsc::to_ns(ticks)
+  }
+}
+
+#[cfg(all(feature = "cputime", any(unix, windows)))]
+#[derive(Debug, Default, Copy, Clone)]
+/// A clock that reads the CPU time the calling thread has used, so windows
+/// leave out time the thread spent preempted, blocked or asleep. Each window
+/// has to end on the thread it started on, so this doesn't suit futures that
+/// move between threads.
+pub struct ThreadCpuClock;
+
+#[cfg(all(feature = "cputime", any(unix, windows)))]
+impl Clock for ThreadCpuClock {
+  #[inline]
+  fn now(&self) -> u64 {
+    cputime::thread()
+  }
+
+  #[inline]
+  fn to_nanos(&self, ticks: u64) -> u64 {
+    ticks
+  }
+}
+
+#[cfg(all(feature = "cputime", any(unix, windows)))]
+#[derive(Debug, Default, Copy, Clone)]
+/// A clock that reads the CPU time every thread in the process has used.
+pub struct ProcessCpuClock;
+
+#[cfg(all(feature = "cputime", any(unix, windows)))]
+impl Clock for ProcessCpuClock {
+  #[inline]
+  fn now(&self) -> u64 {
+    cputime::process()
+  }
+
+  #[inline]
+  fn to_nanos(&self, ticks: u64) -> u64 {
+    ticks
+  }
+}
+
+#[cfg(all(feature = "std", any(target_arch = "x86", target_arch = "x86_64")))]
+/// The cheapest clock available on this platform: `TscClock` on x86, and
+/// `InstantClock` elsewhere.
+pub type FastClock = TscClock;
+
+#[cfg(all(feature = "std", not(any(target_arch = "x86", target_arch = "x86_64"))))]
+/// The cheapest clock available on this platform: `TscClock` on x86, and
+/// `InstantClock` elsewhere.
+pub type FastClock = InstantClock;
+
+#[cfg(feature = "std")]
+#[test]
+fn test_fast_clock() {
+  let clock = FastClock::new();
+  let then = clock.now();
+  ::std::thread::sleep(::std::time::Duration::from_millis(2));
+  let dt = clock.to_nanos(clock.now() - then);
+  assert!(dt >= 1_000_000, "dt = {}", dt);
+}
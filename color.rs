@@ -0,0 +1,117 @@
+//! A colorized report for terminals, with bars of each timer's total.
+
+use {Reporter, TimerSet};
+
+const RED: &str = "\x1b[31m";
+const YELLOW: &str = "\x1b[33m";
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+/// How many characters wide a bar as long as the longest timer's is.
+const BAR_WIDTH: usize = 20;
+
+/// The blocks an eighth of a character wide up to seven eighths, to end a
+/// bar with.
+const PARTIAL_BLOCKS: [char; 7] = ['▏', '▎', '▍', '▌', '▋', '▊', '▉'];
+
+/// Draws a bar `fraction` of `BAR_WIDTH` long, to the nearest eighth of a
+/// character.
+fn bar(fraction: f64) -> String {
+  let eighths = (fraction.clamp(0.0, 1.0) * (BAR_WIDTH * 8) as f64).round() as usize;
+  let mut bar: String = ::std::iter::repeat_n('█', eighths / 8).collect();
+  if let Some(&block) = (eighths % 8).checked_sub(1).and_then(|i| PARTIAL_BLOCKS.get(i)) {
+    bar.push(block);
+  }
+  bar
+}
+
+/// Pads `s` with spaces to `width` characters, on the left if `right` is
+/// set.
+fn pad(s: &str, width: usize, right: bool) -> String {
+  let padding = " ".repeat(width.saturating_sub(s.chars().count()));
+  if right { padding + s } else { s.to_string() + &padding }
+}
+
+impl<K> TimerSet<K> {
+  /// Sends every timer that has run to `reporter` as a table for a
+  /// terminal, colored with ANSI escape codes, e.g. through
+  /// `WriteReporter::stdout()`. Timers are listed longest first, with their
+  /// count, total, mean and max in aligned columns and a bar of their total
+  /// time next to the longest's. The timer with the most total time is red
+  /// and the next two yellow.
+  pub fn report_colored(&self, reporter: &dyn Reporter) {
+    let mut timers = self.sorted_timers();
+    timers.sort_by(|a, b| b.1.total_time.cmp(&a.1.total_time).then_with(|| a.0.cmp(&b.0)));
+    let most = timers.first().map_or(0, |(_, sw)| sw.total_time);
+
+    let header = ["name", "count", "total", "mean", "max"];
+    let rows: Vec<[String; 5]> =
+      timers
+        .iter()
+        .map(|(name, sw)| {
+          [
+            name.clone(),
+            sw.number_of_windows.to_string(),
+            self.format.format(sw.total_time),
+            self.format.format(sw.total_time / sw.number_of_windows),
+            self.format.format(sw.max_time),
+          ]
+        })
+        .collect();
+    let mut widths = header.map(|column| column.chars().count());
+    for row in rows.iter() {
+      for (width, cell) in widths.iter_mut().zip(row.iter()) {
+        *width = (*width).max(cell.chars().count());
+      }
+    }
+    let line = |cells: &[&str]| -> String {
+      let cells: Vec<String> = cells.iter().zip(widths.iter()).enumerate().map(|(i, (cell, &width))| pad(cell, width, i > 0)).collect();
+      cells.join("  ")
+    };
+
+    reporter.line(&format!("{}{}{}", BOLD, line(&header), RESET));
+    for (i, (row, (_, sw))) in rows.iter().zip(timers.iter()).enumerate() {
+      let color =
+        match i {
+          0 => RED,
+          1 | 2 => YELLOW,
+          _ => "",
+        };
+      let cells: Vec<&str> = row.iter().map(|cell| cell.as_str()).collect();
+      let bar = bar(sw.total_time as f64 / most as f64);
+      let reset = if color.is_empty() { "" } else { RESET };
+      reporter.line(&format!("{}{}  {}{}", color, line(&cells), bar, reset));
+    }
+  }
+}
+
+#[test]
+fn test_bar() {
+  assert_eq!(bar(1.0), "█".repeat(BAR_WIDTH));
+  assert_eq!(bar(0.0), "");
+  assert_eq!(bar(0.5 / BAR_WIDTH as f64), "▌");
+  assert_eq!(bar(1.25 / BAR_WIDTH as f64), "█▎");
+}
+
+#[cfg(not(feature = "disable"))]
+#[test]
+fn test_report_colored() {
+  use std::cell::RefCell;
+  use std::time::Duration;
+  use {DurationFormat, TimeUnit};
+
+  let mut ts = TimerSet::new();
+  ts.set_duration_format(DurationFormat::Fixed(TimeUnit::Millis));
+  for (name, ms) in [("a", 1), ("bb", 2), ("c", 4), ("render", 8)].iter() {
+    ts.record(name, Duration::from_millis(*ms));
+  }
+  let lines = RefCell::new(Vec::new());
+  ts.report_colored(&|line: &str| lines.borrow_mut().push(line.to_string()));
+  assert_eq!(*lines.borrow(), vec![
+    format!("{}name    count  total  mean  max{}", BOLD, RESET),
+    format!("{}render      1    8ms   8ms  8ms  {}{}", RED, "█".repeat(20), RESET),
+    format!("{}c           1    4ms   4ms  4ms  {}{}", YELLOW, "█".repeat(10), RESET),
+    format!("{}bb          1    2ms   2ms  2ms  {}{}", YELLOW, "█".repeat(5), RESET),
+    "a           1    1ms   1ms  1ms  ██▌".to_string(),
+  ]);
+}
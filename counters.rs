@@ -0,0 +1,119 @@
+//! Counting events, e.g. cache hits, in the same set as the timers.
+
+use std::collections::HashMap;
+use std::hash::BuildHasherDefault;
+
+use fnv::FnvHasher;
+
+use {lock, Name, Report, Reporter, TimerSet};
+
+/// Counters keyed by name, interned like the timers' names.
+pub(crate) type Counters = HashMap<Name, u64, BuildHasherDefault<FnvHasher>>;
+
+pub(crate) fn new_counters() -> Counters {
+  HashMap::with_hasher(BuildHasherDefault::<FnvHasher>::default())
+}
+
+/// Adds every counter in `others` to `counters`.
+pub(crate) fn merge_counters(counters: &mut Counters, others: &Counters) {
+  for (name, n) in others.iter() {
+    *counters.entry(name.clone()).or_insert(0) += n;
+  }
+}
+
+impl<K> TimerSet<K> {
+  /// Adds `n` to the counter with the given name, creating it if need be.
+  /// Counters are listed after the timers in reports. The name is scoped
+  /// the same way as `time`'s, but never nested.
+  pub fn count(&self, name: &str, n: u64) {
+    if !self.is_enabled() {
+      return;
+    }
+    let name = self.scoped(name);
+    self.with_shard_timers(|shard| {
+      match shard.counters.get_mut(name.as_ref()) {
+        Some(count) => *count += n,
+        None => {
+          shard.counters.insert(self.new_name(&name), n);
+        },
+      }
+    });
+  }
+
+  /// Returns the named counter's total, or zero if it was never counted.
+  pub fn counter(&self, name: &str) -> u64 {
+    lock(&self.shards).iter().map(|shard| lock(shard).counters.get(name).cloned().unwrap_or(0)).sum()
+  }
+
+  /// Returns every counter's total, sorted by name.
+  pub fn counters(&self) -> Vec<(String, u64)> {
+    let mut counters: Vec<(String, u64)> =
+      self.merged_counters()
+        .into_iter()
+        .map(|(name, n)| (name.to_string(), n))
+        .collect();
+    counters.sort();
+    counters
+  }
+
+  /// Adds up every thread's counters.
+  pub(crate) fn merged_counters(&self) -> Counters {
+    let mut merged = new_counters();
+    for shard in lock(&self.shards).iter() {
+      merge_counters(&mut merged, &lock(shard).counters);
+    }
+    merged
+  }
+
+  /// Adds up every thread's counters, and zeroes them.
+  pub(crate) fn take_counters(&self) -> Counters {
+    let mut taken = new_counters();
+    for shard in lock(&self.shards).iter() {
+      merge_counters(&mut taken, &::std::mem::take(&mut lock(shard).counters));
+    }
+    taken
+  }
+
+  /// Sends a line per counter `report` picks to `reporter`, sorted by name.
+  pub(crate) fn report_counters(&self, reporter: &dyn Reporter, report: &Report) {
+    for (name, n) in self.counters().into_iter().filter(|(name, _)| report.matches(name)) {
+      reporter.line(&format!("{}: {}", name, n));
+    }
+  }
+}
+
+#[cfg(not(feature = "disable"))]
+#[test]
+fn test_count() {
+  let ts = TimerSet::new();
+  ts.time("a", || ts.count("hits", 2));
+  ::std::thread::scope(|s| {
+    s.spawn(|| ts.count("hits", 3));
+  });
+  {
+    let _scope = ts.push_scope("cache");
+    ts.count("misses", 1);
+  }
+  assert_eq!(ts.counter("hits"), 5);
+
+  // Both threads' shards key the counter by the same copy of its name.
+  let keys: Vec<Name> = lock(&ts.shards).iter().map(|shard| lock(shard).counters.get_key_value("hits").unwrap().0.clone()).collect();
+  match (&keys[0], &keys[1]) {
+    (Name::Interned(a), Name::Interned(b)) => assert!(::std::sync::Arc::ptr_eq(a, b)),
+    keys => panic!("{:?}", keys),
+  }
+  assert_eq!(ts.counter("nope"), 0);
+  assert_eq!(ts.counters(), vec![("cache.misses".to_string(), 1), ("hits".to_string(), 5)]);
+
+  let lines = ::std::cell::RefCell::new(Vec::new());
+  ts.report(&|line: &str| lines.borrow_mut().push(line.to_string()));
+  assert_eq!(&lines.borrow()[1..], &["cache.misses: 1", "hits: 5"]);
+  assert!(::clone().counters().is_empty());
+  let mut taken: Vec<(String, u64)> = ts.take_counters().into_iter().map(|(name, n)| (name.to_string(), n)).collect();
+  taken.sort();
+  assert_eq!(taken, vec![("cache.misses".to_string(), 1), ("hits".to_string(), 5)]);
+  assert_eq!(ts.counters(), vec![]);
+  assert_eq!(ts.counter("hits"), 0);
+  ts.count("hits", 1);
+  assert_eq!(ts.counters(), vec![("hits".to_string(), 1)]);
+}
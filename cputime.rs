@@ -0,0 +1,76 @@
+//! Reading the CPU time used by the calling thread, or by the whole process.
+
+#[cfg(unix)]
+use libc;
+#[cfg(windows)]
+use windows_sys::Win32::Foundation::FILETIME;
+#[cfg(windows)]
+use windows_sys::Win32::System::Threading::{GetCurrentProcess, GetCurrentThread, GetProcessTimes, GetThreadTimes};
+
+/// Reads a POSIX CPU-time clock, in nanoseconds.
+#[cfg(unix)]
+fn read(clock: libc::clockid_t) -> u64 {
+  let mut ts: libc::timespec = unsafe { ::std::mem::zeroed() };
+  // This can only fail for an invalid clock.
+  let ret = unsafe { libc::clock_gettime(clock, &mut ts) };
+  debug_assert_eq!(ret, 0);
+  (ts.tv_sec as u64).saturating_mul(1_000_000_000).saturating_add(ts.tv_nsec as u64)
+}
+
+/// Returns the CPU time the calling thread has used, in nanoseconds.
+#[cfg(unix)]
+pub fn thread() -> u64 {
+  read(libc::CLOCK_THREAD_CPUTIME_ID)
+}
+
+/// Returns the CPU time every thread in the process has used, in nanoseconds.
+#[cfg(unix)]
+pub fn process() -> u64 {
+  read(libc::CLOCK_PROCESS_CPUTIME_ID)
+}
+
+/// Converts a `FILETIME` span, in 100ns units, to nanoseconds.
+#[cfg(windows)]
+fn nanos(t: FILETIME) -> u64 {
+  ((u64::from(t.dwHighDateTime) << 32) | u64::from(t.dwLowDateTime)) * 100
+}
+
+/// Returns the user and kernel time reported by `GetThreadTimes` or
+/// `GetProcessTimes`, in nanoseconds.
+#[cfg(windows)]
+fn read<F: FnOnce(*mut FILETIME, *mut FILETIME, *mut FILETIME, *mut FILETIME) -> i32>(get_times: F) -> u64 {
+  let zero = FILETIME {
+    dwLowDateTime: 0,
+    dwHighDateTime: 0,
+  };
+  let (mut creation, mut exit, mut kernel, mut user) = (zero, zero, zero, zero);
+  let ret = get_times(&mut creation, &mut exit, &mut kernel, &mut user);
+  debug_assert!(ret != 0);
+  nanos(kernel) + nanos(user)
+}
+
+/// Returns the CPU time the calling thread has used, in nanoseconds.
+#[cfg(windows)]
+pub fn thread() -> u64 {
+  read(|c, e, k, u| unsafe { GetThreadTimes(GetCurrentThread(), c, e, k, u) })
+}
+
+/// Returns the CPU time every thread in the process has used, in nanoseconds.
+#[cfg(windows)]
+pub fn process() -> u64 {
+  read(|c, e, k, u| unsafe { GetProcessTimes(GetCurrentProcess(), c, e, k, u) })
+}
+
+#[test]
+fn test_thread_excludes_sleep() {
+  let then = thread();
+  ::std::thread::sleep(::std::time::Duration::from_millis(20));
+  let mut x = 0u64;
+  for i in 0..1_000_000 {
+    x = x.wrapping_add(::std::hint::black_box(i));
+  }
+  ::std::hint::black_box(x);
+  let dt = thread() - then;
+  assert!(dt > 0 && dt < 20_000_000, "dt = {}", dt);
+  assert!(process() >= dt);
+}
@@ -0,0 +1,60 @@
+//! CSV export of timer statistics.
+
+use std::io::{self, Write};
+
+use {to_nanos, TimerSet};
+
+/// Quotes a CSV field if it needs it.
+fn escape(field: &str) -> String {
+  if field.contains(&[',', '"', '\n', '\r'][..]) {
+    format!("\"{}\"", field.replace('"', "\"\""))
+  } else {
+    field.to_string()
+  }
+}
+
+impl<K> TimerSet<K> {
+  /// Writes every timer's statistics as CSV, one row per timer sorted by
+  /// name, after a header row. Durations are in whole nanoseconds, so fast
+  /// timers can still be compared between builds.
+  pub fn write_csv<W: Write>(&self, mut w: W) -> io::Result<()> {
+    writeln!(w, "name,total_ns,count,mean_ns,min_ns,max_ns,violations,self_ns")?;
+    for (name, sw) in self.sorted_timers().iter() {
+      writeln!(
+        w,
+        "{},{},{},{},{},{},{},{}",
+        escape(name),
+        sw.total_time,
+        sw.number_of_windows,
+        sw.total_time / sw.number_of_windows,
+        sw.min_time,
+        sw.max_time,
+        sw.violations,
+        to_nanos(sw.self_time())
+      )?;
+    }
+    Ok(())
+  }
+}
+
+#[cfg(not(feature = "disable"))]
+#[test]
+fn test_write_csv() {
+  let ts = TimerSet::new();
+  ts.time("hello, \"world\"", || {});
+  let mut out = Vec::new();
+  ts.write_csv(&mut out).unwrap();
+  let out = String::from_utf8(out).unwrap();
+  let lines: Vec<&str> = out.lines().collect();
+  assert_eq!(lines.len(), 2);
+  assert_eq!(lines[0], "name,total_ns,count,mean_ns,min_ns,max_ns,violations,self_ns");
+  assert!(lines[1].starts_with("\"hello, \"\"world\"\"\","));
+
+  // Sub-microsecond timers keep their resolution.
+  let ts = TimerSet::new();
+  ts.record("fast", ::std::time::Duration::from_nanos(250));
+  ts.record("fast", ::std::time::Duration::from_nanos(750));
+  let mut out = Vec::new();
+  ts.write_csv(&mut out).unwrap();
+  assert_eq!(String::from_utf8(out).unwrap().lines().nth(1), Some("fast,1000,2,500,250,750,0,1000"));
+}
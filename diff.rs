@@ -0,0 +1,163 @@
+//! Comparing snapshots, e.g. of two runs, to catch regressions.
+
+use std::time::Duration;
+
+use {to_nanos, DurationFormat, Reporter, Stopwatch, TimerSetSnapshot};
+
+/// How much slower, in percent, a timer's average has to get for `diff` to
+/// flag it as a regression.
+pub const DEFAULT_REGRESSION_THRESHOLD: f64 = 10.0;
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+/// How one timer changed between two snapshots.
+pub struct TimerDiff {
+  /// The timer's name.
+  pub name: String,
+  /// The timer's total time in the baseline, if it ran there.
+  pub baseline_total: Option<Duration>,
+  /// The timer's total time now, if it ran.
+  pub current_total: Option<Duration>,
+  /// The timer's mean window in the baseline, if it ran there.
+  pub baseline_avg: Option<Duration>,
+  /// The timer's mean window now, if it ran.
+  pub current_avg: Option<Duration>,
+  /// The percentage change in total time, if the timer ran in both.
+  pub total_change_percent: Option<f64>,
+  /// The percentage change in the mean window, if the timer ran in both.
+  pub avg_change_percent: Option<f64>,
+  /// Whether the mean window grew by more than the threshold.
+  pub regressed: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+/// Every timer's changes between two snapshots, sorted by name.
+pub struct DiffReport {
+  /// The percentage growth in a mean window counted as a regression.
+  pub threshold_percent: f64,
+  /// Each timer that ran in either snapshot.
+  pub timers: Vec<TimerDiff>,
+}
+
+/// A timer's total and mean window, if it ran.
+fn totals(sw: Option<&Stopwatch>) -> (Option<Duration>, Option<Duration>) {
+  match sw {
+    Some(sw) if sw.number_of_windows > 0 =>
+      (Some(Duration::from_nanos(sw.total_time)), Some(Duration::from_nanos(sw.total_time / sw.number_of_windows))),
+    _ => (None, None),
+  }
+}
+
+/// The percentage change from `before` to `after`, if both are known and
+/// `before` isn't zero.
+fn change_percent(before: Option<Duration>, after: Option<Duration>) -> Option<f64> {
+  match (before.map(to_nanos), after.map(to_nanos)) {
+    (Some(before), Some(after)) if before > 0 => Some(100.0 * (after as f64 - before as f64) / before as f64),
+    _ => None,
+  }
+}
+
+impl TimerSetSnapshot {
+  /// Compares this snapshot to `baseline`, flagging timers whose mean window
+  /// grew by more than `DEFAULT_REGRESSION_THRESHOLD` percent.
+  pub fn diff(&self, baseline: &TimerSetSnapshot) -> DiffReport {
+    self.diff_with_threshold(baseline, DEFAULT_REGRESSION_THRESHOLD)
+  }
+
+  /// Like `diff`, but flags timers whose mean window grew by more than
+  /// `threshold_percent` percent.
+  pub fn diff_with_threshold(&self, baseline: &TimerSetSnapshot, threshold_percent: f64) -> DiffReport {
+    let mut names: Vec<&String> = self.timers.keys().chain(baseline.timers.keys()).collect();
+    names.sort();
+    names.dedup();
+    let timers =
+      names
+        .into_iter()
+        .filter_map(|name| {
+          let (baseline_total, baseline_avg) = totals(baseline.get(name));
+          let (current_total, current_avg) = totals(self.get(name));
+          if baseline_total.is_none() && current_total.is_none() {
+            return None;
+          }
+          let avg_change_percent = change_percent(baseline_avg, current_avg);
+          Some(TimerDiff {
+            name: name.clone(),
+            baseline_total,
+            current_total,
+            baseline_avg,
+            current_avg,
+            total_change_percent: change_percent(baseline_total, current_total),
+            avg_change_percent,
+            regressed: avg_change_percent.is_some_and(|change| change > threshold_percent),
+          })
+        })
+        .collect();
+    DiffReport {
+      threshold_percent,
+      timers,
+    }
+  }
+}
+
+impl DiffReport {
+  /// Returns the timers that regressed.
+  pub fn regressions(&self) -> Vec<&TimerDiff> {
+    self.timers.iter().filter(|t| t.regressed).collect()
+  }
+
+  /// Sends the report to `reporter`, one line per timer.
+  pub fn report(&self, reporter: &dyn Reporter) {
+    let format = DurationFormat::Adaptive;
+    let ns = |d: Option<Duration>| format.format(d.map_or(0, to_nanos));
+    let percent = |p: Option<f64>| p.map_or(String::new(), |p| format!(" ({:+.1}%)", p));
+    for t in self.timers.iter() {
+      let line =
+        match (t.baseline_total, t.current_total) {
+          (None, _) => format!("{}: new (avg {}, total {})", t.name, ns(t.current_avg), ns(t.current_total)),
+          (_, None) => format!("{}: removed", t.name),
+          _ =>
+            format!(
+              "{}: avg {} -> {}{}, total {} -> {}{}{}",
+              t.name,
+              ns(t.baseline_avg),
+              ns(t.current_avg),
+              percent(t.avg_change_percent),
+              ns(t.baseline_total),
+              ns(t.current_total),
+              percent(t.total_change_percent),
+              if t.regressed { " REGRESSED" } else { "" }
+            ),
+        };
+      reporter.line(&line);
+    }
+  }
+}
+
+#[test]
+fn test_diff() {
+  let snapshot = |windows: &[(&str, u64)]| {
+    let mut snapshot = TimerSetSnapshot::default();
+    for &(name, dt) in windows {
+      snapshot.timers.entry(name.to_string()).or_default().add_window(dt);
+    }
+    snapshot
+  };
+  let baseline = snapshot(&[("same", 1_000), ("slower", 1_000), ("gone", 1_000)]);
+  let current = snapshot(&[("same", 1_050), ("slower", 2_000), ("slower", 2_000), ("new", 1_000)]);
+
+  let report = current.diff(&baseline);
+  let names: Vec<&str> = report.timers.iter().map(|t| t.name.as_ref()).collect();
+  assert_eq!(names, vec!["gone", "new", "same", "slower"]);
+  let slower = &report.timers[3];
+  assert_eq!(slower.avg_change_percent, Some(100.0));
+  assert_eq!(slower.total_change_percent, Some(300.0));
+  assert_eq!(report.regressions(), vec![slower]);
+  assert!(current.diff_with_threshold(&baseline, 1.0).timers[2].regressed);
+
+  let lines = ::std::cell::RefCell::new(Vec::new());
+  report.report(&|line: &str| lines.borrow_mut().push(line.to_string()));
+  let lines = lines.into_inner();
+  assert_eq!(lines[0], "gone: removed");
+  assert!(lines[3].ends_with("(+300.0%) REGRESSED"), "{}", lines[3]);
+}
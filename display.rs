@@ -0,0 +1,63 @@
+//! Rendering stopwatches with `{}`, the same way reports do.
+
+use std::fmt;
+
+use {DurationFormat, Stopwatch};
+
+/// Renders the stopwatch's stats as `print` does, without the name, e.g.
+/// `3.0ms over 2 samples (avg 1.5ms, ...)`, or `never ran`.
+impl fmt::Display for Stopwatch {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    if self.number_of_windows == 0 {
+      f.write_str("never ran")
+    } else {
+      f.write_str(&self.summary(DurationFormat::default()))
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy)]
+/// A stopwatch with a name, which renders with `{}` as a line of `print`
+/// does, e.g. `parse: 3.0ms over 2 samples (...)`. Made by
+/// `Stopwatch::named`.
+pub struct NamedStopwatch<'a> {
+  /// The name to render the stopwatch under.
+  pub name: &'a str,
+  /// The stopwatch.
+  pub stopwatch: &'a Stopwatch,
+}
+
+impl Stopwatch {
+  /// Pairs this stopwatch with a name to render it under.
+  pub fn named<'a>(&'a self, name: &'a str) -> NamedStopwatch<'a> {
+    NamedStopwatch {
+      name,
+      stopwatch: self,
+    }
+  }
+}
+
+impl<'a> fmt::Display for NamedStopwatch<'a> {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    if self.stopwatch.number_of_windows == 0 {
+      write!(f, "{} never ran", self.name)
+    } else {
+      write!(f, "{}: {}", self.name, self.stopwatch)
+    }
+  }
+}
+
+#[cfg(not(feature = "disable"))]
+#[test]
+fn test_display() {
+  use TimerSet;
+
+  let ts = TimerSet::new();
+  ts.time("a", || {});
+  let sw = &ts.merged()["a"];
+  let lines = ::std::cell::RefCell::new(Vec::new());
+  ts.report(&|line: &str| lines.borrow_mut().push(line.to_string()));
+  assert!(lines.borrow()[0].starts_with(&sw.named("a").to_string()), "{}", lines.borrow()[0]);
+  assert_eq!(format!("{}", sw), sw.summary(DurationFormat::Adaptive));
+  assert_eq!(Stopwatch::new().named("b").to_string(), "b never ran");
+}
@@ -0,0 +1,154 @@
+//! Configuring timing from an environment variable, like `env_logger`, so
+//! it can be turned on in a deployed binary without changing its code.
+
+use std::env;
+use std::str::FromStr;
+use std::time::Duration;
+
+use log::Level;
+
+use {report_threads_with, set_enabled, LogReporter, Report, ReporterHandle, SortBy, SortOrder};
+
+/// The environment variable `init_from_env` reads.
+pub const ENV_VAR: &str = "STOPWATCH";
+
+/// What `STOPWATCH` asks for.
+struct Settings {
+  /// Whether to turn timing on or off, if it says.
+  enabled: Option<bool>,
+  /// What to log periodic reports at.
+  level: Level,
+  /// How often to report, if at all.
+  interval: Option<Duration>,
+  report: Report,
+}
+
+/// Parses a duration like `500ms`, `30s`, `5m` or `1h`, or nothing if it
+/// overflows.
+fn parse_duration(s: &str) -> Option<Duration> {
+  let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+  let n: u64 = s[..split].parse().ok()?;
+  match &s[split..] {
+    "ms" => Some(Duration::from_millis(n)),
+    "s" | "" => Some(Duration::from_secs(n)),
+    "m" => n.checked_mul(60).map(Duration::from_secs),
+    "h" => n.checked_mul(3600).map(Duration::from_secs),
+    _ => None,
+  }
+}
+
+/// Parses the comma-separated settings in `spec`, warning about any that
+/// don't make sense and going without them.
+fn parse(spec: &str) -> Settings {
+  let mut settings = Settings {
+    enabled: None,
+    level: Level::Info,
+    interval: None,
+    report: Report::new(),
+  };
+  for setting in spec.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+    let (key, value) =
+      match setting.find('=') {
+        Some(i) => (&setting[..i], &setting[i + 1..]),
+        None => (setting, ""),
+      };
+    let ok =
+      match key {
+        "on" => {
+          settings.enabled = Some(true);
+          true
+        },
+        "off" => {
+          settings.enabled = Some(false);
+          true
+        },
+        "level" => Level::from_str(value).map(|level| settings.level = level).is_ok(),
+        // Reporting every instant would just spin.
+        "report_interval" => parse_duration(value).filter(|interval| !interval.is_zero()).map(|interval| settings.interval = Some(interval)).is_some(),
+        "sort" => {
+          let order =
+            match value {
+              "name" => Some(SortOrder::ascending(SortBy::Name)),
+              "total" => Some(SortOrder::descending(SortBy::TotalTime)),
+              "self" => Some(SortOrder::descending(SortBy::SelfTime)),
+              "avg" => Some(SortOrder::descending(SortBy::Average)),
+              "count" => Some(SortOrder::descending(SortBy::Count)),
+              _ => None,
+            };
+          order.map(|order| settings.report.order = order).is_some()
+        },
+        "top" => value.parse().map(|n| settings.report.top = Some(n)).is_ok(),
+        "filter" => {
+          let prefix = value.strip_suffix('*').unwrap_or(value);
+          settings.report = settings.report.clone().filter_prefix(prefix);
+          true
+        },
+        _ => false,
+      };
+    if !ok {
+      warn!("Ignoring {:?} in ${}", setting, ENV_VAR);
+    }
+  }
+  settings
+}
+
+/// Configures timing from the `STOPWATCH` environment variable, a
+/// comma-separated list of:
+///
+/// * `on` or `off`, to turn timing on or off everywhere, as `set_enabled`
+///   does.
+/// * `report_interval=30s`, to log every thread's report, as
+///   `report_threads` does, that often. Intervals are in `ms`, `s`, `m` or
+///   `h`.
+/// * `level=debug`, to log those reports at a level other than `info`.
+/// * `sort=total`, to list timers by `name`, or by `total`, `self`, `avg`
+///   or `count`, largest first.
+/// * `top=20`, to only list the timers with the most total time.
+/// * `filter=render.*`, to only report on timers starting with `render.`.
+///
+/// e.g. `STOPWATCH=on,report_interval=30s,filter=render.*`. Anything else is
+/// warned about and ignored. Nothing changes if the variable isn't set.
+///
+/// If reports were asked for, they're logged for as long as the returned
+/// handle is kept.
+pub fn init_from_env() -> Option<ReporterHandle> {
+  let spec = env::var(ENV_VAR).ok()?;
+  let settings = parse(&spec);
+  if let Some(enabled) = settings.enabled {
+    set_enabled(enabled);
+  }
+  let interval = settings.interval?;
+  let (reporter, report) = (LogReporter::new(settings.level), settings.report);
+  Some(ReporterHandle::spawn(interval, move || {
+    report_threads_with(&reporter, &report);
+    true
+  }))
+}
+
+#[test]
+fn test_parse() {
+  let settings = parse("on, level=debug,report_interval=30s,sort=total,top=5,filter=render.*,bogus,top=x");
+  assert_eq!(settings.enabled, Some(true));
+  assert_eq!(settings.level, Level::Debug);
+  assert_eq!(settings.interval, Some(Duration::from_secs(30)));
+  assert_eq!(settings.report.order, SortOrder::descending(SortBy::TotalTime));
+  assert_eq!(settings.report.top, Some(5));
+  assert!(settings.report.matches("render.frame"));
+  assert!(!settings.report.matches("update"));
+
+  let settings = parse("off");
+  assert_eq!(settings.enabled, Some(false));
+  assert_eq!(settings.interval, None);
+  assert_eq!(parse_duration("250ms"), Some(Duration::from_millis(250)));
+  assert_eq!(parse_duration("2m"), Some(Duration::from_secs(120)));
+  assert_eq!(parse_duration("2d"), None);
+  assert_eq!(parse_duration("5124095576030431h"), Some(Duration::from_secs(5124095576030431 * 3600)));
+  assert_eq!(parse_duration("5124095576030432h"), None);
+  assert_eq!(parse_duration("999999999999999999h"), None);
+  assert_eq!(parse_duration("999999999999999999m"), None);
+
+  assert_eq!(parse("report_interval=0s").interval, None);
+  assert_eq!(parse("report_interval=0ms").interval, None);
+  assert_eq!(parse("report_interval=999999999999999999h").interval, None);
+  assert_eq!(parse("report_interval=1ms").interval, Some(Duration::from_millis(1)));
+}
@@ -0,0 +1,88 @@
+//! Measures how much threads timing into one `TimerSet` at once slow each
+//! other down, next to a single `Stopwatch` behind a `Mutex` that every
+//! thread times into. For each number of threads up to the machine's, it
+//! prints the calls per second per thread as a share of one thread's: near
+//! 100% means the threads don't contend.
+//!
+//! With `--check`, it exits with an error if the set's share at the most
+//! threads falls below `MIN_EFFICIENCY`, so it can guard against contention
+//! creeping back in, on a machine with cores to spare.
+//!
+//! Run with `cargo run --release --example contention [-- --check]`.
+
+extern crate stopwatch;
+
+use std::env;
+use std::process;
+use std::sync::{Arc, Barrier, Mutex};
+use std::thread;
+use std::time::Instant;
+
+use stopwatch::{Stopwatch, TimerSet};
+
+const CALLS_PER_THREAD: u64 = 1_000_000;
+
+/// The least share of one thread's throughput each thread timing into a set
+/// should keep, for `--check`.
+const MIN_EFFICIENCY: f64 = 0.5;
+
+/// Runs `time` `CALLS_PER_THREAD` times on each of `threads` threads at
+/// once, and returns the calls per second each thread managed.
+fn calls_per_thread_per_sec<F: Fn(usize) + Send + Sync + 'static>(threads: usize, time: F) -> f64 {
+  let time = Arc::new(time);
+  let barrier = Arc::new(Barrier::new(threads + 1));
+  let handles: Vec<_> =
+    (0..threads)
+      .map(|i| {
+        let time = time.clone();
+        let barrier = barrier.clone();
+        thread::spawn(move || {
+          barrier.wait();
+          for _ in 0..CALLS_PER_THREAD {
+            time(i);
+          }
+        })
+      })
+      .collect();
+  barrier.wait();
+  let start = Instant::now();
+  for handle in handles {
+    handle.join().unwrap();
+  }
+  CALLS_PER_THREAD as f64 / start.elapsed().as_secs_f64()
+}
+
+/// Times into one set, each thread under its own timer.
+fn timer_set(threads: usize) -> f64 {
+  let ts = TimerSet::new();
+  let names: Vec<String> = (0..threads).map(|i| format!("thread{}", i)).collect();
+  calls_per_thread_per_sec(threads, move |i| ts.time(&names[i], || {}))
+}
+
+/// Times into one stopwatch every thread shares.
+fn shared_stopwatch(threads: usize) -> f64 {
+  let sw = Mutex::new(Stopwatch::new());
+  calls_per_thread_per_sec(threads, move |_| sw.lock().unwrap().timed(|| {}))
+}
+
+fn main() {
+  let check = env::args().any(|arg| arg == "--check");
+  let most = thread::available_parallelism().map_or(1, |n| n.get());
+  let (set_base, shared_base) = (timer_set(1), shared_stopwatch(1));
+
+  let mut threads = 1;
+  let efficiency = loop {
+    let efficiency = timer_set(threads) / set_base;
+    let shared = shared_stopwatch(threads) / shared_base;
+    println!("{:2} threads: TimerSet {:5.1}%, shared Stopwatch {:5.1}%", threads, efficiency * 100.0, shared * 100.0);
+    if threads == most {
+      break efficiency;
+    }
+    threads = (threads * 2).min(most);
+  };
+
+  if check && efficiency < MIN_EFFICIENCY {
+    eprintln!("Each of {} threads timing into one set only kept {:.1}% of one thread's throughput", most, efficiency * 100.0);
+    process::exit(1);
+  }
+}
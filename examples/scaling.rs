@@ -0,0 +1,46 @@
+//! Measures how `TimerSet::time` scales as more threads time into one set,
+//! each under its own timer name. The `contention` example compares it with
+//! timing under one shared lock.
+//!
+//! Run with `cargo run --release --example scaling`.
+
+extern crate stopwatch;
+
+use std::sync::{Arc, Barrier};
+use std::thread;
+use std::time::Instant;
+
+use stopwatch::TimerSet;
+
+const CALLS_PER_THREAD: u64 = 1_000_000;
+
+fn main() {
+  let mut threads = 1;
+  while threads <= 32 {
+    let ts = Arc::new(TimerSet::new());
+    let barrier = Arc::new(Barrier::new(threads + 1));
+    let handles: Vec<_> =
+      (0..threads)
+        .map(|i| {
+          let ts = ts.clone();
+          let barrier = barrier.clone();
+          thread::spawn(move || {
+            let name = format!("thread{}", i);
+            barrier.wait();
+            for _ in 0..CALLS_PER_THREAD {
+              ts.time(&name, || {});
+            }
+          })
+        })
+        .collect();
+    barrier.wait();
+    let start = Instant::now();
+    for handle in handles {
+      handle.join().unwrap();
+    }
+    let secs = start.elapsed().as_secs_f64();
+    let calls = threads as u64 * CALLS_PER_THREAD;
+    println!("{:2} threads: {:6.1}M calls/s", threads, calls as f64 / secs / 1e6);
+    threads *= 2;
+  }
+}
@@ -0,0 +1,66 @@
+//! Folded-stack export, for flame graphs.
+
+use std::io::{self, Write};
+
+use {Timers, TimerSet};
+
+/// Sums the total time of the direct children of the timer at `path`.
+pub(crate) fn child_time(timers: &Timers, path: &str) -> u64 {
+  let prefix = format!("{}/", path);
+  timers
+    .iter()
+    .filter(|&(child, _)| child.starts_with(&prefix) && !child[prefix.len()..].contains('/'))
+    .map(|(_, child)| child.total_time)
+    .sum()
+}
+
+impl<K> TimerSet<K> {
+  /// Writes every timer as a line of Brendan Gregg's folded-stack format,
+  /// e.g. `a;b;c 1234`, weighted by the microseconds spent in the timer
+  /// itself rather than its children, sorted by stack. This can be fed
+  /// straight to `flamegraph.pl` or `inferno-flamegraph`.
+  ///
+  /// Stacks come from hierarchy mode; without it, every timer is a stack of
+  /// its own.
+  pub fn write_folded<W: Write>(&self, mut w: W) -> io::Result<()> {
+    let timers = self.merged();
+    let mut lines: Vec<(String, u64)> =
+      timers
+        .iter()
+        .map(|(name, sw)| {
+          let self_time = sw.total_time.saturating_sub(child_time(&timers, name));
+          let stack: Vec<String> = name.split('/').map(|frame| frame.replace(';', ":")).collect();
+          (stack.join(";"), self_time / 1_000)
+        })
+        .filter(|&(_, us)| us > 0)
+        .collect();
+    lines.sort();
+    for (stack, us) in lines.iter() {
+      writeln!(w, "{} {}", stack, us)?;
+    }
+    Ok(())
+  }
+}
+
+#[cfg(not(feature = "disable"))]
+#[test]
+fn test_write_folded() {
+  use std::thread::sleep;
+  use std::time::Duration;
+
+  let mut ts = TimerSet::new();
+  ts.set_hierarchical(true);
+  ts.time("a", || {
+    sleep(Duration::from_millis(1));
+    ts.time("b;c", || sleep(Duration::from_millis(1)));
+  });
+  let mut out = Vec::new();
+  ts.write_folded(&mut out).unwrap();
+  let out = String::from_utf8(out).unwrap();
+  let lines: Vec<&str> = out.lines().collect();
+  assert_eq!(lines.len(), 2);
+  assert!(lines[0].starts_with("a "), "{}", lines[0]);
+  assert!(lines[1].starts_with("a;b:c "), "{}", lines[1]);
+  let us: u64 = lines[1].rsplit(' ').next().unwrap().parse().unwrap();
+  assert!(us >= 1_000, "{}", us);
+}
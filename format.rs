@@ -0,0 +1,177 @@
+//! Formatting of durations in reports.
+
+#[cfg(not(feature = "std"))]
+use alloc::string::String;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+/// A unit to report durations in.
+pub enum TimeUnit {
+  /// Nanoseconds.
+  Nanos,
+  /// Microseconds.
+  Micros,
+  /// Milliseconds.
+  Millis,
+  /// Seconds.
+  Secs,
+  /// Minutes.
+  Mins,
+}
+
+impl TimeUnit {
+  /// The number of nanoseconds in one of this unit.
+  fn nanos(self) -> u64 {
+    match self {
+      TimeUnit::Nanos => 1,
+      TimeUnit::Micros => 1_000,
+      TimeUnit::Millis => 1_000_000,
+      TimeUnit::Secs => 1_000_000_000,
+      TimeUnit::Mins => 60_000_000_000,
+    }
+  }
+
+  fn suffix(self) -> &'static str {
+    match self {
+      TimeUnit::Nanos => "ns",
+      TimeUnit::Micros => "us",
+      TimeUnit::Millis => "ms",
+      TimeUnit::Secs => "s",
+      TimeUnit::Mins => "min",
+    }
+  }
+
+  /// The next unit up, if there's a larger one.
+  fn larger(self) -> Option<TimeUnit> {
+    match self {
+      TimeUnit::Nanos => Some(TimeUnit::Micros),
+      TimeUnit::Micros => Some(TimeUnit::Millis),
+      TimeUnit::Millis => Some(TimeUnit::Secs),
+      TimeUnit::Secs => Some(TimeUnit::Mins),
+      TimeUnit::Mins => None,
+    }
+  }
+
+  /// The largest unit that `ns` is at least one of.
+  fn fitting(ns: u64) -> TimeUnit {
+    [TimeUnit::Mins, TimeUnit::Secs, TimeUnit::Millis, TimeUnit::Micros]
+      .iter()
+      .cloned()
+      .find(|unit| ns >= unit.nanos())
+      .unwrap_or(TimeUnit::Nanos)
+  }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+/// How reports render durations.
+pub enum DurationFormat {
+  /// Picks a unit per value, so it's at least one whole unit once rounded,
+  /// and shows one decimal place, e.g. `1.5ms` or `2.0min`.
+  #[default]
+  Adaptive,
+  /// Shows every value as a whole number of the given unit, e.g. `1500us`,
+  /// so reports can be compared line by line.
+  Fixed(TimeUnit),
+  /// Shows every value in `unit` with `decimals` decimal places, e.g.
+  /// `1,500.250us` with three and a `,` separator. Like `Fixed`, digits past
+  /// the last place are dropped rather than rounded, and the output doesn't
+  /// depend on the locale: the decimal point is always `.`.
+  Custom {
+    /// The unit to show every value in.
+    unit: TimeUnit,
+    /// How many decimal places to show.
+    decimals: u8,
+    /// What to put between each group of three whole digits, e.g. `,` or
+    /// `_`, or `None` for nothing.
+    separator: Option<char>,
+  },
+}
+
+/// Renders `n` with `separator` between each group of three digits.
+fn group_digits(n: u64, separator: Option<char>) -> String {
+  let digits = format!("{}", n);
+  let separator =
+    match separator {
+      None => return digits,
+      Some(separator) => separator,
+    };
+  let mut grouped = String::new();
+  for (i, c) in digits.chars().enumerate() {
+    if i > 0 && (digits.len() - i).is_multiple_of(3) {
+      grouped.push(separator);
+    }
+    grouped.push(c);
+  }
+  grouped
+}
+
+impl DurationFormat {
+  /// Renders `ns` nanoseconds.
+  pub fn format(self, ns: u64) -> String {
+    match self {
+      DurationFormat::Fixed(unit) => format!("{}{}", ns / unit.nanos(), unit.suffix()),
+      DurationFormat::Custom { unit, decimals, separator } => {
+        let whole = group_digits(ns / unit.nanos(), separator);
+        if decimals == 0 {
+          return format!("{}{}", whole, unit.suffix());
+        }
+        // Nanoseconds don't have more than 19 decimal places of any unit, so
+        // the rest are always zeros.
+        let places = u32::from(decimals.min(19));
+        let fraction = u128::from(ns % unit.nanos()) * 10u128.pow(places) / u128::from(unit.nanos());
+        let zeros = "0".repeat(usize::from(decimals) - places as usize);
+        format!("{}.{:0width$}{}{}", whole, fraction, zeros, unit.suffix(), width = places as usize)
+      },
+      DurationFormat::Adaptive => {
+        let unit = TimeUnit::fitting(ns);
+        if unit == TimeUnit::Nanos {
+          return format!("{}ns", ns);
+        }
+        // A value that rounds up to one of the next unit, e.g. 999.96us,
+        // shows as that, 1.0ms, rather than as 1000.0us.
+        let unit =
+          match unit.larger() {
+            Some(larger) if ns.saturating_add(unit.nanos() / 20) >= larger.nanos() => larger,
+            _ => unit,
+          };
+        format!("{:.1}{}", ns as f64 / unit.nanos() as f64, unit.suffix())
+      },
+    }
+  }
+}
+
+#[test]
+fn test_adaptive() {
+  let f = DurationFormat::Adaptive;
+  assert_eq!(f.format(0), "0ns");
+  assert_eq!(f.format(999), "999ns");
+  assert_eq!(f.format(1_500), "1.5us");
+  assert_eq!(f.format(12_345_678), "12.3ms");
+  assert_eq!(f.format(59_000_000_000), "59.0s");
+  assert_eq!(f.format(90_000_000_000), "1.5min");
+
+  // Units are picked after rounding.
+  assert_eq!(f.format(999_949), "999.9us");
+  assert_eq!(f.format(999_950), "1.0ms");
+  assert_eq!(f.format(999_999_999), "1.0s");
+  assert_eq!(f.format(59_949_999_999), "59.9s");
+  assert_eq!(f.format(59_960_000_000), "1.0min");
+}
+
+#[test]
+fn test_fixed() {
+  assert_eq!(DurationFormat::Fixed(TimeUnit::Micros).format(1_500_999), "1500us");
+  assert_eq!(DurationFormat::Fixed(TimeUnit::Secs).format(999_999_999), "0s");
+}
+
+#[test]
+fn test_custom() {
+  let custom = |unit, decimals, separator| DurationFormat::Custom { unit, decimals, separator };
+  assert_eq!(custom(TimeUnit::Micros, 3, Some(',')).format(1_500_250), "1,500.250us");
+  assert_eq!(custom(TimeUnit::Millis, 2, None).format(1_239_999), "1.23ms");
+  assert_eq!(custom(TimeUnit::Secs, 1, Some('_')).format(1_234_567_000_000_000), "1_234_567.0s");
+  assert_eq!(custom(TimeUnit::Nanos, 0, Some(',')).format(999), "999ns");
+  assert_eq!(custom(TimeUnit::Nanos, 0, Some(',')).format(1_000), "1,000ns");
+  assert_eq!(custom(TimeUnit::Millis, 0, None).format(1_500_999), DurationFormat::Fixed(TimeUnit::Millis).format(1_500_999));
+  assert_eq!(custom(TimeUnit::Secs, 21, None).format(1), "0.000000001000000000000s");
+  assert_eq!(custom(TimeUnit::Mins, 12, None).format(u64::MAX), "307445734.561825860250min");
+}
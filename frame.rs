@@ -0,0 +1,168 @@
+//! Per-frame profiling, e.g. for game loops.
+
+use std::collections::{HashMap, VecDeque};
+use std::time::Duration;
+
+use {to_nanos, DurationFormat, Instant, Reporter, TimerSet};
+
+/// What one frame recorded.
+struct Frame {
+  /// How long the frame took, in nanoseconds.
+  duration: u64,
+  /// Each timer's total time during the frame, in nanoseconds.
+  totals: HashMap<String, u64>,
+}
+
+/// Profiles a loop frame by frame, keeping the stats of the last few frames.
+///
+/// Time things during a frame with `timers()`, then call `end_frame()` once
+/// per frame to move that frame's stats into the rolling window.
+pub struct FrameProfiler {
+  timers: TimerSet,
+  capacity: usize,
+  frames: VecDeque<Frame>,
+  frame_start: Instant,
+}
+
+impl FrameProfiler {
+  /// Creates a profiler that keeps the stats of the last `frames` frames.
+  /// The first frame starts now.
+  ///
+  /// Panics if `frames` is zero.
+  pub fn new(frames: usize) -> FrameProfiler {
+    assert!(frames > 0, "FrameProfiler needs room for at least one frame");
+    FrameProfiler {
+      timers: TimerSet::new(),
+      capacity: frames,
+      frames: VecDeque::with_capacity(frames),
+      frame_start: Instant::now(),
+    }
+  }
+
+  /// Returns the timers for the current frame.
+  pub fn timers(&self) -> &TimerSet {
+    &self.timers
+  }
+
+  /// Ends the current frame, moving its stats into the rolling window and
+  /// forgetting the oldest frame if the window is full. The next frame
+  /// starts now.
+  pub fn end_frame(&mut self) {
+    let now = Instant::now();
+    let totals =
+      self.timers.take_snapshot()
+        .into_iter()
+        .filter(|(_, sw)| sw.number_of_windows > 0)
+        .map(|(name, sw)| (name, sw.total_time))
+        .collect();
+    if self.frames.len() == self.capacity {
+      self.frames.pop_front();
+    }
+    self.frames.push_back(Frame {
+      duration: to_nanos(now - self.frame_start),
+      totals,
+    });
+    self.frame_start = now;
+  }
+
+  /// Returns the number of frames in the rolling window.
+  pub fn frame_count(&self) -> usize {
+    self.frames.len()
+  }
+
+  /// Returns the mean frame time over the window, or `None` if no frame has
+  /// ended yet.
+  pub fn mean_frame_time(&self) -> Option<Duration> {
+    if self.frames.is_empty() {
+      return None;
+    }
+    let total: u64 = self.frames.iter().map(|f| f.duration).sum();
+    Some(Duration::from_nanos(total / self.frames.len() as u64))
+  }
+
+  /// Returns the longest frame time in the window, or `None` if no frame has
+  /// ended yet.
+  pub fn worst_frame_time(&self) -> Option<Duration> {
+    self.frames.iter().map(|f| f.duration).max().map(Duration::from_nanos)
+  }
+
+  /// Returns the frame time below which a fraction `q` of the window's
+  /// frames fall, or `None` if no frame has ended yet.
+  pub fn frame_time_quantile(&self, q: f64) -> Option<Duration> {
+    if self.frames.is_empty() {
+      return None;
+    }
+    let mut durations: Vec<u64> = self.frames.iter().map(|f| f.duration).collect();
+    durations.sort();
+    let rank = (q.clamp(0.0, 1.0) * (durations.len() - 1) as f64).round() as usize;
+    Some(Duration::from_nanos(durations[rank]))
+  }
+
+  /// Returns the named timer's mean total time per frame over the window,
+  /// counting frames it didn't run in as zero, or `None` if no frame has
+  /// ended yet.
+  pub fn average_per_frame(&self, name: &str) -> Option<Duration> {
+    if self.frames.is_empty() {
+      return None;
+    }
+    let total: u64 = self.frames.iter().filter_map(|f| f.totals.get(name)).sum();
+    Some(Duration::from_nanos(total / self.frames.len() as u64))
+  }
+
+  /// Returns the named timer's largest total time in any one frame in the
+  /// window, or `None` if it didn't run in any of them.
+  pub fn worst_per_frame(&self, name: &str) -> Option<Duration> {
+    self.frames.iter().filter_map(|f| f.totals.get(name)).max().map(|&ns| Duration::from_nanos(ns))
+  }
+
+  /// Sends a summary of the window to `reporter`: frame times first, then a
+  /// line per timer, sorted by name.
+  pub fn report(&self, reporter: &dyn Reporter) {
+    let format = DurationFormat::Adaptive;
+    let ns = |d: Option<Duration>| format.format(d.map_or(0, to_nanos));
+    if self.frames.is_empty() {
+      reporter.line("no frames");
+      return;
+    }
+    reporter.line(&format!(
+      "{} frames (avg {}, worst {}, p50 {}, p90 {}, p99 {})",
+      self.frames.len(),
+      ns(self.mean_frame_time()),
+      ns(self.worst_frame_time()),
+      ns(self.frame_time_quantile(0.5)),
+      ns(self.frame_time_quantile(0.9)),
+      ns(self.frame_time_quantile(0.99))
+    ));
+
+    let mut names: Vec<&str> = self.frames.iter().flat_map(|f| f.totals.keys()).map(|k| k.as_ref()).collect();
+    names.sort();
+    names.dedup();
+    for name in names {
+      reporter.line(&format!(
+        "{}: {} per frame (worst {})",
+        name,
+        ns(self.average_per_frame(name)),
+        ns(self.worst_per_frame(name))
+      ));
+    }
+  }
+}
+
+#[cfg(not(feature = "disable"))]
+#[test]
+fn test_frame_profiler() {
+  let mut profiler = FrameProfiler::new(2);
+  assert_eq!(profiler.mean_frame_time(), None);
+  profiler.timers().time("update", || ::std::thread::sleep(Duration::from_millis(2)));
+  profiler.end_frame();
+  profiler.end_frame();
+  profiler.timers().time("update", || {});
+  profiler.end_frame();
+
+  // The sleeping frame has dropped out of the window.
+  assert_eq!(profiler.frame_count(), 2);
+  assert!(profiler.worst_frame_time().unwrap() < Duration::from_millis(2));
+  assert!(profiler.worst_per_frame("update").unwrap() < Duration::from_millis(2));
+  assert_eq!(profiler.average_per_frame("update").unwrap(), profiler.worst_per_frame("update").unwrap() / 2);
+  assert_eq!(profiler.frame_time_quantile(1.0), profiler.worst_frame_time());
+}
@@ -0,0 +1,176 @@
+//! Timing futures.
+
+use std::borrow::Cow;
+use std::future::Future;
+use std::pin::Pin;
+#[cfg(feature = "tokio")]
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use {Clock, InstantClock, TimerSet, TIMERSET};
+
+/// Where a `TimedFuture` logs its windows.
+pub(crate) enum Target<'a> {
+  /// The thread-local `TimerSet` of whichever thread completes the future.
+  ThreadLocal(InstantClock),
+  Set(&'a TimerSet),
+  /// A set shared with the future, e.g. one spawned onto a runtime.
+  #[cfg(feature = "tokio")]
+  Shared(Arc<TimerSet>),
+}
+
+impl<'a> Target<'a> {
+  fn clock(&self) -> &dyn Clock {
+    match *self {
+      Target::ThreadLocal(ref clock) => clock,
+      Target::Set(timers) => &*timers.clock,
+      #[cfg(feature = "tokio")]
+      Target::Shared(ref timers) => &*timers.clock,
+    }
+  }
+
+  fn add_window(&self, name: &str, dt: u64) {
+    match *self {
+      Target::ThreadLocal(_) => TIMERSET.with(|timers| timers.add_window(name, dt)),
+      Target::Set(timers) => timers.add_window(name, dt),
+      #[cfg(feature = "tokio")]
+      Target::Shared(ref timers) => timers.add_window(name, dt),
+    }
+  }
+}
+
+/// A future that times another, created by `TimerSet::time_async` or
+/// `TimedFutureExt`.
+pub struct TimedFuture<'a, F> {
+  target: Target<'a>,
+  name: Cow<'a, str>,
+  poll_name: String,
+  future: F,
+  first_poll: Option<u64>,
+  poll_time: u64,
+}
+
+impl TimerSet {
+  /// Times a future, logging two windows when it completes: the wall time
+  /// from its first poll until it completes under `name`, and the time
+  /// spent actually polling it under `name.poll`. The difference is time
+  /// spent waiting to be woken up and scheduled.
+  ///
+  /// Nothing is logged if the future is dropped before it completes. Scopes
+  /// opened with `push_scope` apply to the name when this is called.
+  pub fn time_async<'a, F: Future>(&'a self, name: &'a str, future: F) -> TimedFuture<'a, F> {
+    TimedFuture::new(Target::Set(self), self.scoped(name), future)
+  }
+}
+
+impl<'a, F> TimedFuture<'a, F> {
+  pub(crate) fn new(target: Target<'a>, name: Cow<'a, str>, future: F) -> TimedFuture<'a, F> {
+    TimedFuture {
+      target,
+      poll_name: format!("{}.poll", name),
+      name,
+      future,
+      first_poll: None,
+      poll_time: 0,
+    }
+  }
+}
+
+/// Adds methods for timing any future inline, e.g.
+/// `fetch().timed("fetch").await`. See `TimerSet::time_async` for what's
+/// logged.
+pub trait TimedFutureExt: Future + Sized {
+  /// Times this future with the thread-local `TimerSet` of whichever thread
+  /// completes it.
+  fn timed<'a>(self, name: &'a str) -> TimedFuture<'a, Self> {
+    let name = TIMERSET.with(|timers| Cow::Owned(timers.scoped(name).into_owned()));
+    TimedFuture::new(Target::ThreadLocal(InstantClock::new()), name, self)
+  }
+
+  /// Times this future with the given `TimerSet`.
+  fn timed_in<'a>(self, timers: &'a TimerSet, name: &'a str) -> TimedFuture<'a, Self> {
+    timers.time_async(name, self)
+  }
+}
+
+impl<F: Future> TimedFutureExt for F {}
+
+impl<'a, F: Future> Future for TimedFuture<'a, F> {
+  type Output = F::Output;
+
+  fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<F::Output> {
+    // Safe because `future` is never moved out of, and nothing else is
+    // structurally pinned.
+    let this = unsafe { self.get_unchecked_mut() };
+    let clock = this.target.clock();
+
+    let then = clock.now();
+    let first_poll = *this.first_poll.get_or_insert(then);
+    let poll = unsafe { Pin::new_unchecked(&mut this.future) }.poll(cx);
+    let now = clock.now();
+    this.poll_time += clock.to_nanos(now.saturating_sub(then));
+
+    if poll.is_ready() {
+      this.target.add_window(&this.name, clock.to_nanos(now.saturating_sub(first_poll)));
+      this.target.add_window(&this.poll_name, this.poll_time);
+    }
+    poll
+  }
+}
+
+#[cfg(all(test, not(feature = "disable")))]
+struct NoopWaker;
+
+#[cfg(all(test, not(feature = "disable")))]
+impl ::std::task::Wake for NoopWaker {
+  fn wake(self: ::std::sync::Arc<Self>) {}
+}
+
+/// A future that's pending on its first poll, and ready on its second.
+#[cfg(all(test, not(feature = "disable")))]
+struct Yield(bool);
+
+#[cfg(all(test, not(feature = "disable")))]
+impl Future for Yield {
+  type Output = u32;
+  fn poll(mut self: Pin<&mut Self>, _: &mut Context) -> Poll<u32> {
+    if self.0 {
+      Poll::Ready(7)
+    } else {
+      self.0 = true;
+      Poll::Pending
+    }
+  }
+}
+
+#[cfg(not(feature = "disable"))]
+#[test]
+fn test_time_async() {
+  let ts = TimerSet::new();
+  let waker = ::std::task::Waker::from(::std::sync::Arc::new(NoopWaker));
+  let mut cx = Context::from_waker(&waker);
+  let mut future = Box::pin(ts.time_async("yield", Yield(false)));
+  assert_eq!(future.as_mut().poll(&mut cx), Poll::Pending);
+  ::std::thread::sleep(::std::time::Duration::from_millis(2));
+  assert_eq!(future.as_mut().poll(&mut cx), Poll::Ready(7));
+
+  let timers = ts.merged();
+  assert_eq!(timers["yield"].number_of_windows, 1);
+  assert_eq!(timers["yield.poll"].number_of_windows, 1);
+  assert!(timers["yield"].total_time >= 2_000_000);
+  assert!(timers["yield.poll"].total_time < timers["yield"].total_time);
+}
+
+#[cfg(not(feature = "disable"))]
+#[test]
+fn test_timed_future_ext() {
+  let waker = ::std::task::Waker::from(::std::sync::Arc::new(NoopWaker));
+  let mut cx = Context::from_waker(&waker);
+  let mut future = Box::pin(Yield(false).timed("test_timed_future_ext"));
+  assert_eq!(future.as_mut().poll(&mut cx), Poll::Pending);
+  assert_eq!(future.as_mut().poll(&mut cx), Poll::Ready(7));
+
+  let timers = ::clone().merged();
+  assert_eq!(timers["test_timed_future_ext"].number_of_windows, 1);
+  assert_eq!(timers["test_timed_future_ext.poll"].number_of_windows, 1);
+}
@@ -0,0 +1,115 @@
+//! Tracking point-in-time values, e.g. queue depths, in the same set as the
+//! timers.
+
+use std::collections::BTreeMap;
+
+use {lock, Name, Report, Reporter, TimerSet};
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+/// What a gauge has been set to.
+pub struct Gauge {
+  /// The latest value.
+  pub last: f64,
+  /// The smallest value.
+  pub min: f64,
+  /// The largest value.
+  pub max: f64,
+  /// How many times the gauge has been set.
+  pub count: u64,
+}
+
+impl Gauge {
+  fn new(value: f64) -> Gauge {
+    Gauge {
+      last: value,
+      min: value,
+      max: value,
+      count: 1,
+    }
+  }
+
+  fn set(&mut self, value: f64) {
+    self.last = value;
+    self.min = self.min.min(value);
+    self.max = self.max.max(value);
+    self.count += 1;
+  }
+
+  /// Folds `later`, set after this gauge, into it.
+  fn merge(&mut self, later: &Gauge) {
+    self.last = later.last;
+    self.min = self.min.min(later.min);
+    self.max = self.max.max(later.max);
+    self.count += later.count;
+  }
+}
+
+/// Gauges keyed by name, interned like the timers' names.
+pub(crate) type Gauges = BTreeMap<Name, Gauge>;
+
+/// Folds every gauge in `later` into `gauges`.
+pub(crate) fn merge_gauges(gauges: &mut Gauges, later: &Gauges) {
+  for (name, gauge) in later.iter() {
+    match gauges.get_mut(name) {
+      Some(g) => g.merge(gauge),
+      None => {
+        gauges.insert(name.clone(), *gauge);
+      },
+    }
+  }
+}
+
+impl<K> TimerSet<K> {
+  /// Sets the gauge with the given name to `value`, creating it if need be.
+  /// Gauges keep their latest, smallest and largest values, and are listed
+  /// after the counters in reports. The name is scoped the same way as
+  /// `time`'s, but never nested.
+  pub fn gauge(&self, name: &str, value: f64) {
+    if !self.is_enabled() {
+      return;
+    }
+    let name = self.scoped(name);
+    let mut gauges = lock(&self.gauges);
+    match gauges.get_mut(name.as_ref()) {
+      Some(gauge) => gauge.set(value),
+      None => {
+        gauges.insert(self.new_name(&name), Gauge::new(value));
+      },
+    }
+  }
+
+  /// Returns every gauge, sorted by name.
+  pub fn gauges(&self) -> Vec<(String, Gauge)> {
+    lock(&self.gauges).iter().map(|(name, gauge)| (name.to_string(), *gauge)).collect()
+  }
+
+  /// Sends a line per gauge `report` picks to `reporter`, sorted by name.
+  pub(crate) fn report_gauges(&self, reporter: &dyn Reporter, report: &Report) {
+    for (name, gauge) in self.gauges().into_iter().filter(|(name, _)| report.matches(name)) {
+      reporter.line(&format!("{}: {} (min {}, max {}, set {} times)", name, gauge.last, gauge.min, gauge.max, gauge.count));
+    }
+  }
+}
+
+#[cfg(not(feature = "disable"))]
+#[test]
+fn test_gauge() {
+  let ts = TimerSet::new();
+  for &depth in [3.0, 1.0, 5.0, 2.0].iter() {
+    ts.gauge("queue", depth);
+  }
+  let other = TimerSet::new();
+  other.gauge("queue", 7.0);
+  other.gauge("memory", 1.5);
+  ts.merge(&other);
+
+  let gauges = ts.gauges();
+  assert_eq!(gauges[0], ("memory".to_string(), Gauge::new(1.5)));
+  assert_eq!(gauges[1].1, Gauge { last: 7.0, min: 1.0, max: 7.0, count: 5 });
+
+  let lines = ::std::cell::RefCell::new(Vec::new());
+  ts.report(&|line: &str| lines.borrow_mut().push(line.to_string()));
+  assert_eq!(*lines.borrow(), vec!["memory: 1.5 (min 1.5, max 1.5, set 1 times)", "queue: 7 (min 1, max 7, set 5 times)"]);
+  ts.reset_all();
+  assert!(ts.gauges().is_empty());
+}
@@ -0,0 +1,81 @@
+//! Pre-registered timers, which skip looking the timer up by name.
+
+use {lock, Allocations, TimerSet};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+/// A timer registered with `TimerSet::register`.
+pub struct TimerHandle {
+  /// The id of the set the timer is registered with.
+  set: usize,
+  pub(crate) index: usize,
+}
+
+impl<K> TimerSet<K> {
+  /// Registers a timer with the given name, returning a handle to time with
+  /// it through `time_handle`. Registering the same name again returns the
+  /// same handle.
+  pub fn register(&self, name: &str) -> TimerHandle {
+    let mut handle_names = lock(&self.handle_names);
+    let index =
+      match handle_names.iter().position(|n| n == name) {
+        Some(index) => index,
+        None => {
+          handle_names.push(name.to_string());
+          handle_names.len() - 1
+        },
+      };
+    TimerHandle {
+      set: self.id,
+      index,
+    }
+  }
+
+  /// Times `f` under a registered timer. Unlike `time`, this doesn't hash
+  /// the timer's name, so it's cheaper for very short functions, but the
+  /// name isn't prefixed by scopes or nested in hierarchy mode either.
+  ///
+  /// Panics if `handle` was registered with a different set.
+  #[inline]
+  pub fn time_handle<T, F: FnOnce() -> T>(&self, handle: TimerHandle, f: F) -> T {
+    assert!(handle.set == self.id, "TimerHandle used with a set it wasn't registered with");
+    if !self.is_enabled() {
+      return f();
+    }
+    let start = self.start_window(None);
+    let ret = f();
+    let dt = self.clock.to_nanos(self.clock.now().saturating_sub(start.ticks));
+    let allocated = Allocations::current().since(start.allocated);
+    let child_time = self.pop_window(start, dt);
+    if self.events.is_some() {
+      let name = lock(&self.handle_names)[handle.index].clone();
+      self.record_event(&name, self.clock.to_nanos(start.ticks), dt);
+    }
+    self.with_shard_timers(|shard| {
+      let sw = shard.handle_mut(handle.index, self.config);
+      sw.add_window(dt);
+      sw.add_allocations(allocated, 1);
+      sw.child_time += child_time;
+    });
+    ret
+  }
+}
+
+#[cfg(not(feature = "disable"))]
+#[test]
+fn test_time_handle() {
+  let ts = TimerSet::new();
+  let a = ts.register("a");
+  let b = ts.register("b");
+  assert_eq!(ts.register("a"), a);
+  for _ in 0..3 {
+    ts.time_handle(b, || {});
+  }
+  ts.time("b", || {});
+  assert!(!ts.merged().contains_key("a"));
+  assert_eq!(ts.merged()["b"].number_of_windows, 4);
+
+  assert_eq!(ts.take_snapshot()["b"].number_of_windows, 4);
+  ts.time_handle(b, || {});
+  assert_eq!(ts.remove("b").unwrap().number_of_windows, 1);
+  assert!(!ts.merged().contains_key("b"));
+}
@@ -0,0 +1,165 @@
+#![deny(missing_docs)]
+
+//! A log-scaled histogram of time windows.
+//!
+//! Every power of two is split into `SUB_BUCKETS` linear buckets, so any
+//! recorded value lands in a bucket less than 25% wider than itself.
+
+#[cfg(feature = "serde")]
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+/// log2 of the number of buckets per power of two.
+const SUB_BITS: u32 = 2;
+const SUB_BUCKETS: u64 = 1 << SUB_BITS;
+const BUCKETS: usize = ((64 - SUB_BITS + 1) as usize) * (SUB_BUCKETS as usize);
+
+fn bucket_of(x: u64) -> usize {
+  if x < SUB_BUCKETS {
+    x as usize
+  } else {
+    let exp = 63 - x.leading_zeros();
+    let mantissa = (x >> (exp - SUB_BITS)) & (SUB_BUCKETS - 1);
+    ((exp - SUB_BITS + 1) as u64 * SUB_BUCKETS + mantissa) as usize
+  }
+}
+
+/// The smallest value that lands in bucket `b`.
+fn lower_bound(b: usize) -> u64 {
+  let b = b as u64;
+  if b < SUB_BUCKETS {
+    b
+  } else {
+    let exp = b / SUB_BUCKETS - 1;
+    (SUB_BUCKETS + b % SUB_BUCKETS) << exp
+  }
+}
+
+#[derive(Clone)]
+/// Counts of time windows, bucketed by duration.
+pub struct Histogram {
+  counts: [u64; BUCKETS],
+  total: u64,
+}
+
+impl Histogram {
+  /// Creates an empty histogram.
+  pub fn new() -> Histogram {
+    Histogram {
+      counts: [0; BUCKETS],
+      total: 0,
+    }
+  }
+
+  #[inline]
+  /// Records `n` time windows of `x` nanoseconds.
+  pub fn add(&mut self, x: u64, n: u64) {
+    self.counts[bucket_of(x)] += n;
+    self.total += n;
+  }
+
+  /// Forgets every recorded window.
+  pub fn clear(&mut self) {
+    self.counts = [0; BUCKETS];
+    self.total = 0;
+  }
+
+  /// Adds all the windows recorded in `other` to this histogram.
+  pub fn merge(&mut self, other: &Histogram) {
+    for (count, &other_count) in self.counts.iter_mut().zip(other.counts.iter()) {
+      *count += other_count;
+    }
+    self.total += other.total;
+  }
+
+  /// Estimates the value below which a fraction `q` of the recorded windows
+  /// fall, interpolating linearly within a bucket.
+  pub fn quantile(&self, q: f64) -> Option<u64> {
+    if self.total == 0 {
+      return None;
+    }
+    let q = q.clamp(0.0, 1.0);
+    let rank = q * (self.total - 1) as f64;
+    let mut seen = 0;
+    for (b, &count) in self.counts.iter().enumerate() {
+      if count == 0 {
+        continue;
+      }
+      if (seen + count) as f64 > rank {
+        let lo = lower_bound(b);
+        let hi = if b + 1 < BUCKETS { lower_bound(b + 1) } else { u64::MAX };
+        let frac = (rank - seen as f64) / count as f64;
+        return Some(lo + ((hi - lo) as f64 * frac) as u64);
+      }
+      seen += count;
+    }
+    unreachable!()
+  }
+
+  /// Returns every bucket with windows in it, as the middle of the bucket and
+  /// how many windows, shortest first.
+  #[cfg(feature = "otel")]
+  pub fn buckets(&self) -> Vec<(u64, u64)> {
+    self.counts
+      .iter()
+      .enumerate()
+      .filter(|&(_, &count)| count > 0)
+      .map(|(b, &count)| {
+        let lo = lower_bound(b);
+        let hi = if b + 1 < BUCKETS { lower_bound(b + 1) } else { u64::MAX };
+        (lo + (hi - lo) / 2, count)
+      })
+      .collect()
+  }
+}
+
+impl ::core::fmt::Debug for Histogram {
+  fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+    write!(f, "Histogram {{ total: {} }}", self.total)
+  }
+}
+
+#[cfg(feature = "serde")]
+/// Serializes as a list of `(bucket, count)` pairs, leaving out empty buckets.
+impl Serialize for Histogram {
+  fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+    let buckets: Vec<(usize, u64)> = self.counts.iter().cloned().enumerate().filter(|&(_, count)| count > 0).collect();
+    buckets.serialize(s)
+  }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for Histogram {
+  fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Histogram, D::Error> {
+    let mut h = Histogram::new();
+    for (b, count) in Vec::<(usize, u64)>::deserialize(d)? {
+      if b >= BUCKETS {
+        return Err(de::Error::custom(format!("histogram bucket {} out of range", b)));
+      }
+      h.total = h.total.checked_add(count).ok_or_else(|| de::Error::custom("histogram window count overflows"))?;
+      h.counts[b] += count;
+    }
+    Ok(h)
+  }
+}
+
+#[test]
+fn test_buckets() {
+  for b in 0..BUCKETS - 1 {
+    assert_eq!(bucket_of(lower_bound(b)), b);
+    assert_eq!(bucket_of(lower_bound(b + 1) - 1), b);
+  }
+  assert_eq!(bucket_of(u64::MAX), BUCKETS - 1);
+}
+
+#[test]
+fn test_quantile() {
+  let mut h = Histogram::new();
+  assert_eq!(h.quantile(0.5), None);
+  for x in 1..1001 {
+    h.add(x, 1);
+  }
+  let p50 = h.quantile(0.5).unwrap();
+  let p99 = h.quantile(0.99).unwrap();
+  assert!(p50 > 400 && p50 < 625, "p50 = {}", p50);
+  assert!(p99 > 800 && p99 < 1250, "p99 = {}", p99);
+}
@@ -0,0 +1,162 @@
+//! Rolling per-interval history of timers, e.g. one bucket per minute.
+
+use std::collections::{HashMap, VecDeque};
+use std::hash::BuildHasherDefault;
+use std::time::Duration;
+
+use fnv::FnvHasher;
+
+use {lock, to_nanos, Instant, Stopwatch, StopwatchConfig, TimerSet};
+
+/// The last few intervals of one timer, as one stopwatch per interval.
+#[derive(Default)]
+pub(crate) struct History {
+  /// Each interval that had windows, by its index since the set's epoch,
+  /// oldest first.
+  buckets: VecDeque<(u64, Stopwatch)>,
+}
+
+impl History {
+  /// Logs `n` windows of `dt` nanoseconds, which ended at `end`, in interval
+  /// `index`, forgetting any interval more than `capacity` before it.
+  fn add(&mut self, index: u64, capacity: usize, dt: u64, n: u64, end: Instant) {
+    // Windows are mostly logged in order, but recorded spans can end before
+    // the last window did.
+    let i = self.buckets.iter().rposition(|&(i, _)| i <= index);
+    let i =
+      match i {
+        Some(i) if self.buckets[i].0 == index => i,
+        _ => {
+          let i = i.map_or(0, |i| i + 1);
+          self.buckets.insert(i, (index, Stopwatch::with_config(StopwatchConfig { histogram: false, ..StopwatchConfig::default() })));
+          i
+        },
+      };
+    self.buckets[i].1.add_windows_ending(dt, n, end);
+    let latest = self.buckets.back().map_or(index, |&(last, _)| last);
+    while self.buckets.front().is_some_and(|&(first, _)| first + capacity as u64 <= latest) {
+      self.buckets.pop_front();
+    }
+  }
+
+  /// Folds interval `index`, if it's kept, into `sw`.
+  fn merge_into(&self, index: u64, sw: &mut Stopwatch) {
+    if let Some((_, bucket)) = self.buckets.iter().find(|&&(i, _)| i == index) {
+      sw.merge(bucket);
+    }
+  }
+}
+
+/// Every timer's history in one shard, by name.
+pub(crate) type Histories = HashMap<String, History, BuildHasherDefault<FnvHasher>>;
+
+impl<K> TimerSet<K> {
+  /// Keeps a rolling history of the named timer: one summary per `interval`
+  /// for the last `buckets` intervals, e.g. per minute for the last hour, to
+  /// see how it's been trending. `name` is the full name the timer reports
+  /// under. A `buckets` of 0 stops keeping it.
+  ///
+  /// Intervals are of wall-clock time since the set was created, whichever
+  /// clock times the windows.
+  pub fn set_history(&mut self, name: &str, interval: Duration, buckets: usize) {
+    if buckets > 0 && interval > Duration::ZERO {
+      self.history_settings.insert(name.to_string(), (to_nanos(interval), buckets));
+    } else {
+      self.history_settings.remove(name);
+    }
+  }
+
+  /// Logs windows that ended at `end` in the named timer's history, if it's
+  /// keeping one.
+  pub(crate) fn add_history(&self, name: &str, dt: u64, n: u64, end: Instant) {
+    let (interval, capacity) =
+      match self.history_settings.get(name) {
+        None => return,
+        Some(&settings) => settings,
+      };
+    let index = to_nanos(end.saturating_duration_since(self.epoch)) / interval;
+    self.with_shard_timers(|shard| {
+      shard.history.entry(name.to_string()).or_default().add(index, capacity, dt, n, end);
+    });
+  }
+
+  /// Returns the named timer's history, one stopwatch per interval, oldest
+  /// first and ending with the current interval. Intervals it didn't run in
+  /// have stopwatches that never ran. Returns nothing if it isn't keeping a
+  /// history.
+  pub fn history(&self, name: &str) -> Vec<Stopwatch> {
+    let (interval, capacity) =
+      match self.history_settings.get(name) {
+        None => return Vec::new(),
+        Some(&settings) => settings,
+      };
+    let current = to_nanos(Instant::now().saturating_duration_since(self.epoch)) / interval;
+    let first = (current + 1).saturating_sub(capacity as u64);
+    let mut history: Vec<Stopwatch> =
+      (first..current + 1)
+        .map(|_| Stopwatch::with_config(StopwatchConfig { histogram: false, ..StopwatchConfig::default() }))
+        .collect();
+    for shard in lock(&self.shards).iter() {
+      if let Some(shard_history) = lock(shard).history.get(name) {
+        for (index, sw) in (first..current + 1).zip(history.iter_mut()) {
+          shard_history.merge_into(index, sw);
+        }
+      }
+    }
+    history
+  }
+
+  /// Merges the named timer's last `buckets` intervals of history, the
+  /// current one included, e.g. to compare the last five minutes with the
+  /// whole hour.
+  pub fn recent(&self, name: &str, buckets: usize) -> Stopwatch {
+    let mut recent = Stopwatch::with_config(StopwatchConfig { histogram: false, ..StopwatchConfig::default() });
+    let history = self.history(name);
+    for sw in history.iter().skip(history.len().saturating_sub(buckets)) {
+      recent.merge(sw);
+    }
+    recent
+  }
+}
+
+#[test]
+fn test_history_buckets() {
+  let now = Instant::now();
+  let mut history = History::default();
+  history.add(0, 3, 10, 1, now);
+  history.add(0, 3, 20, 1, now);
+  history.add(2, 3, 30, 1, now);
+  let mut sw = Stopwatch::new();
+  history.merge_into(0, &mut sw);
+  assert_eq!(sw.total_time, 30);
+  // Interval 0 falls out of a three-interval window once interval 3 starts.
+  history.add(3, 3, 40, 2, now);
+  let kept: Vec<u64> = history.buckets.iter().map(|(i, sw)| i * 1_000 + sw.total_time).collect();
+  assert_eq!(kept, vec![2_030, 3_080]);
+  // Intervals can be logged out of order, unless they're too old to keep.
+  history.add(0, 3, 50, 1, now);
+  history.add(2, 3, 60, 1, now);
+  let kept: Vec<u64> = history.buckets.iter().map(|(i, sw)| i * 1_000 + sw.total_time).collect();
+  assert_eq!(kept, vec![2_090, 3_080]);
+}
+
+#[cfg(not(feature = "disable"))]
+#[test]
+fn test_history() {
+  let mut ts = TimerSet::new();
+  ts.set_history("a", Duration::from_secs(3600), 4);
+  ts.record("a", Duration::from_millis(1));
+  ::std::thread::scope(|s| {
+    s.spawn(|| ts.record("a", Duration::from_millis(2)));
+  });
+  ts.record("b", Duration::from_millis(1));
+
+  let history = ts.history("a");
+  assert!(!history.is_empty() && history.len() <= 4);
+  assert_eq!(history[history.len() - 1].total_time, 3_000_000);
+  assert_eq!(ts.recent("a", 1).number_of_windows, 2);
+  assert!(ts.history("b").is_empty());
+
+  ts.reset_all();
+  assert_eq!(ts.recent("a", 4).number_of_windows, 0);
+}
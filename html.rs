@@ -0,0 +1,111 @@
+//! A standalone HTML report of timer statistics, to open in a browser.
+
+use std::fmt::Write;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use TimerSet;
+
+/// Styles the table and its bars.
+const STYLE: &str = "\
+body { font-family: sans-serif; margin: 2em; }
+table { border-collapse: collapse; }
+th, td { padding: 0.3em 0.8em; text-align: right; border-bottom: 1px solid #ddd; }
+th { cursor: pointer; user-select: none; background: #f4f4f4; }
+th:first-child, td:first-child { text-align: left; }
+td.bar { width: 20em; text-align: left; }
+td.bar div { background: #4a90d9; height: 1em; }
+";
+
+/// Sorts the table by a column when its header is clicked, by each cell's
+/// `data-v` if it has one, and again to reverse it.
+const SCRIPT: &str = "\
+document.querySelectorAll('th').forEach(function (th, i) {
+  th.addEventListener('click', function () {
+    var body = th.closest('table').tBodies[0];
+    var desc = th.dataset.desc !== 'true';
+    th.dataset.desc = desc;
+    var key = function (row) {
+      var cell = row.cells[i];
+      return cell.dataset.v !== undefined ? parseFloat(cell.dataset.v) : cell.textContent;
+    };
+    Array.from(body.rows).sort(function (a, b) {
+      var x = key(a), y = key(b);
+      var ord = x < y ? -1 : x > y ? 1 : 0;
+      return desc ? -ord : ord;
+    }).forEach(function (row) { body.appendChild(row); });
+  });
+});
+";
+
+/// Escapes `s` for use in HTML text or a quoted attribute.
+fn escape(s: &str) -> String {
+  s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+impl<K> TimerSet<K> {
+  /// Renders the HTML report `write_html_report` writes.
+  fn html_report(&self) -> String {
+    let mut timers = self.sorted_timers();
+    timers.sort_by(|a, b| b.1.total_time.cmp(&a.1.total_time).then_with(|| a.0.cmp(&b.0)));
+    let most = timers.first().map_or(0, |(_, sw)| sw.total_time);
+
+    let mut out = String::new();
+    out.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Timers</title>\n");
+    write!(out, "<style>\n{}</style>\n", STYLE).unwrap();
+    out.push_str("</head>\n<body>\n<table>\n<thead>\n<tr>");
+    for column in ["name", "count", "total", "mean", "p95", "max", "vs. longest"].iter() {
+      write!(out, "<th>{}</th>", column).unwrap();
+    }
+    out.push_str("</tr>\n</thead>\n<tbody>\n");
+    let cell = |out: &mut String, ns: u64| write!(out, "<td data-v=\"{}\">{}</td>", ns, self.format.format(ns)).unwrap();
+    for (name, sw) in timers.iter() {
+      write!(out, "<tr><td>{}</td><td data-v=\"{}\">{}</td>", escape(name), sw.number_of_windows, sw.number_of_windows).unwrap();
+      cell(&mut out, sw.total_time);
+      cell(&mut out, sw.total_time / sw.number_of_windows);
+      match sw.histogram.as_ref().and_then(|h| h.quantile(0.95)) {
+        Some(p95) => cell(&mut out, p95),
+        None => out.push_str("<td data-v=\"-1\">-</td>"),
+      }
+      cell(&mut out, sw.max_time);
+      let width = if most > 0 { 100.0 * sw.total_time as f64 / most as f64 } else { 0.0 };
+      writeln!(out, "<td class=\"bar\" data-v=\"{}\"><div style=\"width: {:.1}%\"></div></td></tr>", sw.total_time, width).unwrap();
+    }
+    out.push_str("</tbody>\n</table>\n");
+    write!(out, "<script>\n{}</script>\n", SCRIPT).unwrap();
+    out.push_str("</body>\n</html>\n");
+    out
+  }
+
+  /// Writes every timer that has run to `path` as a standalone HTML page,
+  /// which can be opened in a browser without anything else. The page has a
+  /// table of each timer's count, total, mean, p95 and max, with a bar of
+  /// its total time, longest first. Clicking a column's header sorts by it.
+  pub fn write_html_report<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+    fs::write(path, self.html_report())
+  }
+}
+
+#[cfg(not(feature = "disable"))]
+#[test]
+fn test_write_html_report() {
+  use std::time::Duration;
+
+  let ts = TimerSet::new();
+  ts.record("<parse>", Duration::from_millis(1));
+  ts.record("render", Duration::from_millis(4));
+  ts.record("render", Duration::from_millis(4));
+  let html = ts.html_report();
+  assert!(html.starts_with("<!DOCTYPE html>"));
+  assert!(html.contains("<tr><td>&lt;parse&gt;</td><td data-v=\"1\">1</td><td data-v=\"1000000\">1.0ms</td>"), "{}", html);
+  let render = html.find("<td>render</td>").unwrap();
+  assert!(render < html.find("<td>&lt;parse&gt;</td>").unwrap());
+  assert!(html[render..].contains("width: 100.0%"));
+  assert!(html.contains("width: 12.5%"));
+
+  let path = ::std::env::temp_dir().join(format!("stopwatch-html-{}.html", ::std::process::id()));
+  ts.write_html_report(&path).unwrap();
+  assert_eq!(fs::read_to_string(&path).unwrap(), html);
+  fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,107 @@
+//! A tiny HTTP server exposing live timer statistics.
+
+use std::io;
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use tiny_http::{Header, Response, Server};
+
+use TimerSet;
+
+/// The path `http_response` serves JSON statistics at.
+pub const STATS_PATH: &str = "/stopwatch/stats";
+/// The path `http_response` serves Prometheus metrics at.
+pub const PROMETHEUS_PATH: &str = "/stopwatch/prometheus";
+
+impl<K: 'static> TimerSet<K> {
+  /// Renders the response for a request to `path`, as a content type and a
+  /// body: JSON statistics at `/stopwatch/stats`, and Prometheus metrics,
+  /// prefixed `stopwatch`, at `/stopwatch/prometheus`. Any query string is
+  /// ignored. Returns `None` for any other path.
+  ///
+  /// This lets the statistics be served by any HTTP framework; `serve_http`
+  /// serves them on its own.
+  pub fn http_response(&self, path: &str) -> Option<(&'static str, String)> {
+    match path.split('?').next().unwrap_or(path) {
+      STATS_PATH => Some(("application/json", self.to_json())),
+      PROMETHEUS_PATH => Some(("text/plain; version=0.0.4", self.to_prometheus("stopwatch"))),
+      _ => None,
+    }
+  }
+
+  /// Starts a thread serving `http_response` over HTTP at `addr`, until the
+  /// returned handle is dropped.
+  pub fn serve_http<A: ToSocketAddrs>(self: &Arc<Self>, addr: A) -> io::Result<HttpServer> {
+    let server = Arc::new(Server::http(addr).map_err(io::Error::other)?);
+    let timers = self.clone();
+    let thread = {
+      let server = server.clone();
+      thread::spawn(move || {
+        for request in server.incoming_requests() {
+          let response =
+            match timers.http_response(request.url()) {
+              None => Response::from_string("not found").with_status_code(404),
+              Some((content_type, body)) => {
+                let header = Header::from_bytes("Content-Type", content_type).unwrap();
+                Response::from_string(body).with_header(header)
+              },
+            };
+          if let Err(e) = request.respond(response) {
+            debug!("Couldn't send stopwatch stats: {}", e);
+          }
+        }
+      })
+    };
+    Ok(HttpServer {
+      server,
+      thread: Some(thread),
+    })
+  }
+}
+
+/// A server started by `TimerSet::serve_http`. Dropping this stops it.
+pub struct HttpServer {
+  server: Arc<Server>,
+  thread: Option<JoinHandle<()>>,
+}
+
+impl HttpServer {
+  /// Returns the address the server is listening on, e.g. to find which port
+  /// it was given.
+  pub fn addr(&self) -> SocketAddr {
+    self.server.server_addr().to_ip().unwrap()
+  }
+}
+
+impl Drop for HttpServer {
+  fn drop(&mut self) {
+    self.server.unblock();
+    if let Some(thread) = self.thread.take() {
+      let _ = thread.join();
+    }
+  }
+}
+
+#[cfg(not(feature = "disable"))]
+#[test]
+fn test_serve_http() {
+  use std::io::{Read, Write};
+  use std::net::TcpStream;
+
+  let timers = Arc::new(TimerSet::new());
+  timers.time("a", || {});
+  let server = timers.serve_http("127.0.0.1:0").unwrap();
+
+  let get = |path: &str| {
+    let mut stream = TcpStream::connect(server.addr()).unwrap();
+    write!(stream, "GET {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n", path).unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    response
+  };
+  let stats = get(STATS_PATH);
+  assert!(stats.starts_with("HTTP/1.1 200"), "{}", stats);
+  assert!(stats.contains("\"name\":\"a\""), "{}", stats);
+  assert!(get(PROMETHEUS_PATH).contains("stopwatch_timer_seconds_count{timer=\"a\"} 1"));
+  assert!(get("/nope").starts_with("HTTP/1.1 404"));
+}
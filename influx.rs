@@ -0,0 +1,70 @@
+//! InfluxDB line protocol export of timer statistics.
+
+use std::fmt::Write;
+
+use {tags, TimerSet};
+
+/// Escapes `s` for use as a measurement name, by backslashing commas and
+/// spaces.
+fn escape_measurement(s: &str) -> String {
+  s.replace(',', "\\,").replace(' ', "\\ ")
+}
+
+/// Escapes `s` for use as a tag key or value, by backslashing commas,
+/// equals signs and spaces. Newlines can't be escaped, so they become
+/// spaces.
+fn escape_tag(s: &str) -> String {
+  s.replace('\n', " ").replace(',', "\\,").replace('=', "\\=").replace(' ', "\\ ")
+}
+
+impl<K> TimerSet<K> {
+  /// Renders every timer as a line of InfluxDB line protocol, e.g.
+  /// `jobs,timer=parse total_ns=3000i,count=2i,mean_ns=1500i,max_ns=2000i`,
+  /// for Telegraf or `influx write`, sorted by name. The timer's name is its
+  /// `timer` tag, alongside its own tags, and durations are in nanoseconds.
+  ///
+  /// Lines have no timestamp, so they're stamped by whatever writes them.
+  pub fn to_influx_line_protocol(&self, measurement: &str) -> String {
+    let measurement = escape_measurement(measurement);
+    let mut out = String::new();
+    for (name, sw) in self.sorted_timers().iter() {
+      let (name, tags) = tags::split_tags(name);
+      write!(out, "{},timer={}", measurement, escape_tag(name)).unwrap();
+      for (k, v) in tags {
+        write!(out, ",{}={}", escape_tag(k), escape_tag(v)).unwrap();
+      }
+      writeln!(
+        out,
+        " total_ns={}i,count={}i,mean_ns={}i,max_ns={}i",
+        sw.total_time,
+        sw.number_of_windows,
+        sw.total_time / sw.number_of_windows,
+        sw.max_time,
+      )
+      .unwrap();
+    }
+    out
+  }
+}
+
+#[test]
+fn test_escape() {
+  assert_eq!(escape_measurement("my app,v2"), "my\\ app\\,v2");
+  assert_eq!(escape_tag("a=b, c\nd"), "a\\=b\\,\\ c\\ d");
+}
+
+#[cfg(not(feature = "disable"))]
+#[test]
+fn test_to_influx_line_protocol() {
+  use std::time::Duration;
+
+  let ts = TimerSet::new();
+  ts.record("parse", Duration::from_micros(1));
+  ts.record("parse", Duration::from_micros(2));
+  ts.record_with_tags("query", &[("table", "users")], Duration::from_micros(5));
+  assert_eq!(ts.to_influx_line_protocol("jobs"), concat!(
+    "jobs,timer=parse total_ns=3000i,count=2i,mean_ns=1500i,max_ns=2000i\n",
+    "jobs,timer=query,table=users total_ns=5000i,count=1i,mean_ns=5000i,max_ns=5000i\n",
+  ));
+  assert_eq!(TimerSet::new().to_influx_line_protocol("jobs"), "");
+}
@@ -0,0 +1,97 @@
+//! The `Instant` timers are stamped with. This is `std::time::Instant`,
+//! except in browsers with the `wasm` feature, where that panics, and
+//! `performance.now()` is read instead, and without `std`, where there's
+//! nothing to read.
+
+#[cfg(all(feature = "std", not(all(target_arch = "wasm32", feature = "wasm"))))]
+pub use std::time::Instant;
+
+#[cfg(not(feature = "std"))]
+pub use self::unclocked::Instant;
+
+#[cfg(all(target_arch = "wasm32", feature = "wasm"))]
+pub use self::performance::Instant;
+
+#[cfg(all(target_arch = "wasm32", feature = "wasm"))]
+mod performance {
+  use std::ops::Sub;
+  use std::time::Duration;
+  use web_sys;
+
+  #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+  /// A reading of `performance.now()`, in nanoseconds since the page or
+  /// worker started.
+  pub struct Instant(u64);
+
+  /// Reads `performance.now()` from the window, or from the worker if there
+  /// isn't one.
+  fn performance_now() -> f64 {
+    use web_sys::wasm_bindgen::JsCast;
+
+    let global = web_sys::js_sys::global();
+    let performance =
+      match global.dyn_ref::<web_sys::Window>() {
+        Some(window) => window.performance(),
+        None => global.dyn_ref::<web_sys::WorkerGlobalScope>().and_then(|worker| worker.performance()),
+      };
+    performance.expect("performance.now() isn't available").now()
+  }
+
+  impl Instant {
+    /// Reads `performance.now()`.
+    pub fn now() -> Instant {
+      // performance.now() is in milliseconds.
+      Instant((performance_now() * 1e6) as u64)
+    }
+
+    /// How long it's been since this instant.
+    pub fn elapsed(&self) -> Duration {
+      Instant::now().saturating_duration_since(*self)
+    }
+
+    /// How long it's been from `earlier` to this instant, or zero if
+    /// `earlier` is later.
+    pub fn saturating_duration_since(&self, earlier: Instant) -> Duration {
+      Duration::from_nanos(self.0.saturating_sub(earlier.0))
+    }
+
+    /// The instant `d` before this one, if it's not before the page or
+    /// worker started.
+    pub fn checked_sub(&self, d: Duration) -> Option<Instant> {
+      let d = d.as_secs().checked_mul(1_000_000_000)?.checked_add(u64::from(d.subsec_nanos()))?;
+      self.0.checked_sub(d).map(Instant)
+    }
+  }
+
+  impl Sub for Instant {
+    type Output = Duration;
+
+    fn sub(self, earlier: Instant) -> Duration {
+      self.saturating_duration_since(earlier)
+    }
+  }
+}
+
+#[cfg(not(feature = "std"))]
+mod unclocked {
+  use core::time::Duration;
+
+  #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+  /// Without `std` there's no clock to stamp windows with, so every instant
+  /// is the same one.
+  pub struct Instant;
+
+  impl Instant {
+    pub fn now() -> Instant {
+      Instant
+    }
+
+    pub fn saturating_duration_since(&self, _: Instant) -> Duration {
+      Duration::ZERO
+    }
+
+    pub fn checked_sub(&self, _: Duration) -> Option<Instant> {
+      Some(Instant)
+    }
+  }
+}
@@ -0,0 +1,159 @@
+//! Timer names, interned so each is stored once, however many threads,
+//! sets and reports share it.
+
+use std::borrow::Borrow;
+use std::cmp::Ordering;
+use std::collections::HashSet;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::Deref;
+use std::sync::{Arc, Mutex};
+
+use lock;
+
+/// Every name interned so far, and how many there were when names no
+/// longer in use were last dropped.
+static NAMES: Mutex<Option<(HashSet<Arc<str>>, usize)>> = Mutex::new(None);
+
+/// Returns the shared copy of `name`, making it if there isn't one. Names
+/// are dropped once nothing but the pool holds them, a batch at a time as
+/// the pool grows.
+fn intern(name: &str) -> Arc<str> {
+  let mut names = lock(&NAMES);
+  let (names, pruned) = names.get_or_insert_with(|| (HashSet::new(), 0));
+  if let Some(interned) = names.get(name) {
+    return interned.clone();
+  }
+  if names.len() >= 64 && names.len() >= *pruned * 2 {
+    names.retain(|name| Arc::strong_count(name) > 1);
+    *pruned = names.len();
+  }
+  let interned: Arc<str> = Arc::from(name);
+  names.insert(interned.clone());
+  interned
+}
+
+#[derive(Clone)]
+/// A timer's name. Names that live forever are borrowed, and any other is
+/// interned, so every timer with the same name, in any thread's shard or
+/// any set, shares one copy, unless its set doesn't intern names.
+pub(crate) enum Name {
+  Static(&'static str),
+  Interned(Arc<str>),
+  /// A copy of the name of its own, made without going through the pool.
+  Copied(Arc<str>),
+}
+
+impl Name {
+  /// Interns `name`.
+  pub(crate) fn new(name: &str) -> Name {
+    Name::Interned(intern(name))
+  }
+
+  /// Copies `name`, without interning it.
+  pub(crate) fn copied(name: &str) -> Name {
+    Name::Copied(Arc::from(name))
+  }
+}
+
+impl Deref for Name {
+  type Target = str;
+
+  fn deref(&self) -> &str {
+    match *self {
+      Name::Static(name) => name,
+      Name::Interned(ref name) | Name::Copied(ref name) => name,
+    }
+  }
+}
+
+impl AsRef<str> for Name {
+  fn as_ref(&self) -> &str {
+    self
+  }
+}
+
+impl Borrow<str> for Name {
+  fn borrow(&self) -> &str {
+    self
+  }
+}
+
+impl PartialEq for Name {
+  fn eq(&self, other: &Name) -> bool {
+    **self == **other
+  }
+}
+
+impl Eq for Name {}
+
+impl PartialEq<str> for Name {
+  fn eq(&self, other: &str) -> bool {
+    &**self == other
+  }
+}
+
+impl<'a> PartialEq<&'a str> for Name {
+  fn eq(&self, other: &&'a str) -> bool {
+    &**self == *other
+  }
+}
+
+/// Hashes like the `str`, so timers can be looked up by `&str`.
+impl Hash for Name {
+  fn hash<H: Hasher>(&self, state: &mut H) {
+    (**self).hash(state)
+  }
+}
+
+impl PartialOrd for Name {
+  fn partial_cmp(&self, other: &Name) -> Option<Ordering> {
+    Some(self.cmp(other))
+  }
+}
+
+impl Ord for Name {
+  fn cmp(&self, other: &Name) -> Ordering {
+    (**self).cmp(&**other)
+  }
+}
+
+impl fmt::Debug for Name {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    fmt::Debug::fmt(&**self, f)
+  }
+}
+
+impl fmt::Display for Name {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    f.write_str(self)
+  }
+}
+
+#[test]
+fn test_intern() {
+  let a = Name::new("test_intern");
+  let name = String::from("test_intern");
+  let b = Name::new(&name);
+  let interned =
+    match (&a, &b) {
+      (Name::Interned(a), Name::Interned(b)) if Arc::ptr_eq(a, b) => a,
+      _ => panic!("{:?} and {:?} weren't interned together", a, b),
+    };
+  assert_eq!(a, Name::Static("test_intern"));
+  assert_eq!(a.to_string(), "test_intern");
+
+  // Every thread's shard keys the timer by the same copy of its name.
+  let ts = ::TimerSet::new();
+  ::std::thread::scope(|s| {
+    for _ in 0..2 {
+      s.spawn(|| ts.time(&name, || {}));
+    }
+  });
+  for shard in ts.shards.lock().unwrap().iter() {
+    match shard.lock().unwrap().timers.get_key_value("test_intern") {
+      Some((Name::Interned(key), _)) => assert!(Arc::ptr_eq(key, interned)),
+      key => panic!("{:?}", key.map(|(key, _)| key)),
+    }
+  }
+}
@@ -0,0 +1,72 @@
+//! JSON export of timer statistics.
+
+use serde_json;
+
+use {to_nanos, TimerSet};
+
+#[derive(Serialize)]
+struct TimerStats<'a> {
+  name: &'a str,
+  total_ns: u64,
+  count: u64,
+  avg_ns: u64,
+  ewma_ns: u64,
+  min_ns: u64,
+  max_ns: u64,
+  p50_ns: Option<u64>,
+  p90_ns: Option<u64>,
+  p99_ns: Option<u64>,
+  violations: u64,
+  self_ns: u64,
+}
+
+impl<K> TimerSet<K> {
+  /// Renders every timer's statistics as a JSON array, sorted by name.
+  /// Durations are in whole nanoseconds, so fast timers keep their
+  /// resolution.
+  pub fn to_json(&self) -> String {
+    let timers = self.sorted_timers();
+    let stats: Vec<TimerStats> =
+      timers
+        .iter()
+        .map(|(name, sw)| {
+          TimerStats {
+            name,
+            total_ns: sw.total_time,
+            count: sw.number_of_windows,
+            avg_ns: sw.total_time / sw.number_of_windows,
+            ewma_ns: sw.ewma.round() as u64,
+            min_ns: sw.min_time,
+            max_ns: sw.max_time,
+            p50_ns: sw.quantile(0.5).map(to_nanos),
+            p90_ns: sw.quantile(0.9).map(to_nanos),
+            p99_ns: sw.quantile(0.99).map(to_nanos),
+            violations: sw.violations,
+            self_ns: to_nanos(sw.self_time()),
+          }
+        })
+        .collect();
+    serde_json::to_string(&stats).unwrap()
+  }
+}
+
+#[cfg(not(feature = "disable"))]
+#[test]
+fn test_to_json() {
+  use std::time::Duration;
+
+  let ts = TimerSet::new();
+  ts.time("hello", || {});
+  ts.record("fast", Duration::from_nanos(250));
+  ts.record("fast", Duration::from_nanos(750));
+  let json: serde_json::Value = serde_json::from_str(&ts.to_json()).unwrap();
+  assert_eq!(json[1]["name"], "hello");
+  assert_eq!(json[1]["count"], 1);
+  // Sub-microsecond timers aren't rounded away.
+  assert_eq!(json[0]["name"], "fast");
+  assert_eq!(json[0]["total_ns"], 1_000);
+  assert_eq!(json[0]["avg_ns"], 500);
+  assert_eq!(json[0]["min_ns"], 250);
+  assert_eq!(json[0]["max_ns"], 750);
+  assert!(json[0]["ewma_ns"].as_u64().unwrap() > 0);
+}
@@ -0,0 +1,150 @@
+//! Timers keyed by something other than a string, e.g. an enum, so a
+//! misspelt timer is a compile error.
+
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{BuildHasherDefault, Hash};
+use std::time::Duration;
+
+use fnv::FnvHasher;
+
+use {lock, to_nanos, Stopwatch, StopwatchConfig, TimerHandle, TimerSet};
+
+/// A key for a `TimerSet<K>`'s timers.
+pub trait TimerKey: Eq + Hash + Ord + Clone + Send + 'static {
+  /// The name the key's timer reports under.
+  fn name(&self) -> Cow<'_, str>;
+}
+
+impl TimerKey for String {
+  fn name(&self) -> Cow<'_, str> {
+    Cow::Borrowed(self)
+  }
+}
+
+impl TimerKey for &'static str {
+  fn name(&self) -> Cow<'_, str> {
+    Cow::Borrowed(self)
+  }
+}
+
+/// The handles of the keys a thread has timed under, kept in its shard.
+type KeyHandles<K> = HashMap<K, TimerHandle, BuildHasherDefault<FnvHasher>>;
+
+/// A set keyed by `K`. Each key is registered under its name the first time
+/// a thread times under it, so from then on, timing under it only hashes
+/// the key, e.g. an enum's discriminant, rather than the timer's name.
+///
+/// Reports and exports list the keys' timers under their names.
+impl<K: TimerKey> TimerSet<K> {
+  /// Creates an empty set keyed by `K`.
+  pub fn keyed() -> TimerSet<K> {
+    TimerSet::from_shards(StopwatchConfig::default(), false, Vec::new())
+  }
+
+  /// Returns the handle of the key's timer, registering it if this thread
+  /// hasn't timed under it yet.
+  fn key_handle(&self, key: &K) -> TimerHandle {
+    let handle = self.with_shard_timers(|shard| {
+      shard.keys.as_ref().and_then(|keys| keys.downcast_ref::<KeyHandles<K>>()).and_then(|keys| keys.get(key).cloned())
+    });
+    if let Some(handle) = handle {
+      return handle;
+    }
+    // Registered outside the shard's lock, as reading the set takes the
+    // registered names' lock before the shards'.
+    let handle = self.register(&key.name());
+    self.with_shard_timers(|shard| {
+      let keys = shard.keys.get_or_insert_with(|| Box::new(KeyHandles::<K>::default()));
+      keys.downcast_mut::<KeyHandles<K>>().unwrap().insert(key.clone(), handle);
+    });
+    handle
+  }
+
+  /// Times `f` under the key's timer, like `TimerSet::time_handle`.
+  pub fn time_key<T, F: FnOnce() -> T>(&self, key: &K, f: F) -> T {
+    if !self.is_enabled() {
+      return f();
+    }
+    let handle = self.key_handle(key);
+    self.time_handle(handle, f)
+  }
+
+  /// Logs a window measured elsewhere under the key's timer, like
+  /// `TimerSet::record`.
+  pub fn record_key(&self, key: &K, d: Duration) {
+    if !self.is_enabled() {
+      return;
+    }
+    let handle = self.key_handle(key);
+    self.with_shard_timers(|shard| shard.handle_mut(handle.index, self.config).add_window(to_nanos(d)));
+  }
+
+  /// Returns the key's timer merged across every thread, or `None` if it
+  /// never ran.
+  pub fn get_key(&self, key: &K) -> Option<Stopwatch> {
+    self.stopwatches_by_key().remove(key)
+  }
+
+  /// Returns the timer of every key that's run, merged across every thread,
+  /// in key order.
+  pub fn stopwatches_by_key(&self) -> BTreeMap<K, Stopwatch> {
+    let shards = lock(&self.shards);
+    let mut handles = BTreeMap::new();
+    for shard in shards.iter() {
+      if let Some(keys) = lock(shard).keys.as_ref().and_then(|keys| keys.downcast_ref::<KeyHandles<K>>()) {
+        handles.extend(keys.iter().map(|(key, handle)| (key.clone(), handle.index)));
+      }
+    }
+    let mut stopwatches = BTreeMap::new();
+    for shard in shards.iter() {
+      let shard = lock(shard);
+      for (key, &index) in handles.iter() {
+        if let Some(sw) = shard.handles.get(index).filter(|sw| sw.number_of_windows > 0) {
+          stopwatches.entry(key.clone()).or_insert_with(|| Stopwatch::with_config(self.config)).merge(sw);
+        }
+      }
+    }
+    stopwatches
+  }
+}
+
+#[cfg(not(feature = "disable"))]
+#[test]
+fn test_keyed_timer_set() {
+  #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+  enum Phase {
+    Parse,
+    Render,
+    Idle,
+  }
+
+  impl TimerKey for Phase {
+    fn name(&self) -> Cow<'_, str> {
+      Cow::Borrowed(match *self {
+        Phase::Parse => "parse",
+        Phase::Render => "render",
+        Phase::Idle => "idle",
+      })
+    }
+  }
+
+  let ts = TimerSet::<Phase>::keyed();
+  assert_eq!(ts.time_key(&Phase::Parse, || 7), 7);
+  ts.time_key(&Phase::Parse, || {});
+  ts.record_key(&Phase::Render, Duration::from_millis(1));
+  ::std::thread::scope(|s| {
+    s.spawn(|| ts.time_key(&Phase::Parse, || {}));
+  });
+  assert_eq!(ts.get_key(&Phase::Parse).unwrap().number_of_windows, 3);
+  assert_eq!(ts.get_key(&Phase::Render).unwrap().total_time, 1_000_000);
+  assert_eq!(ts.get_key(&Phase::Idle), None);
+  let stopwatches = ts.stopwatches_by_key();
+  assert_eq!(stopwatches.keys().cloned().collect::<Vec<_>>(), vec![Phase::Parse, Phase::Render]);
+  assert_eq!(ts.merged()["parse"].number_of_windows, 3);
+
+  let names = TimerSet::builder().histogram(false).build_keyed::<String>();
+  names.time_key(&"a".to_string(), || {});
+  assert_eq!(names.get_key(&"a".to_string()).unwrap().number_of_windows, 1);
+  assert_eq!(names.merged()["a"].quantile(0.5), None);
+}
@@ -1,151 +1,2043 @@
 //! Closure-timing data structure.
+//!
+//! Without the default `std` feature, `Stopwatch`, `Clock` and a `TimerSet`
+//! timed with a user-supplied clock are available. That set isn't shared
+//! between threads, and has none of the std set's reporting.
 
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg_attr(not(feature = "std"), macro_use)]
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate core;
+#[cfg(feature = "std")]
 extern crate fnv;
-extern crate time;
-#[macro_use]
+#[cfg(all(feature = "rayon", any(unix, windows)))]
+extern crate rayon;
+#[cfg(feature = "regex")]
+extern crate regex;
+#[cfg(all(unix, any(feature = "cputime", feature = "shm", feature = "signal")))]
+extern crate libc;
+#[cfg_attr(feature = "std", macro_use)]
 extern crate log;
-extern crate x86;
+#[cfg(feature = "metrics")]
+extern crate metrics;
+#[cfg(feature = "otel")]
+extern crate opentelemetry;
+#[cfg(feature = "serde")]
+extern crate serde;
+#[cfg(feature = "serde")]
+#[macro_use]
+extern crate serde_derive;
+#[cfg(feature = "serde")]
+extern crate serde_json;
+#[cfg(feature = "macros")]
+extern crate stopwatch_macros;
+#[cfg(feature = "http")]
+extern crate tiny_http;
+#[cfg(feature = "tokio")]
+extern crate tokio;
+#[cfg(feature = "tracing")]
+extern crate tracing;
+#[cfg(feature = "tracing")]
+extern crate tracing_subscriber;
+#[cfg(all(target_arch = "wasm32", feature = "wasm"))]
+extern crate web_sys;
+#[cfg(all(windows, feature = "cputime"))]
+extern crate windows_sys;
+// Lets `#[timed]`'s expansion refer to this crate from its own tests.
+#[cfg(all(test, feature = "macros"))]
+extern crate self as stopwatch;
+
+#[cfg(feature = "std")]
+#[macro_use]
+mod macros;
 
+#[cfg(feature = "std")]
+mod allocations;
+#[cfg(feature = "std")]
+mod assertions;
+#[cfg(feature = "std")]
+mod async_report;
+#[cfg(feature = "std")]
+mod atomic;
+#[cfg(feature = "std")]
+mod bench;
+#[cfg(feature = "std")]
+mod blocking;
+#[cfg(feature = "std")]
+mod budgets;
+#[cfg(feature = "std")]
+mod builder;
+#[cfg(feature = "std")]
+mod cardinality;
+#[cfg(feature = "std")]
+mod cell;
+#[cfg(feature = "std")]
+mod child;
+mod clock;
+#[cfg(feature = "color")]
+mod color;
+#[cfg(all(feature = "cputime", any(unix, windows)))]
+mod cputime;
+#[cfg(feature = "std")]
+mod counters;
+#[cfg(feature = "std")]
+mod csv;
+#[cfg(feature = "std")]
+mod diff;
+#[cfg(feature = "std")]
+mod display;
+#[cfg(feature = "std")]
+mod env;
+#[cfg(feature = "std")]
+mod folded;
+mod format;
+#[cfg(feature = "std")]
+mod frame;
+#[cfg(feature = "std")]
+mod future;
+#[cfg(feature = "std")]
+mod gauges;
+#[cfg(feature = "std")]
+mod handle;
+#[cfg(not(feature = "tdigest"))]
+mod histogram;
+#[cfg(feature = "std")]
+mod history;
+#[cfg(feature = "std")]
+mod html;
+#[cfg(feature = "http")]
+mod http;
+#[cfg(feature = "std")]
+mod influx;
+mod instant;
+#[cfg(feature = "std")]
+mod intern;
+#[cfg(feature = "serde")]
+mod json;
+#[cfg(feature = "std")]
+mod keyed;
+#[cfg(feature = "std")]
+mod markdown;
+#[cfg(feature = "metrics")]
+mod metrics_recorder;
+#[cfg(not(feature = "std"))]
+mod nostd;
+mod ops;
+#[cfg(feature = "otel")]
+mod otel;
+#[cfg(feature = "std")]
+mod overhead;
+#[cfg(feature = "std")]
+mod panics;
+#[cfg(all(feature = "rayon", any(unix, windows)))]
+mod parallel;
+#[cfg(feature = "serde")]
+mod persist;
+#[cfg(feature = "std")]
+mod phases;
+#[cfg(feature = "std")]
+mod process;
+#[cfg(feature = "std")]
+mod prometheus;
+#[cfg(feature = "std")]
+mod recursion;
+#[cfg(feature = "std")]
+mod registry;
+#[cfg(feature = "std")]
+mod report;
+#[cfg(feature = "tokio")]
+mod runtime;
+#[cfg(feature = "std")]
+mod self_time;
+#[cfg(all(unix, feature = "shm"))]
+mod shm;
+#[cfg(all(unix, feature = "signal"))]
+mod signal;
+#[cfg(feature = "std")]
+mod slowest;
+#[cfg(feature = "std")]
+mod snapshot;
+#[cfg(feature = "std")]
+mod spans;
+mod stats;
+#[cfg(feature = "statsd")]
+mod statsd;
+#[cfg(feature = "tdigest")]
+mod tdigest;
+#[cfg(feature = "std")]
+mod tags;
+#[cfg(feature = "std")]
+mod thresholds;
+#[cfg(feature = "std")]
+mod timeouts;
+#[cfg(feature = "std")]
+mod trace;
+#[cfg(feature = "tracing")]
+mod tracing_layer;
+#[cfg(all(feature = "std", any(target_arch = "x86", target_arch = "x86_64")))]
 mod tsc;
 
+#[cfg(feature = "std")]
+pub use allocations::CountingAllocator;
+#[cfg(feature = "std")]
+pub use async_report::AsyncReporter;
+#[cfg(feature = "std")]
+pub use atomic::AtomicStopwatch;
+pub use clock::Clock;
+#[cfg(all(feature = "std", any(target_arch = "x86", target_arch = "x86_64")))]
+pub use clock::TscClock;
+#[cfg(all(feature = "cputime", any(unix, windows)))]
+pub use clock::{ProcessCpuClock, ThreadCpuClock};
+#[cfg(feature = "std")]
+pub use bench::BenchResult;
+#[cfg(feature = "std")]
+pub use blocking::{timed_lock, timed_read, timed_recv, timed_wait, timed_write};
+#[cfg(feature = "std")]
+pub use builder::TimerSetBuilder;
+#[cfg(feature = "std")]
+pub use cardinality::OVERFLOW_TIMER;
+#[cfg(feature = "std")]
+pub use cell::StopwatchCell;
+#[cfg(feature = "std")]
+pub use child::TimerSetHandle;
+#[cfg(feature = "std")]
+pub use clock::{FastClock, InstantClock};
+#[cfg(feature = "std")]
+pub use diff::{DiffReport, TimerDiff, DEFAULT_REGRESSION_THRESHOLD};
+#[cfg(feature = "std")]
+pub use display::NamedStopwatch;
+#[cfg(feature = "std")]
+pub use env::{init_from_env, ENV_VAR};
+pub use format::{DurationFormat, TimeUnit};
+#[cfg(feature = "std")]
+pub use frame::FrameProfiler;
+#[cfg(feature = "std")]
+pub use future::{TimedFuture, TimedFutureExt};
+#[cfg(feature = "std")]
+pub use gauges::Gauge;
+#[cfg(feature = "std")]
+pub use handle::TimerHandle;
+#[cfg(feature = "http")]
+pub use http::{HttpServer, PROMETHEUS_PATH, STATS_PATH};
+pub use instant::Instant;
+#[cfg(feature = "std")]
+pub use keyed::TimerKey;
+#[cfg(feature = "metrics")]
+pub use metrics_recorder::TimerRecorder;
+#[cfg(not(feature = "std"))]
+pub use nostd::TimerSet;
+#[cfg(feature = "otel")]
+pub use otel::OtelExporter;
+#[cfg(feature = "std")]
+pub use panics::OnPanic;
+#[cfg(all(feature = "rayon", any(unix, windows)))]
+pub use parallel::{ParallelTimer, TimedParallelIterator};
+#[cfg(feature = "std")]
+pub use process::TimedCommand;
+#[cfg(feature = "std")]
+pub use registry::{merge_threads, print_threads, report_threads, report_threads_with, threads};
+#[cfg(feature = "std")]
+pub use report::{LogReporter, Report, Reporter, ReporterHandle, SortBy, SortOrder, WriteReporter};
+#[cfg(feature = "tokio")]
+pub use runtime::BUSY_TIMER;
+#[cfg(all(unix, feature = "shm"))]
+pub use shm::{SharedTimerSet, MAX_SHARED_NAME_LEN};
+#[cfg(all(unix, feature = "signal"))]
+pub use signal::dump_on_signal;
+#[cfg(feature = "std")]
+pub use slowest::SlowWindow;
+#[cfg(feature = "std")]
+pub use snapshot::TimerSetSnapshot;
+pub use stats::StopwatchStats;
+#[cfg(feature = "statsd")]
+pub use statsd::StatsdExporter;
+#[cfg(all(feature = "std", any(target_arch = "x86", target_arch = "x86_64")))]
+pub use tsc::TscDuration;
+#[cfg(feature = "macros")]
+pub use stopwatch_macros::timed;
+#[cfg(feature = "tracing")]
+pub use tracing_layer::TimerLayer;
+
+#[cfg(feature = "std")]
+use allocations::Allocations;
+#[cfg(feature = "std")]
+use counters::{merge_counters, new_counters, Counters};
+#[cfg(feature = "std")]
 use fnv::FnvHasher;
+#[cfg(feature = "std")]
+use gauges::{merge_gauges, Gauges};
+#[cfg(not(feature = "tdigest"))]
+use histogram::Histogram;
+#[cfg(feature = "std")]
+use history::Histories;
+#[cfg(feature = "std")]
+use intern::Name;
+#[cfg(feature = "tdigest")]
+use tdigest::TDigest as Histogram;
+#[cfg(feature = "std")]
+use thresholds::Threshold;
 
-use std::collections::HashMap;
+#[cfg(not(feature = "std"))]
+use alloc::boxed::Box;
+use core::time::Duration;
+#[cfg(feature = "std")]
+use std::any::Any;
+#[cfg(feature = "std")]
+use std::borrow::Cow;
+#[cfg(feature = "std")]
+use std::cell::RefCell;
+#[cfg(feature = "std")]
+use std::collections::{HashMap, HashSet};
+#[cfg(feature = "std")]
 use std::convert::AsRef;
+#[cfg(feature = "std")]
 use std::hash::BuildHasherDefault;
-use std::sync::Mutex;
+#[cfg(feature = "std")]
+use std::marker::PhantomData;
+#[cfg(feature = "std")]
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+#[cfg(feature = "std")]
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError, Weak};
+
+/// Converts a duration to whole nanoseconds, saturating on overflow.
+fn to_nanos(d: Duration) -> u64 {
+  d.as_secs()
+    .saturating_mul(1_000_000_000)
+    .saturating_add(d.subsec_nanos() as u64)
+}
+
+/// The whole part of the square root of `x`, which `core` can't take of
+/// floats.
+fn sqrt(x: f64) -> u64 {
+  (x as u128).isqrt() as u64
+}
+
+/// `x` to the power of `n`, which `core` can't take of floats either.
+fn powu(mut x: f64, mut n: u64) -> f64 {
+  let mut y = 1.0;
+  while n > 0 {
+    if n & 1 == 1 {
+      y *= x;
+    }
+    x *= x;
+    n >>= 1;
+  }
+  y
+}
 
 #[derive(Debug, Copy, Clone)]
+/// Controls which optional stats a `Stopwatch` keeps.
+pub struct StopwatchConfig {
+  /// Whether to keep a histogram of window durations, so percentiles can be
+  /// reported. This costs about 2KB per stopwatch.
+  ///
+  /// With the `tdigest` feature, a t-digest is kept instead, which gives
+  /// more accurate percentiles in about as much memory, but is slower to
+  /// update.
+  pub histogram: bool,
+  /// How much each window moves the exponential moving average of windows,
+  /// between 0 and 1. Higher values follow recent windows more closely.
+  pub ewma_alpha: f64,
+}
+
+impl Default for StopwatchConfig {
+  fn default() -> StopwatchConfig {
+    StopwatchConfig {
+      histogram: true,
+      ewma_alpha: 0.1,
+    }
+  }
+}
+
+#[cfg(feature = "std")]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+/// What `TimerSet::set_warmup` does with a timer's first windows.
+pub enum Warmup {
+  /// Forgets them.
+  Discard,
+  /// Logs them under a timer of their own, named after the timer with
+  /// `.warmup` appended, e.g. `parse.warmup`.
+  Separate,
+}
+
+#[derive(Debug, Clone)]
 /// A simple stopwatch that can time events and print stats about them.
 pub struct Stopwatch {
-  /// The total amount of time clocked.
+  /// The total amount of time clocked, in nanoseconds.
   pub total_time: u64,
   /// The number of time windows we've clocked.
   pub number_of_windows: u64,
+  /// The shortest time window clocked, in nanoseconds.
+  min_time: u64,
+  /// The longest time window clocked, in nanoseconds.
+  max_time: u64,
+  /// The running mean of the time windows, in nanoseconds.
+  mean: f64,
+  /// The running sum of squared differences from the mean, for Welford's
+  /// variance algorithm. `NaN` if unknown.
+  m2: f64,
+  /// The exponential moving average of the time windows, in nanoseconds.
+  ewma: f64,
+  /// How much each window moves `ewma`.
+  ewma_alpha: f64,
+  /// When the first time window clocked started, if known.
+  first_start: Option<Instant>,
+  /// When the last time window clocked ended, if known.
+  last_end: Option<Instant>,
+  /// The distribution of time windows, if we're keeping one.
+  histogram: Option<Box<Histogram>>,
+  /// How many more calls a sampled timer skips before timing the next one.
+  unsampled: u64,
+  /// How many windows have been set aside as warm-up. This survives
+  /// resets, since whatever was warmed up still is.
+  #[cfg(feature = "std")]
+  warmup_windows: u64,
+  /// How long the window opened by `start` has run so far, if one is open.
+  open_window: Option<u64>,
+  /// When the open window was last started or resumed, unless it's paused.
+  resumed_at: Option<Instant>,
+  /// The number of allocations made in the time windows clocked, if they're
+  /// being counted by a `CountingAllocator`.
+  allocations: u64,
+  /// The total size of those allocations, in bytes.
+  allocated_bytes: u64,
+  /// How many windows timed with `TimerSet::time_with_timeout` went over
+  /// their timeout.
+  violations: u64,
+  /// How much of the time clocked was spent in other timers of the same set
+  /// nested inside this one, in nanoseconds.
+  child_time: u64,
+}
+
+impl Default for Stopwatch {
+  fn default() -> Stopwatch {
+    Stopwatch::new()
+  }
 }
 
 impl Stopwatch {
   #[inline]
   /// Creates a new stopwatch.
   pub fn new() -> Stopwatch {
+    Stopwatch::with_config(StopwatchConfig::default())
+  }
+
+  #[inline]
+  /// Creates a new stopwatch which keeps the stats specified by `config`.
+  pub fn with_config(config: StopwatchConfig) -> Stopwatch {
     Stopwatch {
       total_time: 0,
       number_of_windows: 0,
+      min_time: u64::MAX,
+      max_time: 0,
+      mean: 0.0,
+      m2: 0.0,
+      ewma: 0.0,
+      ewma_alpha: config.ewma_alpha,
+      first_start: None,
+      last_end: None,
+      histogram: if config.histogram { Some(Box::new(Histogram::new())) } else { None },
+      unsampled: 0,
+      #[cfg(feature = "std")]
+      warmup_windows: 0,
+      open_window: None,
+      resumed_at: None,
+      allocations: 0,
+      allocated_bytes: 0,
+      violations: 0,
+      child_time: 0,
+    }
+  }
+
+  #[inline]
+  /// Returns the shortest time window clocked, or `None` if we've never run.
+  pub fn min_time(&self) -> Option<Duration> {
+    if self.number_of_windows == 0 {
+      None
+    } else {
+      Some(Duration::from_nanos(self.min_time))
     }
   }
 
+  #[inline]
+  /// Returns the longest time window clocked, or `None` if we've never run.
+  pub fn max_time(&self) -> Option<Duration> {
+    if self.number_of_windows == 0 {
+      None
+    } else {
+      Some(Duration::from_nanos(self.max_time))
+    }
+  }
+
+  /// Returns the sample variance of the time windows clocked, in square
+  /// nanoseconds, or `None` if we've never run.
+  pub fn variance(&self) -> Option<f64> {
+    if self.number_of_windows == 0 || self.m2.is_nan() {
+      None
+    } else if self.number_of_windows == 1 {
+      Some(0.0)
+    } else {
+      Some(self.m2 / (self.number_of_windows - 1) as f64)
+    }
+  }
+
+  /// Returns the exponential moving average of the time windows clocked,
+  /// which follows recent windows rather than every window ever clocked, or
+  /// `None` if we've never run. `StopwatchConfig::ewma_alpha` sets how
+  /// closely.
+  pub fn ewma(&self) -> Option<Duration> {
+    if self.number_of_windows == 0 {
+      None
+    } else {
+      Some(Duration::from_nanos(self.ewma as u64))
+    }
+  }
+
+  /// Returns the sample standard deviation of the time windows clocked, or
+  /// `None` if we've never run.
+  pub fn stddev(&self) -> Option<Duration> {
+    self.variance().map(|v| Duration::from_nanos(sqrt(v)))
+  }
+
+  /// Returns the number of allocations made in the time windows clocked.
+  /// This is zero unless a `CountingAllocator` is the global allocator.
+  pub fn allocations(&self) -> u64 {
+    self.allocations
+  }
+
+  /// Returns the total size, in bytes, of the allocations made in the time
+  /// windows clocked.
+  pub fn allocated_bytes(&self) -> u64 {
+    self.allocated_bytes
+  }
+
+  /// Returns the time clocked that wasn't spent in other timers of the same
+  /// set nested inside this one, e.g. for `a` timing `b`, the time `a` spent
+  /// itself. Summed over every timer, this adds up to the time timed, where
+  /// `total_time` counts nested windows more than once.
+  pub fn self_time(&self) -> Duration {
+    Duration::from_nanos(self.total_time.saturating_sub(self.child_time))
+  }
+
+  /// Returns how many windows went over their timeout, of those timed with
+  /// `TimerSet::time_with_timeout`.
+  pub fn violations(&self) -> u64 {
+    self.violations
+  }
+
+  /// Returns the time from when the first window clocked started until the
+  /// last one ended, or `None` if we've never run. Without `std`, windows
+  /// aren't stamped, so this is zero.
+  pub fn observed_interval(&self) -> Option<Duration> {
+    match (self.first_start, self.last_end) {
+      (Some(first), Some(last)) => Some(last.saturating_duration_since(first)),
+      _ => None,
+    }
+  }
+
+  /// Returns the throughput, in windows per second, over the observed
+  /// interval, or `None` if we've never run or no time has been observed.
+  pub fn rate(&self) -> Option<f64> {
+    self.observed_interval()
+      .map(to_nanos)
+      .and_then(|ns| if ns == 0 { None } else { Some(self.number_of_windows as f64 * 1e9 / ns as f64) })
+  }
+
+  /// Forgets every time window clocked so far.
+  pub fn reset(&mut self) {
+    self.total_time = 0;
+    self.number_of_windows = 0;
+    self.min_time = u64::MAX;
+    self.max_time = 0;
+    self.mean = 0.0;
+    self.m2 = 0.0;
+    self.first_start = None;
+    self.last_end = None;
+    self.unsampled = 0;
+    self.open_window = None;
+    self.resumed_at = None;
+    self.allocations = 0;
+    self.allocated_bytes = 0;
+    self.violations = 0;
+    self.child_time = 0;
+    if let Some(ref mut histogram) = self.histogram {
+      histogram.clear();
+    }
+  }
+
+  /// Folds all of `other`'s time windows into this stopwatch.
+  ///
+  /// If `other` has windows but isn't keeping a histogram, this stopwatch
+  /// stops keeping one too, since it could no longer account for every window.
+  pub fn merge(&mut self, other: &Stopwatch) {
+    if other.number_of_windows == 0 {
+      return;
+    }
+    // Chan et al.'s parallel variant of Welford's algorithm.
+    let n_a = self.number_of_windows as f64;
+    let n_b = other.number_of_windows as f64;
+    let n = n_a + n_b;
+    let delta = other.mean - self.mean;
+    self.mean += delta * n_b / n;
+    self.m2 += other.m2 + delta * delta * n_a * n_b / n;
+    // There's no telling which windows were more recent, so weigh each side
+    // by how many it has.
+    self.ewma = (self.ewma * n_a + other.ewma * n_b) / n;
+
+    self.total_time += other.total_time;
+    self.number_of_windows += other.number_of_windows;
+    self.allocations += other.allocations;
+    self.allocated_bytes += other.allocated_bytes;
+    self.violations += other.violations;
+    self.child_time += other.child_time;
+    self.first_start =
+      match (self.first_start, other.first_start) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, b) => a.or(b),
+      };
+    self.last_end =
+      match (self.last_end, other.last_end) {
+        (Some(a), Some(b)) => Some(a.max(b)),
+        (a, b) => a.or(b),
+      };
+    if other.min_time < self.min_time {
+      self.min_time = other.min_time;
+    }
+    if other.max_time > self.max_time {
+      self.max_time = other.max_time;
+    }
+    match other.histogram {
+      None => self.histogram = None,
+      Some(ref other) =>
+        if let Some(ref mut histogram) = self.histogram {
+          histogram.merge(other);
+        },
+    }
+  }
+
+  /// Takes `overhead` off every window clocked, e.g. the cost of timing
+  /// itself measured by `TimerSet::calibrate_overhead`. Windows shorter than
+  /// that count as zero. Variance and percentiles are left as they were.
+  pub fn compensate(&mut self, overhead: Duration) {
+    if self.number_of_windows == 0 {
+      return;
+    }
+    let overhead = to_nanos(overhead);
+    self.total_time = self.total_time.saturating_sub(overhead.saturating_mul(self.number_of_windows));
+    self.min_time = self.min_time.saturating_sub(overhead);
+    self.max_time = self.max_time.saturating_sub(overhead);
+    self.mean = (self.mean - overhead as f64).max(0.0);
+    self.ewma = (self.ewma - overhead as f64).max(0.0);
+  }
+
+  /// Estimates the time window below which a fraction `q` of windows fall,
+  /// e.g. `quantile(0.99)` is the 99th percentile.
+  ///
+  /// Returns `None` if we've never run or aren't keeping a histogram.
+  pub fn quantile(&self, q: f64) -> Option<Duration> {
+    self.histogram.as_ref().and_then(|h| h.quantile(q)).map(Duration::from_nanos)
+  }
+
+  #[inline]
+  /// Folds a single time window, in nanoseconds, that just ended into the
+  /// stats.
+  fn add_window(&mut self, dt: u64) {
+    self.add_window_ending(dt, Instant::now());
+  }
+
+  /// Folds a single time window, in nanoseconds, that ended at `end` into
+  /// the stats.
+  fn add_window_ending(&mut self, dt: u64, end: Instant) {
+    self.add_windows_ending(dt, 1, end);
+  }
+
+  /// Folds `n` time windows of `dt` nanoseconds into the stats, as if the
+  /// latest ended at `end`. A sampled window stands for `n` windows, since
+  /// the rest weren't timed.
+  fn add_windows_ending(&mut self, dt: u64, n: u64, end: Instant) {
+    let start = end.checked_sub(Duration::from_nanos(dt)).unwrap_or(end);
+    if self.first_start.is_none_or(|first| start < first) {
+      self.first_start = Some(start);
+    }
+    if self.last_end.is_none_or(|last| end > last) {
+      self.last_end = Some(end);
+    }
+    // Moving the average once per window stood for.
+    self.ewma =
+      if self.number_of_windows == 0 {
+        dt as f64
+      } else {
+        self.ewma + (1.0 - powu(1.0 - self.ewma_alpha, n)) * (dt as f64 - self.ewma)
+      };
+    self.total_time += dt * n;
+    self.number_of_windows += n;
+    // Welford's algorithm, weighting this window by `n`.
+    let delta = dt as f64 - self.mean;
+    self.mean += delta * n as f64 / self.number_of_windows as f64;
+    self.m2 += delta * (dt as f64 - self.mean) * n as f64;
+    if dt < self.min_time {
+      self.min_time = dt;
+    }
+    if dt > self.max_time {
+      self.max_time = dt;
+    }
+    if let Some(ref mut histogram) = self.histogram {
+      histogram.add(dt, n);
+    }
+  }
+
+  #[cfg(feature = "std")]
+  /// Folds what `n` time windows allocated, each as much as `allocated`,
+  /// into the stats.
+  fn add_allocations(&mut self, allocated: Allocations, n: u64) {
+    self.allocations += allocated.count * n;
+    self.allocated_bytes += allocated.bytes * n;
+  }
+
+  #[cfg(feature = "std")]
   #[inline]
   /// Times a function, updating stats as necessary.
   pub fn timed<T, F: FnOnce() -> T>(&mut self, event: F) -> T {
-    let then = tsc::read();
+    #[cfg(feature = "disable")]
+    {
+      event()
+    }
+    #[cfg(not(feature = "disable"))]
+    {
+      self.timed_with_result(event).0
+    }
+  }
+
+  #[cfg(feature = "std")]
+  /// Opens a time window, which can be paused and resumed so it covers
+  /// several separate stretches of code, until `stop` folds it into the
+  /// stats. Any window that's already open is discarded.
+  pub fn start(&mut self) {
+    self.open_window = Some(0);
+    self.resumed_at = Some(Instant::now());
+  }
+
+  #[cfg(feature = "std")]
+  /// Pauses the open window, if it's running.
+  pub fn pause(&mut self) {
+    if let (Some(so_far), Some(resumed_at)) = (self.open_window.as_mut(), self.resumed_at.take()) {
+      *so_far += to_nanos(resumed_at.elapsed());
+    }
+  }
+
+  #[cfg(feature = "std")]
+  /// Resumes the open window, if it's paused.
+  pub fn resume(&mut self) {
+    if self.open_window.is_some() && self.resumed_at.is_none() {
+      self.resumed_at = Some(Instant::now());
+    }
+  }
+
+  #[cfg(feature = "std")]
+  /// Closes the open window, folding the time it was running for into the
+  /// stats, and returns that time. Returns `None` if no window was open.
+  pub fn stop(&mut self) -> Option<Duration> {
+    self.pause();
+    self.open_window.take().map(|dt| {
+      self.add_window(dt);
+      Duration::from_nanos(dt)
+    })
+  }
+
+  /// Folds a time window measured elsewhere, which just ended, into the
+  /// stats.
+  pub fn record(&mut self, d: Duration) {
+    self.add_window(to_nanos(d));
+  }
+
+  #[cfg(feature = "std")]
+  #[inline]
+  /// Like `timed`, but also returns how long this call of `event` took.
+  pub fn timed_with_result<T, F: FnOnce() -> T>(&mut self, event: F) -> (T, Duration) {
+    #[cfg(feature = "disable")]
+    {
+      (event(), Duration::ZERO)
+    }
+    #[cfg(not(feature = "disable"))]
+    {
+      let allocated = Allocations::current();
+      let then = Instant::now();
+      let ret = event();
+      let now = Instant::now();
+      let dt = now - then;
+      self.add_window_ending(to_nanos(dt), now);
+      self.add_allocations(Allocations::current().since(allocated), 1);
+      (ret, dt)
+    }
+  }
+
+  #[inline]
+  /// Like `timed_with_result`, but reads the time from `clock`, e.g. a cycle
+  /// counter on a target without `std`.
+  pub fn timed_with_clock<C: Clock + ?Sized, T, F: FnOnce() -> T>(&mut self, clock: &C, event: F) -> (T, Duration) {
+    let then = clock.now();
     let ret = event();
-    self.total_time += tsc::read() - then;
-    self.number_of_windows += 1;
-    ret
+    let dt = clock.to_nanos(clock.now().saturating_sub(then));
+    self.add_window(dt);
+    (ret, Duration::from_nanos(dt))
   }
 
-  /// Prints out timing statistics of this stopwatch.
-  fn print(&self, name: &str, tps: tsc::T) {
+  #[cfg(feature = "std")]
+  /// Prints out timing statistics of this stopwatch, followed by `extra`.
+  fn report(&self, name: &str, reporter: &dyn Reporter, format: DurationFormat, extra: &str) {
     if self.number_of_windows == 0 {
-      info!("{} never ran", name);
+      reporter.line(&format!("{} never ran", name));
     } else {
-      info!(
-        "{}: {}ms over {} samples (avg {}us)",
-        name,
-        tsc::to_ms(self.total_time, tps),
-        self.number_of_windows,
-        tsc::to_us(self.total_time / self.number_of_windows, tps)
-      );
+      reporter.line(&format!("{}: {}{}", name, self.summary(format), extra));
     }
   }
 
+  #[cfg(feature = "std")]
+  /// Describes the timing statistics of a stopwatch that has run.
+  fn summary(&self, format: DurationFormat) -> String {
+    // A histogram loaded from a snapshot can be empty even though the
+    // stopwatch has run.
+    let percentiles =
+      match self.histogram.as_ref().map(|h| [0.5, 0.9, 0.95, 0.99].map(|q| h.quantile(q))) {
+        Some([Some(p50), Some(p90), Some(p95), Some(p99)]) =>
+          format!(
+            ", p50 {}, p90 {}, p95 {}, p99 {}",
+            format.format(p50),
+            format.format(p90),
+            format.format(p95),
+            format.format(p99)
+          ),
+        _ => String::new(),
+      };
+    let stddev =
+      match self.variance() {
+        None => String::new(),
+        Some(v) => format!(", stddev {}", format.format(sqrt(v))),
+      };
+    let rate =
+      match self.rate() {
+        None => String::new(),
+        Some(rate) => format!(" at {:.1}/s", rate),
+      };
+    let self_time =
+      if self.child_time > 0 {
+        format!(", self {}", format.format(self.total_time.saturating_sub(self.child_time)))
+      } else {
+        String::new()
+      };
+    let violations =
+      if self.violations > 0 {
+        format!(", {} over timeout", self.violations)
+      } else {
+        String::new()
+      };
+    let allocations =
+      if allocations::in_use() {
+        format!(" with {} allocs of {}B", self.allocations, self.allocated_bytes)
+      } else {
+        String::new()
+      };
+    format!(
+      "{} over {} samples{} (avg {}, ewma {}{}, min {}, max {}{}{}{}){}",
+      format.format(self.total_time),
+      self.number_of_windows,
+      rate,
+      format.format(self.total_time / self.number_of_windows),
+      format.format(self.ewma as u64),
+      stddev,
+      format.format(self.min_time),
+      format.format(self.max_time),
+      percentiles,
+      self_time,
+      violations,
+      allocations
+    )
+  }
 }
 
 unsafe impl Send for Stopwatch {}
 unsafe impl Sync for Stopwatch {}
 
+#[cfg(feature = "std")]
+/// Timers keyed by name. Names given as `&'static str` are borrowed, so
+/// timing under them never allocates, and any others are interned.
+type Timers = HashMap<Name, Stopwatch, BuildHasherDefault<FnvHasher>>;
+
+#[cfg(feature = "std")]
+/// Locks `mutex`, even if a thread panicked while holding it. Nothing is ever
+/// left half-updated in a way that matters, so one panicking thread shouldn't
+/// stop every other from timing.
+fn lock<T: ?Sized>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+  mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+#[cfg(feature = "std")]
+fn new_timers() -> Timers {
+  HashMap::with_hasher(BuildHasherDefault::<FnvHasher>::default())
+}
+
+#[cfg(feature = "std")]
+/// Folds every timer in `others` into `timers`, merging stopwatches that
+/// share a name.
+fn merge_timers(timers: &mut Timers, others: &Timers, config: StopwatchConfig) {
+  for (name, other) in others.iter() {
+    if !timers.contains_key(name) {
+      timers.insert(name.clone(), Stopwatch::with_config(config));
+    }
+    timers.get_mut(name).unwrap().merge(other);
+  }
+}
+
+#[cfg(feature = "std")]
+/// One thread's share of a `TimerSet`'s timers.
+struct ShardTimers {
+  timers: Timers,
+  /// Timers registered with `TimerSet::register`, indexed by handle. This
+  /// is only as long as the largest handle this thread has timed with.
+  handles: Vec<Stopwatch>,
+  /// The handles of the keys this thread has timed under, if the set is
+  /// keyed by something other than names. See `keyed::KeyHandles`.
+  keys: Option<Box<dyn Any + Send>>,
+  counters: Counters,
+  history: Histories,
+  /// The slowest windows of the timers keeping them, slowest first.
+  slowest: HashMap<String, Vec<SlowWindow>, BuildHasherDefault<FnvHasher>>,
+}
+
+#[cfg(feature = "std")]
+impl ShardTimers {
+  fn new(timers: Timers) -> ShardTimers {
+    ShardTimers {
+      timers,
+      handles: Vec::new(),
+      keys: None,
+      counters: new_counters(),
+      history: Histories::default(),
+      slowest: HashMap::default(),
+    }
+  }
+
+  /// Returns the stopwatch of the registered timer at `index`, making it if
+  /// this thread hasn't timed with it yet.
+  fn handle_mut(&mut self, index: usize, config: StopwatchConfig) -> &mut Stopwatch {
+    if self.handles.len() <= index {
+      self.handles.resize_with(index + 1, || Stopwatch::with_config(config));
+    }
+    &mut self.handles[index]
+  }
+
+  /// Runs `f` on every stopwatch in the shard, along with its name, given
+  /// the names of the registered timers.
+  fn for_each_mut<F: FnMut(&str, &mut Stopwatch)>(&mut self, handle_names: &[String], mut f: F) {
+    for (name, sw) in self.timers.iter_mut() {
+      f(name, sw);
+    }
+    for (name, sw) in handle_names.iter().zip(self.handles.iter_mut()) {
+      f(name, sw);
+    }
+  }
+}
+
+#[cfg(feature = "std")]
+type Shard = Mutex<ShardTimers>;
+
+#[cfg(feature = "std")]
+#[derive(Default)]
+/// What to keep about a window besides how long it took.
+struct Extras<'a> {
+  /// Describes the window, for if it's one of the timer's slowest.
+  context: Option<&'a mut dyn FnMut() -> String>,
+  /// How long the window can take, in nanoseconds, before it's counted as a
+  /// violation.
+  timeout: Option<u64>,
+}
+
+#[cfg(feature = "std")]
+#[derive(Debug, Default, Copy, Clone)]
+/// When a time window started: the clock's reading, how much the thread had
+/// allocated, and how deep in the thread's open windows it is.
+struct Start {
+  ticks: u64,
+  allocated: Allocations,
+  depth: Option<usize>,
+}
+
+#[cfg(feature = "std")]
+static NEXT_TIMERSET_ID: AtomicUsize = AtomicUsize::new(0);
+
+#[cfg(feature = "std")]
+/// Whether any timing happens at all. See `set_enabled`.
+static ENABLED: AtomicBool = AtomicBool::new(true);
+
+#[doc(hidden)]
+/// Whether the `disable` feature is on, for macros, which can't check this
+/// crate's features themselves.
+pub const DISABLED: bool = cfg!(feature = "disable");
+
+#[cfg(feature = "std")]
+/// Turns timing on or off for every `TimerSet`. While it's off, `time` and
+/// friends just run what they're given, at the cost of a single atomic load.
+pub fn set_enabled(enabled: bool) {
+  ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+#[cfg(feature = "std")]
+// Every thread's shards, tagged with the id of the `TimerSet` they belong to.
+thread_local!(static SHARDS: RefCell<Vec<(usize, Weak<Shard>)>> = const { RefCell::new(Vec::new()) });
+
+#[cfg(feature = "std")]
 /// A set of stopwatches for multiple, named events.
-pub struct TimerSet {
-  ticks_per_second: tsc::T,
-  timers: Mutex<HashMap<String, Stopwatch, BuildHasherDefault<FnvHasher>>>,
+///
+/// Each thread records into its own shard of the set, so timing from many
+/// threads at once doesn't contend on a single lock. Shards are merged when
+/// the set is read.
+///
+/// Timers are named by strings, but a set can also be keyed by some other
+/// `TimerKey`, e.g. an enum, and timed with `time_key`. See `TimerKey`.
+pub struct TimerSet<K = String> {
+  id: usize,
+  config: StopwatchConfig,
+  hierarchical: bool,
+  /// Whether to report timers named by path as a tree outside hierarchy
+  /// mode, too.
+  tree: bool,
+  enabled: AtomicBool,
+  format: DurationFormat,
+  root: Option<String>,
+  /// What to take off every window in reports, if anything.
+  overhead: Option<Duration>,
+  /// Every call timed, if we're recording them.
+  events: Option<Mutex<Vec<trace::Event>>>,
+  /// Timers that only time one in every so many calls.
+  sample_rates: HashMap<String, u64, BuildHasherDefault<FnvHasher>>,
+  /// How many calls every other timer times one in.
+  default_sample_rate: u64,
+  /// Timers whose first few windows are set aside, and how.
+  warmups: HashMap<String, (u64, Warmup), BuildHasherDefault<FnvHasher>>,
+  /// Timers keeping a history, and their intervals, in nanoseconds, and
+  /// how many of them to keep.
+  history_settings: HashMap<String, (u64, usize), BuildHasherDefault<FnvHasher>>,
+  /// When the set was created, which history intervals count from.
+  epoch: Instant,
+  /// When `mark_phase` was last called, if it has been.
+  last_phase: Mutex<Option<Instant>>,
+  /// Timers that alert on windows over a certain length.
+  thresholds: HashMap<String, Threshold, BuildHasherDefault<FnvHasher>>,
+  /// Timers keeping their slowest windows, and how many of them.
+  slowest_settings: HashMap<String, usize, BuildHasherDefault<FnvHasher>>,
+  /// How long timers are expected to take per window, in nanoseconds, to
+  /// compare them with in reports.
+  budgets: Mutex<HashMap<String, u64, BuildHasherDefault<FnvHasher>>>,
+  clock: Arc<dyn Clock>,
+  /// The most distinct timers to keep, if there's a cap.
+  max_timers: Option<usize>,
+  /// Every timer counted against `max_timers`, if there's a cap.
+  timer_names: Mutex<HashSet<String>>,
+  /// Whether a timer has gone over `max_timers` yet.
+  overflowed: AtomicBool,
+  /// The order `report` lists timers in.
+  order: SortOrder,
+  /// What to do with windows whose functions panic.
+  on_panic: OnPanic,
+  /// Whether to only time the outermost of a timer's nested windows.
+  collapse_recursion: bool,
+  /// Whether timers', counters' and gauges' names are interned, or copied
+  /// for each thread's shard.
+  intern_names: bool,
+  /// The number of `TimerScope`s open on this set, across all threads.
+  scopes: AtomicUsize,
+  shards: Mutex<Vec<Arc<Shard>>>,
+  /// The names of the timers registered with `register`, indexed by handle.
+  handle_names: Mutex<Vec<String>>,
+  gauges: Mutex<Gauges>,
+  key: PhantomData<fn(&K)>,
 }
 
+#[cfg(feature = "std")]
+impl Default for TimerSet {
+  fn default() -> TimerSet {
+    TimerSet::new()
+  }
+}
+
+#[cfg(feature = "std")]
 impl TimerSet {
   /// Creates a new set of timers.
   pub fn new() -> TimerSet {
+    TimerSet::with_config(StopwatchConfig::default())
+  }
+
+  /// Creates a new set of timers, whose stopwatches keep the stats specified
+  /// by `config`.
+  pub fn with_config(config: StopwatchConfig) -> TimerSet {
+    TimerSet::from_shards(config, false, Vec::new())
+  }
+}
+
+#[cfg(feature = "std")]
+impl<K> TimerSet<K> {
+  fn from_shards(config: StopwatchConfig, hierarchical: bool, shards: Vec<Arc<Shard>>) -> TimerSet<K> {
     TimerSet {
-      ticks_per_second: tsc::ticks_per_second(),
-      timers: Mutex::new(HashMap::with_hasher(BuildHasherDefault::<FnvHasher>::default()))
+      id: NEXT_TIMERSET_ID.fetch_add(1, Ordering::Relaxed),
+      config,
+      hierarchical,
+      tree: false,
+      enabled: AtomicBool::new(true),
+      format: DurationFormat::default(),
+      root: None,
+      overhead: None,
+      events: None,
+      sample_rates: HashMap::default(),
+      default_sample_rate: 1,
+      warmups: HashMap::default(),
+      history_settings: HashMap::default(),
+      epoch: Instant::now(),
+      last_phase: Mutex::new(None),
+      thresholds: HashMap::default(),
+      slowest_settings: HashMap::default(),
+      budgets: Mutex::default(),
+      clock: Arc::new(InstantClock::new()),
+      max_timers: None,
+      timer_names: Mutex::new(HashSet::new()),
+      overflowed: AtomicBool::new(false),
+      order: SortOrder::default(),
+      on_panic: OnPanic::default(),
+      collapse_recursion: false,
+      intern_names: true,
+      scopes: AtomicUsize::new(0),
+      shards: Mutex::new(shards),
+      handle_names: Mutex::new(Vec::new()),
+      gauges: Mutex::new(Gauges::new()),
+      key: PhantomData,
     }
   }
 
+  /// Creates a set holding `timers` and `counters`, with the same settings
+  /// and gauges as this one.
+  fn with_timers(&self, timers: Timers, counters: Counters) -> TimerSet<K> {
+    let mut shard = ShardTimers::new(timers);
+    shard.counters = counters;
+    let shard = Arc::new(Mutex::new(shard));
+    let mut set = TimerSet::from_shards(self.config, self.hierarchical, vec![shard]);
+    set.set_enabled(self.enabled.load(Ordering::Relaxed));
+    set.tree = self.tree;
+    set.format = self.format;
+    set.root = self.root.clone();
+    set.overhead = self.overhead;
+    set.sample_rates = self.sample_rates.clone();
+    set.default_sample_rate = self.default_sample_rate;
+    set.warmups = self.warmups.clone();
+    set.history_settings = self.history_settings.clone();
+    set.thresholds = self.thresholds.clone();
+    set.slowest_settings = self.slowest_settings.clone();
+    set.budgets = Mutex::new(lock(&self.budgets).clone());
+    set.clock = self.clock.clone();
+    set.max_timers = self.max_timers;
+    set.order = self.order;
+    set.on_panic = self.on_panic;
+    set.collapse_recursion = self.collapse_recursion;
+    set.intern_names = self.intern_names;
+    set.gauges = Mutex::new(lock(&self.gauges).clone());
+    set
+  }
+
+  /// Runs `f` on this thread's shard of the timers, creating it if need be.
+  fn with_shard<T, F: FnOnce(&mut Timers) -> T>(&self, f: F) -> T {
+    self.with_shard_timers(|shard| f(&mut shard.timers))
+  }
+
+  /// Runs `f` on this thread's whole shard, registered timers included,
+  /// creating it if need be.
+  fn with_shard_timers<T, F: FnOnce(&mut ShardTimers) -> T>(&self, f: F) -> T {
+    let shard = SHARDS.with(|shards| {
+      let mut shards = shards.borrow_mut();
+      if let Some(shard) = shards.iter().find(|s| s.0 == self.id).and_then(|s| s.1.upgrade()) {
+        return shard;
+      }
+      // Forget the shards of any sets that have since been dropped.
+      shards.retain(|s| s.1.strong_count() > 0);
+      let shard = Arc::new(Mutex::new(ShardTimers::new(new_timers())));
+      lock(&self.shards).push(shard.clone());
+      shards.push((self.id, Arc::downgrade(&shard)));
+      shard
+    });
+    let mut timers = lock(&shard);
+    f(&mut timers)
+  }
+
+  /// Merges every thread's shard into a single set of timers.
+  fn merged(&self) -> Timers {
+    let mut merged = new_timers();
+    let handle_names = lock(&self.handle_names);
+    for shard in lock(&self.shards).iter() {
+      let mut shard = lock(shard);
+      merge_timers(&mut merged, &shard.timers, self.config);
+      for (name, sw) in handle_names.iter().zip(shard.handles.iter_mut()) {
+        if sw.number_of_windows > 0 {
+          merged.entry(self.new_name(name)).or_insert_with(|| Stopwatch::with_config(self.config)).merge(sw);
+        }
+      }
+    }
+    merged
+  }
+
   /// Times the execution of a function, and logs it under a timer with
-  /// the given name.
+  /// the given name. If the function panics, the window is still logged as
+  /// it unwinds, unless `set_on_panic` says otherwise.
   ///
   /// This function is not marked `mut` because borrow checking is done
   /// dynamically.
   pub fn time<T, F: FnOnce() -> T>(&self, name: &str, f: F) -> T {
-    let then = tsc::read();
-    trace!("Start timing {:?} at {:?}", name, then);
+    self.time_keyed(name, None, f)
+  }
+
+  /// Like `time`, for names that live forever, such as literals. The first
+  /// call on each thread doesn't have to copy the name to make a timer for it,
+  /// unless the name is scoped or nested.
+  pub fn time_static<T, F: FnOnce() -> T>(&self, name: &'static str, f: F) -> T {
+    self.time_keyed(name, Some(name), f)
+  }
+
+  /// Times `f` under the given name, which is `key` if that's been given and
+  /// the name doesn't end up scoped or nested.
+  #[inline]
+  fn time_keyed<T, F: FnOnce() -> T>(&self, name: &str, key: Option<&'static str>, f: F) -> T {
+    self.time_window(name, key, f, |name, key, start, weight, _| {
+      self.stop(name, key, start, weight);
+    }).0
+  }
+
+  /// Runs `f` in a window of the named timer, and ends the window with
+  /// `stop`, given the timer's full name, its key, when the window started,
+  /// how many windows it counts as, and what `f` returned. `key` is the
+  /// name, if it's static and doesn't end up scoped or nested. Returns what
+  /// `f` and `stop` did, but `f` only runs, untimed, if the set is off, the
+  /// call is nested in a window of its own timer that it's collapsed into,
+  /// or it's sampled out.
+  #[inline]
+  fn time_window<T, R, F, S>(&self, name: &str, key: Option<&'static str>, f: F, stop: S) -> (T, Option<R>)
+  where
+    F: FnOnce() -> T,
+    S: FnOnce(&str, Option<&'static str>, Start, u64, &T) -> R,
+  {
+    if !self.is_enabled() {
+      return (f(), None);
+    }
+    let (name, scope) = self.enter(name);
+    let key = if let Cow::Borrowed(_) = name { key } else { None };
+    if self.is_recursive(&name) {
+      return (f(), None);
+    }
+    let weight =
+      match self.sample(&name) {
+        None => return (f(), None),
+        Some(weight) => weight,
+      };
+    let start = self.start_window(Some(&name));
+    trace!("Start timing {:?} at {:?}", name, start.ticks);
+    let unwind = self.on_unwind(&name, key, start, weight);
     let ret = f();
-    let now = tsc::read();
-    let total_time = now - then;
-    trace!("Stop timing {:?} at {:?} ({:?}us)", name, now, tsc::to_us(total_time, self.ticks_per_second));
+    unwind.finish();
+    drop(scope);
+    let stopped = stop(&name, key, start, weight, &ret);
+    (ret, Some(stopped))
+  }
 
-    let mut timers = self.timers.lock().unwrap();
+  /// Logs a time window measured elsewhere, e.g. a query latency reported by
+  /// a database driver, under the timer with the given name. The name is
+  /// scoped and nested the same way as `time`'s.
+  pub fn record(&self, name: &str, d: Duration) {
+    if !self.is_enabled() {
+      return;
+    }
+    let (name, scope) = self.enter(name);
+    drop(scope);
+    self.add_window(&name, to_nanos(d));
+  }
 
-    if !timers.contains_key(name) {
-      timers.insert(name.to_string(), Stopwatch::new());
+  /// Like `time`, for fallible functions. Besides the usual timer, the call
+  /// is also logged under `name.ok` or `name.err`, depending on what `f`
+  /// returned, so successes and failures are counted, and timed, separately.
+  pub fn time_result<T, E, F: FnOnce() -> Result<T, E>>(&self, name: &str, f: F) -> Result<T, E> {
+    self.time_window(name, None, f, |name, key, start, weight, ret| {
+      let (dt, allocated) = self.stop(name, key, start, weight);
+      let outcome = if ret.is_ok() { "ok" } else { "err" };
+      self.add_windows(&format!("{}.{}", name, outcome), None, dt, weight, allocated);
+    }).0
+  }
+
+  /// Like `time`, but also returns how long this call of `f` took. The call
+  /// is measured even if timing is turned off or sampled out, but then isn't
+  /// logged.
+  pub fn time_with_result<T, F: FnOnce() -> T>(&self, name: &str, f: F) -> (T, Duration) {
+    let start = self.start_clock();
+    let (ret, dt) = self.time_window(name, None, f, |name, key, start, weight, _| self.stop(name, key, start, weight).0);
+    let dt = dt.unwrap_or_else(|| self.clock.to_nanos(self.clock.now().saturating_sub(start.ticks)));
+    (ret, Duration::from_nanos(dt))
+  }
+
+  /// Starts timing under the timer with the given name. The time is logged
+  /// when the returned guard is dropped, e.g. at the end of the enclosing
+  /// scope or on an early return.
+  pub fn start<'a>(&'a self, name: &'a str) -> TimerGuard<'a, K> {
+    self.start_named(Cow::Borrowed(name))
+  }
+
+  /// Like `start`, for names that may have been built for the guard.
+  pub(crate) fn start_named<'a>(&'a self, name: Cow<'a, str>) -> TimerGuard<'a, K> {
+    if !self.is_enabled() {
+      return TimerGuard {
+        timers: self,
+        name,
+        start: Start::default(),
+        weight: None,
+        _scope: None,
+      };
     }
-    let stopwatch = timers.get_mut(name).unwrap();
-    stopwatch.number_of_windows += 1;
-    stopwatch.total_time += total_time;
+    let (entered, scope) = self.enter(&name);
+    let name =
+      match entered {
+        Cow::Borrowed(_) => name,
+        Cow::Owned(entered) => Cow::Owned(entered),
+      };
+    let (weight, start) =
+      if self.is_recursive(&name) {
+        (None, Start::default())
+      } else {
+        (self.sample(&name), self.start_window(Some(&name)))
+      };
+    trace!("Start timing {:?} at {:?}", name, start.ticks);
+    TimerGuard {
+      timers: self,
+      name,
+      start,
+      weight,
+      _scope: scope,
+    }
+  }
+
+  /// Turns timing on or off for this set. While it's off, `time` and friends
+  /// just run what they're given. Timing also needs to be turned on
+  /// globally, with `stopwatch::set_enabled`.
+  pub fn set_enabled(&self, enabled: bool) {
+    self.enabled.store(enabled, Ordering::Relaxed);
+  }
 
-    ret
+  #[inline]
+  /// Whether this set is timing anything right now. This is always false
+  /// with the `disable` feature, which compiles timing out entirely.
+  pub fn is_enabled(&self) -> bool {
+    !cfg!(feature = "disable") && ENABLED.load(Ordering::Relaxed) && self.enabled.load(Ordering::Relaxed)
+  }
+
+  /// Turns hierarchy mode on or off. In hierarchy mode, timers started while
+  /// another timer from this set is running on the same thread are named by
+  /// their path, e.g. `a/b`, and `print` renders them as a tree.
+  pub fn set_hierarchical(&mut self, hierarchical: bool) {
+    self.hierarchical = hierarchical;
   }
 
-  /// Prints all the timer statistics to stdout, each tagged with their name.
+  /// Sets whether reports render timers named by path, e.g. `a/b/c`, as a
+  /// tree even outside hierarchy mode, like `time("a/b", f)` had been
+  /// called within `a`. Hierarchy mode always renders a tree.
+  pub fn set_tree_report(&mut self, tree: bool) {
+    self.tree = tree;
+  }
+
+  /// Sets how reports render durations. The default is
+  /// `DurationFormat::Adaptive`.
+  pub fn set_duration_format(&mut self, format: DurationFormat) {
+    self.format = format;
+  }
+
+  /// Designates a timer, e.g. one wrapping the whole program, that reports
+  /// show every other timer's share of, alongside its share of the sum of
+  /// all timers. `None` turns this off.
+  pub fn set_root_timer(&mut self, root: Option<&str>) {
+    self.root = root.map(|root| root.to_string());
+  }
+
+  /// Makes the named timer only time one in every `n` calls of `time` or
+  /// `start`, and count each timed call as `n` windows, so very hot code
+  /// pays for a clock read only occasionally. `name` is the full name the
+  /// timer reports under. An `n` of 0 or 1 times every call again.
+  ///
+  /// Each thread counts calls separately, and times its first call.
+  pub fn set_sample_rate(&mut self, name: &str, n: u64) {
+    if n > 1 {
+      self.sample_rates.insert(name.to_string(), n);
+    } else {
+      self.sample_rates.remove(name);
+    }
+  }
+
+  /// Decides whether to time this call of the named timer. Returns the number
+  /// of windows to count it as, or `None` to skip timing it.
+  fn sample(&self, name: &str) -> Option<u64> {
+    if self.sample_rates.is_empty() && self.default_sample_rate == 1 {
+      return Some(1);
+    }
+    let rate =
+      match self.sample_rates.get(name) {
+        None if self.default_sample_rate == 1 => return Some(1),
+        None => self.default_sample_rate,
+        Some(&rate) => rate,
+      };
+    self.with_stopwatch(name, None, |sw| {
+      if sw.unsampled == 0 {
+        sw.unsampled = rate - 1;
+        Some(rate)
+      } else {
+        sw.unsampled -= 1;
+        None
+      }
+    })
+  }
+
+  /// Sets aside the first `k` windows of the named timer, which are often
+  /// slower than the rest from page faults, lazy initialization or cold
+  /// caches, so they don't skew its steady-state stats. `name` is the full
+  /// name the timer reports under. A `k` of 0 keeps every window again.
+  ///
+  /// Each thread sets aside its own first windows, since each has its own
+  /// caches to warm.
+  pub fn set_warmup(&mut self, name: &str, k: u64, warmup: Warmup) {
+    if k > 0 {
+      self.warmups.insert(name.to_string(), (k, warmup));
+    } else {
+      self.warmups.remove(name);
+    }
+  }
+
+  /// Decides whether this window of the named timer is part of its warm-up.
+  /// Returns what to do with it instead, or `None` to log it as usual.
+  fn warmup(&self, name: &str, key: Option<&'static str>, n: u64) -> Option<Warmup> {
+    if self.warmups.is_empty() {
+      return None;
+    }
+    let (k, warmup) = *self.warmups.get(name)?;
+    self.with_stopwatch(name, key, |sw| {
+      if sw.warmup_windows < k {
+        sw.warmup_windows += n;
+        Some(warmup)
+      } else {
+        None
+      }
+    })
+  }
+
+  /// Sets the clock that windows are timed with.
+  pub fn set_clock<C: Clock + 'static>(&mut self, clock: C) {
+    self.clock = Arc::new(clock);
+  }
+
+  /// Opens a scope on this thread, until the returned guard is dropped.
+  /// Timers started on this thread while it's open have `prefix` and a dot
+  /// prepended to their names. Scopes nest, e.g.
+  ///
+  /// ```
+  /// let ts = stopwatch::TimerSet::new();
+  /// let _render = ts.push_scope("render");
+  /// let _shadows = ts.push_scope("shadows");
+  /// ts.time("upload", || {}); // Times "render.shadows.upload".
+  /// ```
+  pub fn push_scope<'a>(&'a self, prefix: &str) -> TimerScope<'a, K> {
+    let prefix = self.scoped(prefix).into_owned();
+    self.scopes.fetch_add(1, Ordering::Relaxed);
+    PREFIXES.with(|prefixes| prefixes.borrow_mut().push((self.id, prefix)));
+    TimerScope {
+      timers: self,
+    }
+  }
+
+  /// Prepends this thread's innermost open scope, if any, to `name`.
+  fn scoped<'a>(&self, name: &'a str) -> Cow<'a, str> {
+    if self.scopes.load(Ordering::Relaxed) == 0 {
+      return Cow::Borrowed(name);
+    }
+    PREFIXES.with(|prefixes| {
+      match prefixes.borrow().iter().rev().find(|&&(id, _)| id == self.id) {
+        None => Cow::Borrowed(name),
+        Some((_, prefix)) => Cow::Owned(format!("{}.{}", prefix, name)),
+      }
+    })
+  }
+
+  /// Resolves the name to time under, entering a new scope if we're in
+  /// hierarchy mode.
+  fn enter<'a>(&self, name: &'a str) -> (Cow<'a, str>, Option<Scope>) {
+    let name = self.scoped(name);
+    if !self.hierarchical {
+      return (name, None);
+    }
+    let id = self.id;
+    let path = SCOPES.with(|scopes| {
+      let mut scopes = scopes.borrow_mut();
+      let path =
+        match scopes.iter().rev().find(|&&(scope_id, _)| scope_id == id) {
+          None => name.into_owned(),
+          Some((_, parent)) => format!("{}/{}", parent, name),
+        };
+      scopes.push((id, path.clone()));
+      path
+    });
+    (Cow::Owned(path), Some(Scope))
+  }
+
+  #[inline]
+  /// Starts a time window of the named timer now, or of a registered timer
+  /// if `name` is `None`.
+  fn start_window(&self, name: Option<&str>) -> Start {
+    let allocated = Allocations::current();
+    let depth = self.push_window(name);
+    Start {
+      ticks: self.clock.now(),
+      allocated,
+      depth,
+    }
+  }
+
+  #[inline]
+  /// Starts a time window now, without opening it, so nothing nested in it
+  /// is counted against it.
+  fn start_clock(&self) -> Start {
+    Start {
+      ticks: self.clock.now(),
+      allocated: Allocations::current(),
+      depth: None,
+    }
+  }
+
+  /// Logs the window from `start` until now under the timer with the given
+  /// name, counted as `n` windows, and returns its length in nanoseconds and
+  /// what it allocated. `key` is the name, if it's static.
+  fn stop(&self, name: &str, key: Option<&'static str>, start: Start, n: u64) -> (u64, Allocations) {
+    self.stop_with_extras(name, key, start, n, Extras::default())
+  }
+
+  /// Like `stop`, keeping `extras` about the window too.
+  fn stop_with_extras(&self, name: &str, key: Option<&'static str>, start: Start, n: u64, extras: Extras) -> (u64, Allocations) {
+    let now = self.clock.now();
+    let allocated = Allocations::current().since(start.allocated);
+    let total_time = self.clock.to_nanos(now.saturating_sub(start.ticks));
+    trace!("Stop timing {:?} at {:?} ({:?}us)", name, now, total_time / 1_000);
+    self.record_event(name, self.clock.to_nanos(start.ticks), total_time);
+    let child_time = self.pop_window(start, total_time);
+    self.add_windows_with_extras(name, key, total_time, n, allocated, child_time, Instant::now(), extras);
+    (total_time, allocated)
+  }
+
+  /// Names a new timer, counter or gauge `name`, interning it if the set
+  /// does.
+  pub(crate) fn new_name(&self, name: &str) -> Name {
+    if self.intern_names {
+      Name::new(name)
+    } else {
+      Name::copied(name)
+    }
+  }
+
+  /// Runs `f` on this thread's copy of the named timer, creating it if need
+  /// be. `key` is the name, if it's static, so it needn't be copied.
+  fn with_stopwatch<T, F: FnOnce(&mut Stopwatch) -> T>(&self, name: &str, key: Option<&'static str>, f: F) -> T {
+    self.with_shard(|timers| {
+      let mut name = name;
+      if !timers.contains_key(name) {
+        let mut key = key;
+        if !self.admit(name) {
+          name = OVERFLOW_TIMER;
+          key = Some(OVERFLOW_TIMER);
+        }
+        if !timers.contains_key(name) {
+          let key =
+            match key {
+              Some(key) => Name::Static(key),
+              None => self.new_name(name),
+            };
+          timers.insert(key, Stopwatch::with_config(self.config));
+        }
+      }
+      f(timers.get_mut(name).unwrap())
+    })
+  }
+
+  /// Logs a window, in nanoseconds, under the timer with the given name.
+  fn add_window(&self, name: &str, dt: u64) {
+    if !self.is_enabled() {
+      return;
+    }
+    self.add_windows(name, None, dt, 1, Allocations::default());
+  }
+
+  /// Logs `n` windows of `dt` nanoseconds, which each allocated as much as
+  /// `allocated`, under the timer with the given name. `key` is the name, if
+  /// it's static.
+  fn add_windows(&self, name: &str, key: Option<&'static str>, dt: u64, n: u64, allocated: Allocations) {
+    self.add_windows_with_extras(name, key, dt, n, allocated, 0, Instant::now(), Extras::default());
+  }
+
+  /// Like `add_windows`, for windows that ended at `end`, keeping `extras`
+  /// about them too. Windows over their timeout are only counted as
+  /// violations if they're logged, e.g. not while they're discarded during
+  /// warmup.
+  #[allow(clippy::too_many_arguments)]
+  fn add_windows_with_extras(
+    &self,
+    name: &str,
+    key: Option<&'static str>,
+    dt: u64,
+    n: u64,
+    allocated: Allocations,
+    child_time: u64,
+    end: Instant,
+    extras: Extras,
+  ) {
+    if !self.thresholds.is_empty() {
+      self.check_threshold(name, dt);
+    }
+    let violations =
+      match extras.timeout {
+        Some(timeout) if dt > timeout => n,
+        _ => 0,
+      };
+    match self.warmup(name, key, n) {
+      None => {},
+      Some(Warmup::Discard) => return,
+      Some(Warmup::Separate) => {
+        let name = format!("{}.warmup", name);
+        self.with_stopwatch(&name, None, |sw| {
+          sw.add_windows_ending(dt, n, end);
+          sw.add_allocations(allocated, n);
+          sw.child_time += child_time.saturating_mul(n);
+          sw.violations += violations;
+        });
+        return;
+      },
+    }
+    self.with_stopwatch(name, key, |sw| {
+      sw.add_windows_ending(dt, n, end);
+      sw.add_allocations(allocated, n);
+      sw.child_time += child_time.saturating_mul(n);
+      sw.violations += violations;
+    });
+    if !self.history_settings.is_empty() {
+      self.add_history(name, dt, n, end);
+    }
+    if !self.slowest_settings.is_empty() {
+      self.add_slowest(name, dt, extras.context);
+    }
+  }
+
+  /// Folds all of `other`'s timers into this set, merging stopwatches that
+  /// share a name.
+  pub fn merge(&self, other: &TimerSet<K>) {
+    // Copy `other` out first, so we never hold both sets' locks at once.
+    let others = other.merged();
+    let other_counters = other.merged_counters();
+    let other_gauges = lock(&other.gauges).clone();
+    self.with_shard_timers(|shard| {
+      merge_timers(&mut shard.timers, &others, self.config);
+      merge_counters(&mut shard.counters, &other_counters);
+    });
+    merge_gauges(&mut lock(&self.gauges), &other_gauges);
+  }
+
+  /// Resets every timer in this set. The timers themselves are kept, and
+  /// print as never having run until they're used again. Counters, gauges,
+  /// histories, slowest windows and any recorded events are forgotten.
+  pub fn reset_all(&self) {
+    lock(&self.gauges).clear();
+    if let Some(ref events) = self.events {
+      lock(events).clear();
+    }
+    let handle_names = lock(&self.handle_names);
+    for shard in lock(&self.shards).iter() {
+      let mut shard = lock(shard);
+      shard.counters.clear();
+      shard.history.clear();
+      shard.slowest.clear();
+      shard.for_each_mut(&handle_names, |_, sw| sw.reset());
+    }
+  }
+
+  /// Copies out every timer and resets them, atomically, so that no window
+  /// is lost between reading the stats and resetting them.
+  pub fn take_snapshot(&self) -> HashMap<String, Stopwatch> {
+    let handle_names = lock(&self.handle_names);
+    let shards = lock(&self.shards);
+    // Hold every shard's lock at once, so the snapshot is consistent.
+    let mut shards: Vec<_> = shards.iter().map(|shard| lock(shard)).collect();
+    let mut snapshot = HashMap::new();
+    for shard in shards.iter_mut() {
+      shard.for_each_mut(&handle_names, |name, sw| {
+        let mut fresh = Stopwatch::with_config(self.config);
+        fresh.warmup_windows = sw.warmup_windows;
+        let sw = std::mem::replace(sw, fresh);
+        snapshot.entry(name.to_string()).or_insert_with(|| Stopwatch::with_config(self.config)).merge(&sw);
+      });
+    }
+    snapshot
+  }
+
+  /// Removes the timer with the given name, returning it if it existed. A
+  /// registered timer is only reset, since its handle stays valid.
+  pub fn remove(&self, name: &str) -> Option<Stopwatch> {
+    let mut removed: Option<Stopwatch> = None;
+    let mut found = |sw: Stopwatch| {
+      match removed {
+        None => removed = Some(sw),
+        Some(ref mut removed) => removed.merge(&sw),
+      }
+    };
+    let handle_names = lock(&self.handle_names);
+    for shard in lock(&self.shards).iter() {
+      let mut shard = lock(shard);
+      if let Some(sw) = shard.timers.remove(name) {
+        found(sw);
+      }
+      shard.history.remove(name);
+      shard.slowest.remove(name);
+      for (handle_name, sw) in handle_names.iter().zip(shard.handles.iter_mut()) {
+        if handle_name == name {
+          found(std::mem::replace(sw, Stopwatch::with_config(self.config)));
+        }
+      }
+    }
+    lock(&self.timer_names).remove(name);
+    removed
+  }
+
+  /// Copies out every timer that has run, sorted by name.
+  fn sorted_timers(&self) -> Vec<(String, Stopwatch)> {
+    let mut timer_vec: Vec<(String, Stopwatch)> =
+      self.merged()
+        .into_iter()
+        .filter(|(_, sw)| sw.number_of_windows > 0)
+        .map(|(name, sw)| (name.to_string(), sw))
+        .collect();
+    timer_vec.sort_by(|a, b| a.0.cmp(&b.0));
+    timer_vec
+  }
+
+  /// Logs all the timer statistics at `info` level, each tagged with their
+  /// name. Use `report` to send them elsewhere.
   pub fn print(&self) {
-    let timers = self.timers.lock().unwrap();
+    self.report(&LogReporter::default());
+  }
+
+  /// Logs all the timer statistics at `info` level, listed in `order`.
+  pub fn print_sorted(&self, order: SortOrder) {
+    self.report_sorted(&LogReporter::default(), order);
+  }
+
+  /// Sends every timer's statistics to `reporter`, one line per timer, in
+  /// the same format as `print`. Timers are listed in alphabetical order,
+  /// unless the set was built with another `report_order`.
+  pub fn report(&self, reporter: &dyn Reporter) {
+    self.report_sorted(reporter, self.order);
+  }
+
+  /// Like `report`, but lists the timers in `order`. In hierarchical mode,
+  /// or with `set_tree_report`, each timer's children are sorted among
+  /// themselves. Counters and then gauges follow, sorted by name.
+  pub fn report_sorted(&self, reporter: &dyn Reporter, order: SortOrder) {
+    self.report_with(reporter, &Report::new().sorted(order));
+  }
+
+  /// Logs the timer statistics picked by `report` at `info` level, e.g.
+  /// `print_with(&Report::top(20).filter_prefix("render."))`.
+  pub fn print_with(&self, report: &Report) {
+    self.report_with(&LogReporter::default(), report);
+  }
+
+  /// Like `report_sorted`, but only sends the timers, counters and gauges
+  /// picked by `report`. Shares of the total still count every timer, and
+  /// so do the self and child times of a tree. Any overhead set with
+  /// `set_overhead` is taken off every window.
+  pub fn report_with(&self, reporter: &dyn Reporter, report: &Report) {
+    let mut timers = self.merged();
+    if let Some(overhead) = self.overhead {
+      for sw in timers.values_mut() {
+        sw.compensate(overhead);
+      }
+    }
+    let mut kept: Vec<(&str, &Stopwatch)> =
+      timers
+        .iter()
+        .map(|(name, sw)| (name.as_ref(), sw))
+        .filter(|&(name, _)| report.matches(name))
+        .collect();
+    if let Some(n) = report.top {
+      kept.sort_by(|&(a, a_sw), &(b, b_sw)| SortOrder::descending(SortBy::TotalTime).compare((a, Some(a_sw)), (b, Some(b_sw))));
+      kept.truncate(n);
+    }
+    let kept: HashSet<&str> = kept.into_iter().map(|(name, _)| name).collect();
+    let keep = |name: &str| kept.contains(name);
+    if self.hierarchical || self.tree {
+      self.report_tree(&timers, reporter, report.order, &keep);
+    } else {
+      self.report_flat(&timers, reporter, report.order, &keep);
+    }
+    self.report_counters(reporter, report);
+    self.report_gauges(reporter, report);
+  }
+
+  /// Reports every timer `keep`s on its own line, listed in `order`. Timers
+  /// tagged under the same name are listed together, sorted among
+  /// themselves.
+  fn report_flat(&self, timers: &Timers, reporter: &dyn Reporter, order: SortOrder, keep: &dyn Fn(&str) -> bool) {
     let mut timer_vec : Vec<(&str, &Stopwatch)> =
       timers
         .iter()
         .map(|(name, sw)| (name.as_ref(), sw))
+        .filter(|&(name, _)| keep(name))
         .collect();
 
-    timer_vec.sort_by(|&(k1, _), &(k2, _)| k1.cmp(k2));
+    let mut groups: HashMap<&str, Stopwatch> = HashMap::new();
+    for &(name, sw) in timer_vec.iter() {
+      groups.entry(tags::untagged(name)).or_insert_with(|| Stopwatch::with_config(StopwatchConfig { histogram: false, ..StopwatchConfig::default() })).merge(sw);
+    }
+    timer_vec.sort_by(|&(a, a_sw), &(b, b_sw)| {
+      let (a_group, b_group) = (tags::untagged(a), tags::untagged(b));
+      if a_group == b_group {
+        order.compare((a, Some(a_sw)), (b, Some(b_sw)))
+      } else {
+        order.compare((a_group, groups.get(a_group)), (b_group, groups.get(b_group)))
+      }
+    });
+
+    let total = timers.values().map(|sw| sw.total_time).sum();
+    for &(name, timer) in timer_vec.iter() {
+      timer.report(name, reporter, self.format, &format!("{}{}", self.shares(timer, total, timers), self.budget_note(name, timer)));
+    }
+  }
 
-    for &(name, ref timer) in timer_vec.iter() {
-      timer.print(name, self.ticks_per_second);
+  /// Describes `timer`'s share of `total`, and of the root timer if there is
+  /// one.
+  fn shares(&self, timer: &Stopwatch, total: u64, timers: &Timers) -> String {
+    let percent = |of: u64| 100.0 * timer.total_time as f64 / of as f64;
+    let mut shares = String::new();
+    if total > 0 {
+      shares.push_str(&format!(", {:.1}% of total", percent(total)));
+    }
+    if let Some(ref root) = self.root {
+      if let Some(root_timer) = timers.get(root.as_str()) {
+        if root_timer.total_time > 0 {
+          shares.push_str(&format!(", {:.1}% of {}", percent(root_timer.total_time), root));
+        }
+      }
+    }
+    shares
+  }
+
+  /// Reports hierarchical timers as an ASCII tree, splitting each timer's
+  /// time between itself and its children, and giving its share of its
+  /// parent's. Shares of the total are of the sum of the top-level timers,
+  /// since children's time is already counted there. Only the timers `keep`s
+  /// are reported, along with their ancestors.
+  fn report_tree(&self, timers: &Timers, reporter: &dyn Reporter, order: SortOrder, keep: &dyn Fn(&str) -> bool) {
+    // Include every ancestor, in case one is still running.
+    let mut paths: Vec<Vec<&str>> = Vec::new();
+    for name in timers.keys().filter(|name| keep(name)) {
+      let path: Vec<&str> = name.split('/').collect();
+      for len in 1..path.len() + 1 {
+        paths.push(path[..len].to_vec());
+      }
+    }
+    paths.sort();
+    paths.dedup();
+    // Order each path by its ancestor where it branches off from the other,
+    // so children stay under their parents.
+    paths.sort_by(|a, b| {
+      match a.iter().zip(b.iter()).position(|(x, y)| x != y) {
+        None => a.len().cmp(&b.len()),
+        Some(i) => {
+          let a_name = a[..i + 1].join("/");
+          let b_name = b[..i + 1].join("/");
+          order.compare((a[i], timers.get(a_name.as_str())), (b[i], timers.get(b_name.as_str())))
+        },
+      }
+    });
+
+    // Whether each path is the last of its siblings, so the tree can be
+    // drawn without a branch hanging past it.
+    let last: Vec<bool> =
+      (0..paths.len())
+        .map(|i| {
+          let path = &paths[i];
+          paths[i + 1..]
+            .iter()
+            .take_while(|p| p.len() >= path.len() && p[..path.len() - 1] == path[..path.len() - 1])
+            .all(|p| p.len() > path.len())
+        })
+        .collect();
+
+    let total =
+      timers
+        .iter()
+        .filter(|&(name, _)| !name.contains('/'))
+        .map(|(_, sw)| sw.total_time)
+        .sum();
+    // The branches drawn for each level of the current path's ancestors.
+    let mut branches: Vec<&str> = Vec::new();
+    for (path, &last) in paths.iter().zip(last.iter()) {
+      branches.truncate(path.len().saturating_sub(2));
+      let indent =
+        if path.len() > 1 {
+          format!("{}{}", branches.concat(), if last { "`-- " } else { "|-- " })
+        } else {
+          String::new()
+        };
+      if path.len() > 1 {
+        branches.push(if last { "    " } else { "|   " });
+      }
+      let leaf = path[path.len() - 1];
+      let name = path.join("/");
+      match timers.get(name.as_str()) {
+        None => reporter.line(&format!("{}{}", indent, leaf)),
+        Some(timer) => {
+          let child_time = folded::child_time(timers, &name);
+          let parent = timers.get(path[..path.len() - 1].join("/").as_str()).map_or(0, |sw| sw.total_time);
+          let of_parent =
+            if path.len() > 1 && parent > 0 {
+              format!(", {:.1}% of parent", 100.0 * timer.total_time as f64 / parent as f64)
+            } else {
+              String::new()
+            };
+          reporter.line(&format!(
+            "{}{}: {} (self {}, children {}){}{}{}",
+            indent,
+            leaf,
+            timer.summary(self.format),
+            self.format.format(timer.total_time.saturating_sub(child_time)),
+            self.format.format(child_time),
+            of_parent,
+            self.shares(timer, total, timers),
+            self.budget_note(&name, timer)
+          ));
+        },
+      }
     }
   }
 }
 
-unsafe impl Send for TimerSet {}
-unsafe impl Sync for TimerSet {}
+#[cfg(feature = "std")]
+unsafe impl<K> Send for TimerSet<K> {}
+#[cfg(feature = "std")]
+unsafe impl<K> Sync for TimerSet<K> {}
+
+#[cfg(feature = "std")]
+thread_local!(static SCOPES: RefCell<Vec<(usize, String)>> = const { RefCell::new(Vec::new()) });
+
+#[cfg(feature = "std")]
+/// Leaves this thread's innermost hierarchical scope when dropped.
+struct Scope;
+
+#[cfg(feature = "std")]
+impl Drop for Scope {
+  fn drop(&mut self) {
+    SCOPES.with(|scopes| { scopes.borrow_mut().pop(); });
+  }
+}
+
+#[cfg(feature = "std")]
+thread_local!(static PREFIXES: RefCell<Vec<(usize, String)>> = const { RefCell::new(Vec::new()) });
 
-thread_local!(static TIMERSET: TimerSet = TimerSet::new());
+#[cfg(feature = "std")]
+/// Keeps a scope opened by `TimerSet::push_scope` open until it's dropped.
+///
+/// Scopes should be dropped in the reverse order they were opened.
+#[must_use]
+pub struct TimerScope<'a, K: 'a = String> {
+  timers: &'a TimerSet<K>,
+}
 
-/// Time with the thread-local `TimerSet`.
+#[cfg(feature = "std")]
+impl<'a, K> Drop for TimerScope<'a, K> {
+  fn drop(&mut self) {
+    let id = self.timers.id;
+    PREFIXES.with(|prefixes| {
+      let mut prefixes = prefixes.borrow_mut();
+      if let Some(i) = prefixes.iter().rposition(|&(scope_id, _)| scope_id == id) {
+        prefixes.remove(i);
+      }
+    });
+    self.timers.scopes.fetch_sub(1, Ordering::Relaxed);
+  }
+}
+
+#[cfg(feature = "std")]
+/// Times a window under a named timer in a `TimerSet`, from when it was
+/// created by `TimerSet::start` until it is dropped.
+///
+/// In hierarchy mode, guards should be dropped in the reverse order they
+/// were started.
+pub struct TimerGuard<'a, K: 'a = String> {
+  timers: &'a TimerSet<K>,
+  name: Cow<'a, str>,
+  start: Start,
+  /// How many windows to count this one as, or `None` if it isn't sampled.
+  weight: Option<u64>,
+  _scope: Option<Scope>,
+}
+
+#[cfg(feature = "std")]
+impl<'a, K> Drop for TimerGuard<'a, K> {
+  fn drop(&mut self) {
+    if let Some(weight) = self.weight {
+      if ::std::thread::panicking() {
+        self.timers.stop_panicked(&self.name, None, self.start, weight);
+      } else {
+        self.timers.stop(&self.name, None, self.start, weight);
+      }
+    } else {
+      self.timers.discard_window(self.start);
+    }
+  }
+}
+
+#[cfg(feature = "std")]
+thread_local!(static TIMERSET: Arc<TimerSet> = registry::register());
+
+#[cfg(feature = "std")]
+/// Time with the thread-local `TimerSet`. Every thread's set can be
+/// reported on with `report_threads` or `merge_threads`.
+///
+/// With the `disable` feature, this just calls `f`, and so do
+/// `time_block!` and `#[timed]`, which expand to calls of this.
+#[inline]
 pub fn time<T, F: FnOnce() -> T>(name: &str, f: F) -> T {
+  if cfg!(feature = "disable") {
+    return f();
+  }
   TIMERSET.with(|timerset| timerset.time(name, f))
 }
 
+#[cfg(feature = "std")]
+/// Like `time`, for names that live forever; see `TimerSet::time_static`.
+#[inline]
+pub fn time_static<T, F: FnOnce() -> T>(name: &'static str, f: F) -> T {
+  if cfg!(feature = "disable") {
+    return f();
+  }
+  TIMERSET.with(|timerset| timerset.time_static(name, f))
+}
+
+#[cfg(feature = "std")]
 pub fn clone() -> TimerSet {
-  TIMERSET.with(|timerset| {
-    TimerSet {
-      ticks_per_second: timerset.ticks_per_second,
-      timers: Mutex::new(timerset.timers.lock().unwrap().clone()),
-    }
-  })
+  TIMERSET.with(|timerset| timerset.with_timers(timerset.merged(), timerset.merged_counters()))
 }
 
+#[cfg(feature = "std")]
 #[test]
 fn test_simple() {
   let ts = TimerSet::new();
   ts.time("hello", || {});
 }
 
+#[cfg(feature = "std")]
 #[test]
 fn test_nested() {
   let ts = TimerSet::new();
@@ -153,3 +2045,524 @@ fn test_nested() {
     ts.time("world", || {});
   });
 }
+
+#[test]
+fn test_min_max() {
+  let mut sw = Stopwatch::new();
+  assert_eq!(sw.min_time(), None);
+  assert_eq!(sw.max_time(), None);
+  sw.add_window(5);
+  sw.add_window(2);
+  sw.add_window(9);
+  assert_eq!(sw.min_time(), Some(Duration::from_nanos(2)));
+  assert_eq!(sw.max_time(), Some(Duration::from_nanos(9)));
+  assert_eq!(sw.total_time, 16);
+  assert_eq!(sw.number_of_windows, 3);
+}
+
+#[test]
+fn test_no_histogram() {
+  let mut sw = Stopwatch::with_config(StopwatchConfig { histogram: false, ..StopwatchConfig::default() });
+  sw.add_window(5);
+  assert_eq!(sw.quantile(0.5), None);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_summary_empty_histogram() {
+  // As a snapshot with a histogram that lost its windows loads.
+  let mut sw = Stopwatch::new();
+  sw.add_window(5);
+  sw.histogram = Some(Box::new(Histogram::new()));
+  assert!(!sw.summary(DurationFormat::default()).contains("p50"));
+}
+
+#[cfg(all(feature = "std", not(feature = "disable")))]
+#[test]
+fn test_merge() {
+  let ts1 = TimerSet::new();
+  let ts2 = TimerSet::new();
+  ts1.time("a", || {});
+  ts2.time("a", || {});
+  ts2.time("b", || {});
+  ts1.merge(&ts2);
+  ts1.merge(&ts1);
+  let timers = ts1.merged();
+  assert_eq!(timers["a"].number_of_windows, 4);
+  assert_eq!(timers["b"].number_of_windows, 2);
+}
+
+#[cfg(all(feature = "std", not(feature = "disable")))]
+#[test]
+fn test_guard() {
+  let ts = TimerSet::new();
+  {
+    let _g = ts.start("hello");
+    let _g = ts.start("world");
+  }
+  let _g = ts.start("world");
+  let timers = ts.merged();
+  assert_eq!(timers["hello"].number_of_windows, 1);
+  assert_eq!(timers["world"].number_of_windows, 1);
+}
+
+#[cfg(all(feature = "std", not(feature = "disable")))]
+#[test]
+fn test_hierarchical() {
+  let mut ts = TimerSet::new();
+  ts.set_hierarchical(true);
+  ts.time("a", || {
+    ts.time("b", || {});
+    let _g = ts.start("c");
+    ts.time("b", || {});
+  });
+  ts.time("b", || {});
+  ts.print();
+  let timers = ts.merged();
+  let mut names: Vec<&str> = timers.keys().map(|k| k.as_ref()).collect();
+  names.sort();
+  assert_eq!(names, vec!["a", "a/b", "a/c", "a/c/b", "b"]);
+}
+
+#[cfg(all(feature = "std", not(feature = "disable")))]
+#[test]
+fn test_tree_report() {
+  let mut ts = TimerSet::new();
+  ts.set_duration_format(DurationFormat::Fixed(TimeUnit::Nanos));
+  ts.set_tree_report(true);
+  ts.record("a", Duration::from_nanos(100));
+  ts.record("a/b", Duration::from_nanos(60));
+  ts.record("a/b/c", Duration::from_nanos(30));
+  ts.record("a/d", Duration::from_nanos(20));
+  ts.record("e/f", Duration::from_nanos(10));
+  let lines = ::std::cell::RefCell::new(Vec::new());
+  ts.report_sorted(&|line: &str| lines.borrow_mut().push(line.to_string()), SortOrder::descending(SortBy::TotalTime));
+  let lines = lines.into_inner();
+  let names: Vec<&str> = lines.iter().map(|line| line.split(':').next().unwrap()).collect();
+  assert_eq!(names, vec!["a", "|-- b", "|   `-- c", "`-- d", "e", "`-- f"]);
+  assert!(lines[1].contains("(self 30ns, children 30ns), 60.0% of parent, 60.0% of total"), "{}", lines[1]);
+  assert!(!lines[5].contains("of parent"), "{}", lines[5]);
+}
+
+#[cfg(all(feature = "std", not(feature = "disable")))]
+#[test]
+fn test_reset() {
+  let ts = TimerSet::new();
+  ts.time("a", || {});
+  ts.time("b", || {});
+  ts.reset_all();
+  ts.write_csv(Vec::new()).unwrap();
+  assert_eq!(ts.remove("a").unwrap().number_of_windows, 0);
+  assert!(ts.remove("a").is_none());
+  ts.time("b", || {});
+  let timers = ts.merged();
+  assert_eq!(timers["b"].number_of_windows, 1);
+  assert!(timers["b"].min_time().is_some());
+}
+
+#[cfg(all(feature = "std", not(feature = "disable")))]
+#[test]
+fn test_take_snapshot() {
+  let ts = TimerSet::new();
+  ts.time("a", || {});
+  ts.time("a", || {});
+  let snapshot = ts.take_snapshot();
+  assert_eq!(snapshot["a"].number_of_windows, 2);
+  assert_eq!(ts.take_snapshot()["a"].number_of_windows, 0);
+}
+
+#[cfg(all(feature = "std", not(feature = "disable")))]
+#[test]
+fn test_threads() {
+  let ts = Arc::new(TimerSet::new());
+  let threads: Vec<_> =
+    (0..4)
+      .map(|_| {
+        let ts = ts.clone();
+        std::thread::spawn(move || {
+          for _ in 0..10 {
+            ts.time("hello", || {});
+          }
+        })
+      })
+      .collect();
+  for thread in threads {
+    thread.join().unwrap();
+  }
+  ts.time("hello", || {});
+  assert_eq!(ts.shards.lock().unwrap().len(), 5);
+  assert_eq!(ts.merged()["hello"].number_of_windows, 41);
+  assert_eq!(ts.take_snapshot()["hello"].number_of_windows, 41);
+  assert_eq!(ts.remove("hello").unwrap().number_of_windows, 0);
+}
+
+#[cfg(all(feature = "std", not(feature = "disable")))]
+#[test]
+fn test_set_clock() {
+  let mut ts = TimerSet::new();
+  ts.set_clock(FastClock::new());
+  ts.time("hello", || std::thread::sleep(Duration::from_millis(2)));
+  assert!(ts.merged()["hello"].total_time >= 1_000_000);
+}
+
+#[cfg(all(feature = "std", not(feature = "disable")))]
+#[test]
+fn test_push_scope() {
+  let mut ts = TimerSet::new();
+  {
+    let _render = ts.push_scope("render");
+    ts.time("draw", || {});
+    let _shadows = ts.push_scope("shadows");
+    ts.time("upload", || {});
+  }
+  ts.time("draw", || {});
+  ts.set_hierarchical(true);
+  {
+    let _render = ts.push_scope("render");
+    ts.time("frame", || ts.time("draw", || {}));
+  }
+  let mut names: Vec<_> = ts.merged().into_keys().collect();
+  names.sort();
+  assert_eq!(names, vec!["draw", "render.draw", "render.frame", "render.frame/render.draw", "render.shadows.upload"]);
+}
+
+#[test]
+fn test_ewma() {
+  let mut sw = Stopwatch::with_config(StopwatchConfig { ewma_alpha: 0.5, ..StopwatchConfig::default() });
+  assert_eq!(sw.ewma(), None);
+  sw.add_window(1_000);
+  assert_eq!(sw.ewma(), Some(Duration::from_nanos(1_000)));
+  for _ in 0..20 {
+    sw.add_window(100);
+  }
+  // The mean still remembers the first window, but the average has moved on.
+  assert!(sw.stats().mean.unwrap() > Duration::from_nanos(140));
+  assert!(sw.ewma().unwrap() < Duration::from_nanos(101));
+  // A window standing for two moves it twice.
+  sw.add_windows_ending(300, 2, Instant::now());
+  assert_eq!(sw.ewma(), Some(Duration::from_nanos(250)));
+  assert_eq!(powu(0.5, 10), 1.0 / 1024.0);
+}
+
+#[test]
+fn test_variance() {
+  let mut sw = Stopwatch::new();
+  assert_eq!(sw.variance(), None);
+  sw.add_window(2);
+  assert_eq!(sw.variance(), Some(0.0));
+  for &x in [4, 4, 4, 5, 5, 7, 9].iter() {
+    sw.add_window(x);
+  }
+  assert!((sw.variance().unwrap() - 32.0 / 7.0).abs() < 1e-9);
+
+  let mut a = Stopwatch::new();
+  let mut b = Stopwatch::new();
+  for &x in [2, 4, 4, 4].iter() {
+    a.add_window(x);
+  }
+  for &x in [5, 5, 7, 9].iter() {
+    b.add_window(x);
+  }
+  a.merge(&b);
+  assert!((a.variance().unwrap() - 32.0 / 7.0).abs() < 1e-9);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_rate() {
+  let mut sw = Stopwatch::new();
+  assert_eq!(sw.rate(), None);
+  let start = Instant::now();
+  sw.add_window_ending(1_000_000, start + Duration::from_millis(1));
+  sw.add_window_ending(1_000_000, start + Duration::from_millis(500));
+  assert_eq!(sw.observed_interval(), Some(Duration::from_millis(500)));
+  assert_eq!(sw.rate(), Some(4.0));
+
+  let mut other = Stopwatch::new();
+  other.add_window_ending(1_000_000, start + Duration::from_millis(1_000));
+  sw.merge(&other);
+  assert_eq!(sw.observed_interval(), Some(Duration::from_millis(1_000)));
+  assert_eq!(sw.rate(), Some(3.0));
+}
+
+#[cfg(all(feature = "std", not(feature = "disable")))]
+#[test]
+fn test_sample_rate() {
+  let mut ts = TimerSet::new();
+  ts.set_sample_rate("hot", 4);
+  let mut calls = 0;
+  for _ in 0..10 {
+    ts.time("hot", || calls += 1);
+  }
+  drop(ts.start("hot"));
+  assert_eq!(calls, 10);
+  let timers = ts.merged();
+  // Calls 0, 4 and 8 are timed, each standing for 4 calls.
+  assert_eq!(timers["hot"].number_of_windows, 12);
+
+  ts.set_sample_rate("hot", 1);
+  ts.time("hot", || {});
+  assert_eq!(ts.merged()["hot"].number_of_windows, 13);
+}
+
+#[cfg(all(feature = "std", not(feature = "disable")))]
+#[test]
+fn test_set_enabled() {
+  let ts = TimerSet::new();
+  ts.set_enabled(false);
+  assert_eq!(ts.time("a", || 3), 3);
+  drop(ts.start("b"));
+  assert!(ts.merged().is_empty());
+
+  ts.set_enabled(true);
+  ts.time("a", || {});
+  assert_eq!(ts.merged()["a"].number_of_windows, 1);
+}
+
+#[cfg(feature = "disable")]
+#[test]
+fn test_disable() {
+  let ts = TimerSet::new();
+  assert!(!ts.is_enabled());
+  assert_eq!(ts.time("a", || 3), 3);
+  assert_eq!(time("a", || 4), 4);
+  assert!(ts.merged().is_empty());
+  assert!(clone().merged().is_empty());
+
+  // Stopwatches used on their own don't read the clock either.
+  let mut sw = Stopwatch::new();
+  assert_eq!(sw.timed(|| 5), 5);
+  assert_eq!(sw.timed_with_result(|| 6), (6, Duration::ZERO));
+  assert_eq!(sw.number_of_windows, 0);
+  let atomic = AtomicStopwatch::new();
+  assert_eq!(atomic.timed(|| 7), 7);
+  assert_eq!(atomic.to_stopwatch().number_of_windows, 0);
+  let cell = StopwatchCell::new();
+  assert_eq!(cell.timed(|| 8), 8);
+  assert_eq!(cell.get().number_of_windows, 0);
+  declare_timers! { PARSE }
+  assert_eq!(Timers::PARSE.time(|| 9), 9);
+  assert_eq!(Timers::PARSE.stopwatch().to_stopwatch().number_of_windows, 0);
+}
+
+#[cfg(all(feature = "std", not(feature = "disable")))]
+#[test]
+fn test_threads_time_into_own_shards() {
+  use std::sync::Barrier;
+
+  // Each thread times into its own shard, however many time at once. How
+  // much that saves over one shared lock is measured by the `contention`
+  // example.
+  let ts = TimerSet::new();
+  let barrier = Barrier::new(8);
+  ::std::thread::scope(|s| {
+    for i in 0..8 {
+      let (ts, barrier) = (&ts, &barrier);
+      s.spawn(move || {
+        let name = format!("thread{}", i);
+        barrier.wait();
+        for _ in 0..10_000 {
+          ts.time(&name, || {});
+        }
+      });
+    }
+  });
+  assert_eq!(lock(&ts.shards).len(), 8);
+  for shard in lock(&ts.shards).iter() {
+    assert_eq!(lock(shard).timers.len(), 1);
+  }
+  assert_eq!(ts.merged().values().map(|sw| sw.number_of_windows).sum::<u64>(), 80_000);
+}
+
+#[cfg(all(feature = "std", not(feature = "disable")))]
+#[test]
+fn test_time_with_result() {
+  let ts = TimerSet::new();
+  let (x, dt) = ts.time_with_result("a", || {
+    ::std::thread::sleep(Duration::from_millis(1));
+    3
+  });
+  assert_eq!(x, 3);
+  assert!(dt >= Duration::from_millis(1));
+  assert_eq!(ts.merged()["a"].total_time, to_nanos(dt));
+
+  let mut sw = Stopwatch::new();
+  let (y, dt) = sw.timed_with_result(|| 4);
+  assert_eq!(y, 4);
+  assert_eq!(sw.max_time(), Some(dt));
+}
+
+#[cfg(all(feature = "std", not(feature = "disable")))]
+#[test]
+fn test_record() {
+  let mut ts = TimerSet::new();
+  ts.set_hierarchical(true);
+  ts.record("query", Duration::from_millis(3));
+  ts.time("request", || ts.record("query", Duration::from_millis(2)));
+  let timers = ts.merged();
+  assert_eq!(timers["query"].total_time, 3_000_000);
+  assert_eq!(timers["request/query"].total_time, 2_000_000);
+
+  let mut sw = Stopwatch::new();
+  sw.record(Duration::from_micros(5));
+  assert_eq!(sw.min_time(), Some(Duration::from_micros(5)));
+}
+
+#[cfg(all(feature = "std", not(feature = "disable")))]
+#[test]
+fn test_set_warmup() {
+  let mut ts = TimerSet::new();
+  ts.set_warmup("cold", 2, Warmup::Separate);
+  ts.set_warmup("dropped", 1, Warmup::Discard);
+  for ms in [9, 5, 1, 1].iter() {
+    ts.record("cold", Duration::from_millis(*ms));
+    ts.time("dropped", || {});
+  }
+  let timers = ts.merged();
+  assert_eq!(timers["cold"].number_of_windows, 2);
+  assert_eq!(timers["cold"].max_time(), Some(Duration::from_millis(1)));
+  assert_eq!(timers["cold.warmup"].total_time, 14_000_000);
+  assert_eq!(timers["dropped"].number_of_windows, 3);
+
+  // Warm-up only happens once, even across resets.
+  ts.take_snapshot();
+  ts.reset_all();
+  ts.record("cold", Duration::from_millis(9));
+  assert_eq!(ts.merged()["cold"].number_of_windows, 1);
+}
+
+#[cfg(all(feature = "std", not(feature = "disable")))]
+#[test]
+fn test_time_result() {
+  let ts = TimerSet::new();
+  for i in 0..3 {
+    let _ = ts.time_result("parse", || if i == 0 { Err(()) } else { Ok(i) });
+  }
+  let timers = ts.merged();
+  assert_eq!(timers["parse"].number_of_windows, 3);
+  assert_eq!(timers["parse.ok"].number_of_windows, 2);
+  assert_eq!(timers["parse.err"].number_of_windows, 1);
+  assert_eq!(timers["parse"].total_time, timers["parse.ok"].total_time + timers["parse.err"].total_time);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_pause_resume() {
+  use std::thread::sleep;
+
+  let mut sw = Stopwatch::new();
+  assert_eq!(sw.stop(), None);
+  sw.start();
+  sleep(Duration::from_millis(1));
+  sw.pause();
+  sleep(Duration::from_millis(20));
+  sw.resume();
+  sleep(Duration::from_millis(1));
+  let dt = sw.stop().unwrap();
+  assert!(dt >= Duration::from_millis(2) && dt < Duration::from_millis(20), "dt = {:?}", dt);
+  assert_eq!(sw.number_of_windows, 1);
+  assert_eq!(sw.max_time(), Some(dt));
+}
+
+#[cfg(all(feature = "std", not(feature = "disable")))]
+#[test]
+fn test_time_static() {
+  let ts = TimerSet::new();
+  ts.time_static("a", || {});
+  ts.time("a", || {});
+  {
+    let _scope = ts.push_scope("s");
+    ts.time_static("a", || {});
+  }
+  let timers = ts.merged();
+  let (key, sw) = timers.get_key_value("a").unwrap();
+  assert!(matches!(*key, Name::Static(_)));
+  assert_eq!(sw.number_of_windows, 2);
+  assert_eq!(timers["s.a"].number_of_windows, 1);
+}
+
+#[test]
+fn test_timed_with_clock() {
+  use core::sync::atomic::{AtomicU64, Ordering};
+
+  // Ticks are microseconds, and each reading is one tick later.
+  struct FakeClock(AtomicU64);
+  impl Clock for FakeClock {
+    fn now(&self) -> u64 {
+      self.0.fetch_add(1, Ordering::Relaxed)
+    }
+    fn to_nanos(&self, ticks: u64) -> u64 {
+      ticks * 1_000
+    }
+  }
+
+  let clock = FakeClock(AtomicU64::new(0));
+  let mut sw = Stopwatch::new();
+  assert_eq!(sw.timed_with_clock(&clock, || 7), (7, Duration::from_micros(1)));
+  assert_eq!(sw.number_of_windows, 1);
+  assert_eq!(sw.max_time(), Some(Duration::from_micros(1)));
+}
+
+#[test]
+fn test_timed_with_clock_no_std() {
+  use core::sync::atomic::{AtomicU64, Ordering};
+
+  // A cycle counter at 2 ticks a nanosecond, which each window's event
+  // advances by however long it's meant to take.
+  struct CycleCounter(AtomicU64);
+  impl Clock for CycleCounter {
+    fn now(&self) -> u64 {
+      self.0.load(Ordering::Relaxed)
+    }
+    fn to_nanos(&self, ticks: u64) -> u64 {
+      ticks / 2
+    }
+  }
+
+  let clock = CycleCounter(AtomicU64::new(0));
+  let mut sw = Stopwatch::new();
+  for &ns in [100u64, 300, 200].iter() {
+    let (ret, dt) = sw.timed_with_clock(&clock, || {
+      clock.0.fetch_add(ns * 2, Ordering::Relaxed);
+      ns
+    });
+    assert_eq!(dt, Duration::from_nanos(ret));
+  }
+  sw.record(Duration::from_nanos(400));
+  assert_eq!(sw.number_of_windows, 4);
+  assert_eq!(sw.total_time, 1_000);
+  assert_eq!(sw.min_time(), Some(Duration::from_nanos(100)));
+  assert_eq!(sw.max_time(), Some(Duration::from_nanos(400)));
+  assert!(sw.quantile(0.5).is_some());
+
+  let mut merged = Stopwatch::new();
+  merged.merge(&sw);
+  merged.merge(&sw);
+  assert_eq!(merged.number_of_windows, 8);
+  assert_eq!(merged.total_time, 2_000);
+}
+
+#[cfg(all(feature = "std", not(feature = "disable")))]
+#[test]
+fn test_poisoned() {
+  let ts = TimerSet::new();
+  ts.time("a", || {});
+  // Panic while holding the set's locks, and this thread's shard.
+  ::std::thread::scope(|s| {
+    let panicked = s.spawn(|| {
+      let _shards = ts.shards.lock().unwrap();
+      panic!("poisoning the shards");
+    });
+    assert!(panicked.join().is_err());
+  });
+  assert!(::std::panic::catch_unwind(::std::panic::AssertUnwindSafe(|| {
+    ts.with_shard_timers(|_| panic!("poisoning a shard"))
+  })).is_err());
+  assert!(ts.shards.is_poisoned());
+
+  ts.time("a", || {});
+  ::std::thread::scope(|s| {
+    s.spawn(|| ts.time("a", || {}));
+  });
+  assert_eq!(ts.merged()["a"].number_of_windows, 3);
+}
@@ -0,0 +1,178 @@
+//! Macros for timing with the thread-local `TimerSet`, or with timers
+//! declared up front.
+
+/// Times a block with the thread-local `TimerSet`.
+///
+/// The timer is named by the given expression, or if there isn't one, by
+/// where the block is in the source, e.g. `my_crate::parse (src/parse.rs:12)`.
+///
+/// ```
+/// #[macro_use]
+/// extern crate stopwatch;
+///
+/// fn main() {
+///   let x = time_block!("parse", { 1 + 1 });
+///   let y = time_block!({ x * 2 });
+///   assert_eq!(y, 4);
+/// }
+/// ```
+///
+/// The block runs inside a closure, so `return` and `?` leave the block, not
+/// the enclosing function.
+#[macro_export]
+macro_rules! time_block {
+  ($name:expr, $body:block) => {
+    $crate::time($name, || $body)
+  };
+  ($body:block) => {
+    $crate::time_static(concat!(module_path!(), " (", file!(), ":", line!(), ")"), || $body)
+  };
+}
+
+/// Declares a fixed set of timers, as an enum named `Timers` with a variant
+/// per timer, each timed by a static `AtomicStopwatch`. Timing under one
+/// just indexes a table, so there's no hashing or locking, and a misspelt
+/// timer is a compile error.
+///
+/// ```
+/// #[macro_use]
+/// extern crate stopwatch;
+///
+/// declare_timers! { PARSE, RENDER, IO }
+///
+/// fn main() {
+///   let x = Timers::time(Timers::PARSE, || 1 + 1);
+///   Timers::RENDER.time(|| {});
+/// #   if stopwatch::DISABLED { return; }
+///   assert_eq!(Timers::PARSE.stopwatch().to_stopwatch().number_of_windows, 1);
+///   assert_eq!(Timers::IO.name(), "IO");
+///   Timers::print();
+/// }
+/// ```
+///
+/// The timers are shared by every thread. Like `AtomicStopwatch`, they
+/// don't keep a histogram, or track the variance or throughput.
+#[macro_export]
+macro_rules! declare_timers {
+  ($($name:ident),+ $(,)*) => {
+    #[allow(dead_code, non_camel_case_types, clippy::upper_case_acronyms)]
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+    /// The timers declared with `declare_timers!`.
+    pub enum Timers {
+      $($name),+
+    }
+
+    // Not every program uses every function.
+    #[allow(dead_code)]
+    impl Timers {
+      /// Every timer, in the order declared.
+      pub const ALL: &'static [Timers] = &[$(Timers::$name),+];
+
+      /// Returns the timer's name, as declared.
+      pub fn name(self) -> &'static str {
+        match self {
+          $(Timers::$name => stringify!($name)),+
+        }
+      }
+
+      #[inline]
+      /// Returns the timer's stopwatch.
+      pub fn stopwatch(self) -> &'static $crate::AtomicStopwatch {
+        static TIMERS: [$crate::AtomicStopwatch; Timers::ALL.len()] =
+          [const { $crate::AtomicStopwatch::new() }; Timers::ALL.len()];
+        &TIMERS[self as usize]
+      }
+
+      #[inline]
+      /// Times `f` under the timer.
+      pub fn time<T, F: FnOnce() -> T>(self, f: F) -> T {
+        if $crate::DISABLED {
+          return f();
+        }
+        self.stopwatch().timed(f)
+      }
+
+      /// Writes every timer's stats through `reporter`, a line per timer, in
+      /// the order declared.
+      pub fn report(reporter: &dyn $crate::Reporter) {
+        for &timer in Timers::ALL {
+          reporter.line(&timer.stopwatch().to_stopwatch().named(timer.name()).to_string());
+        }
+      }
+
+      /// Logs every timer's stats at `info` level.
+      pub fn print() {
+        Timers::report(&$crate::LogReporter::default());
+      }
+
+      /// Forgets every timer's windows.
+      pub fn reset_all() {
+        for &timer in Timers::ALL {
+          timer.stopwatch().reset();
+        }
+      }
+    }
+  };
+}
+
+#[cfg(not(feature = "disable"))]
+#[test]
+fn test_time_block() {
+  let x = time_block!("test_time_block", { 1 });
+  let line = line!() + 1;
+  let y = time_block!({ x + 1 });
+  assert_eq!(y, 2);
+
+  let timers = ::clone().merged();
+  assert_eq!(timers["test_time_block"].number_of_windows, 1);
+  let name = format!("stopwatch::macros ({}:{})", file!(), line);
+  assert_eq!(timers[name.as_str()].number_of_windows, 1);
+}
+
+#[cfg(not(feature = "disable"))]
+#[test]
+fn test_declare_timers() {
+  declare_timers! { PARSE, RENDER, IO, }
+
+  assert_eq!(Timers::time(Timers::PARSE, || 7), 7);
+  Timers::PARSE.time(|| {});
+  Timers::IO.time(|| {});
+  assert_eq!(Timers::ALL, &[Timers::PARSE, Timers::RENDER, Timers::IO]);
+  assert_eq!(Timers::PARSE.stopwatch().to_stopwatch().number_of_windows, 2);
+  assert_eq!(Timers::RENDER.stopwatch().to_stopwatch().number_of_windows, 0);
+
+  let lines = ::std::cell::RefCell::new(Vec::new());
+  Timers::report(&|line: &str| lines.borrow_mut().push(line.to_string()));
+  let lines = lines.into_inner();
+  assert_eq!(lines.len(), 3);
+  assert!(lines[0].starts_with("PARSE: "), "{}", lines[0]);
+  assert_eq!(lines[1], "RENDER never ran");
+
+  Timers::reset_all();
+  assert_eq!(Timers::IO.stopwatch().to_stopwatch().number_of_windows, 0);
+}
+
+#[cfg(feature = "macros")]
+#[cfg(not(feature = "disable"))]
+#[test]
+fn test_timed() {
+  #[::timed]
+  fn checked_double(x: u32) -> Option<u32> {
+    if x > 100 {
+      return None;
+    }
+    let y = x.checked_mul(2)?;
+    Some(y)
+  }
+
+  #[::timed("test_timed")]
+  fn named() {}
+
+  assert_eq!(checked_double(2), Some(4));
+  assert_eq!(checked_double(200), None);
+  named();
+
+  let timers = ::clone().merged();
+  assert_eq!(timers["stopwatch::macros::checked_double"].number_of_windows, 2);
+  assert_eq!(timers["test_timed"].number_of_windows, 1);
+}
@@ -0,0 +1,89 @@
+//! Markdown table export of timer statistics.
+
+use std::fmt::Write;
+
+use TimerSet;
+
+/// Escapes `name` for a Markdown table cell, by backslashing the pipes that
+/// would split it, and anything that would start formatting.
+fn escape(name: &str) -> String {
+  let mut escaped = String::with_capacity(name.len());
+  for c in name.chars() {
+    match c {
+      '|' | '\\' | '`' | '*' | '_' | '<' | '[' => {
+        escaped.push('\\');
+        escaped.push(c);
+      },
+      '\n' | '\r' => escaped.push(' '),
+      c => escaped.push(c),
+    }
+  }
+  escaped
+}
+
+impl<K> TimerSet<K> {
+  /// Renders every timer that has run as a GitHub-flavored Markdown table,
+  /// with the name, count, total, mean, p95 and max of each, e.g. to paste
+  /// into a pull request. Timers are listed in the set's `report_order`, and
+  /// durations rendered in its duration format. The p95 is `-` for timers
+  /// that aren't keeping a histogram.
+  pub fn to_markdown(&self) -> String {
+    let mut timers = self.sorted_timers();
+    let order = self.order;
+    timers.sort_by(|a, b| order.compare((&a.0, Some(&a.1)), (&b.0, Some(&b.1))));
+
+    let mut out = String::new();
+    out.push_str("| name | count | total | mean | p95 | max |\n");
+    out.push_str("| :--- | ---: | ---: | ---: | ---: | ---: |\n");
+    for (name, sw) in timers.iter() {
+      let p95 =
+        match sw.histogram.as_ref().and_then(|h| h.quantile(0.95)) {
+          Some(p95) => self.format.format(p95),
+          None => "-".to_string(),
+        };
+      writeln!(
+        out,
+        "| {} | {} | {} | {} | {} | {} |",
+        escape(name),
+        sw.number_of_windows,
+        self.format.format(sw.total_time),
+        self.format.format(sw.total_time / sw.number_of_windows),
+        p95,
+        self.format.format(sw.max_time),
+      )
+      .unwrap();
+    }
+    out
+  }
+}
+
+#[test]
+fn test_escape() {
+  assert_eq!(escape("db|query"), "db\\|query");
+  assert_eq!(escape("my_fn*\n"), "my\\_fn\\* ");
+}
+
+#[cfg(not(feature = "disable"))]
+#[test]
+fn test_to_markdown() {
+  use std::time::Duration;
+  use {DurationFormat, SortBy, SortOrder, TimeUnit};
+
+  let mut ts = TimerSet::builder().histogram(false).report_order(SortOrder::descending(SortBy::TotalTime)).build();
+  ts.set_duration_format(DurationFormat::Fixed(TimeUnit::Micros));
+  ts.record("parse", Duration::from_micros(10));
+  ts.record("parse", Duration::from_micros(30));
+  ts.record("render", Duration::from_micros(100));
+  assert_eq!(ts.to_markdown(), concat!(
+    "| name | count | total | mean | p95 | max |\n",
+    "| :--- | ---: | ---: | ---: | ---: | ---: |\n",
+    "| render | 1 | 100us | 100us | - | 100us |\n",
+    "| parse | 2 | 40us | 20us | - | 30us |\n",
+  ));
+
+  let ts = TimerSet::new();
+  ts.record("a", Duration::from_millis(2));
+  let out = ts.to_markdown();
+  assert!(out.ends_with(" |\n") && out.lines().nth(2).unwrap().starts_with("| a | 1 | 2.0ms | 2.0ms | "), "{}", out);
+  assert!(!out.contains("| - |"), "{}", out);
+}
@@ -0,0 +1,95 @@
+//! A `metrics` recorder that aggregates histograms into timers.
+
+use metrics::{Counter, Gauge, Histogram, HistogramFn, Key, KeyName, Metadata, Recorder, SharedString, Unit};
+use std::sync::Arc;
+
+use {tags, Allocations, TimerSet};
+
+/// A `metrics::Recorder` that records every histogram into a `TimerSet`, so
+/// `metrics::histogram!` values show up in its reports alongside directly
+/// timed code.
+///
+/// Histogram values are taken to be seconds, which is what `metrics` records
+/// a `Duration` as, and labels become tags, as with
+/// `TimerSet::time_with_tags`, with any of `{},=` in them replaced by `_`.
+/// Counters and gauges are ignored.
+pub struct TimerRecorder {
+  timers: Arc<TimerSet>,
+}
+
+impl TimerRecorder {
+  /// Creates a recorder that records histograms into `timers`.
+  pub fn new(timers: Arc<TimerSet>) -> TimerRecorder {
+    TimerRecorder {
+      timers,
+    }
+  }
+}
+
+/// Names a timer after a metric, tagged with its labels, e.g.
+/// `requests{path=/}`.
+fn timer_name(key: &Key) -> String {
+  let labels: Vec<(&str, &str)> = key.labels().map(|l| (l.key(), l.value())).collect();
+  tags::tagged_name(key.name(), &labels)
+}
+
+/// One histogram, feeding the timer of the same name.
+struct TimerHistogram {
+  timers: Arc<TimerSet>,
+  name: String,
+}
+
+impl HistogramFn for TimerHistogram {
+  fn record(&self, value: f64) {
+    self.record_many(value, 1);
+  }
+
+  fn record_many(&self, value: f64, count: usize) {
+    // Negative and NaN values saturate to zero.
+    let dt = (value * 1e9) as u64;
+    if self.timers.is_enabled() {
+      self.timers.add_windows(&self.name, None, dt, count as u64, Allocations::default());
+    }
+  }
+}
+
+impl Recorder for TimerRecorder {
+  fn describe_counter(&self, _key: KeyName, _unit: Option<Unit>, _description: SharedString) {}
+
+  fn describe_gauge(&self, _key: KeyName, _unit: Option<Unit>, _description: SharedString) {}
+
+  fn describe_histogram(&self, _key: KeyName, _unit: Option<Unit>, _description: SharedString) {}
+
+  fn register_counter(&self, _key: &Key, _metadata: &Metadata) -> Counter {
+    Counter::noop()
+  }
+
+  fn register_gauge(&self, _key: &Key, _metadata: &Metadata) -> Gauge {
+    Gauge::noop()
+  }
+
+  fn register_histogram(&self, key: &Key, _metadata: &Metadata) -> Histogram {
+    Histogram::from_arc(Arc::new(TimerHistogram {
+      timers: self.timers.clone(),
+      name: timer_name(key),
+    }))
+  }
+}
+
+#[cfg(not(feature = "disable"))]
+#[test]
+fn test_timer_recorder() {
+  use std::time::Duration;
+
+  let timers = Arc::new(TimerSet::new());
+  let recorder = TimerRecorder::new(timers.clone());
+  metrics::with_local_recorder(&recorder, || {
+    metrics::histogram!("latency").record(Duration::from_millis(3));
+    metrics::histogram!("latency").record(0.001);
+    metrics::histogram!("latency", "path" => "/").record(0.002);
+  });
+  let merged = timers.merged();
+  assert_eq!(merged["latency"].number_of_windows, 2);
+  assert_eq!(merged["latency"].total_time / 1_000, 4_000);
+  assert_eq!(merged["latency{path=/}"].number_of_windows, 1);
+}
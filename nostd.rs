@@ -0,0 +1,149 @@
+//! A `TimerSet` for targets without std. There are no threads to shard the
+//! set by, or a `Mutex` to share it with, so its timers sit behind a
+//! `RefCell`, and it's timed with a `Clock` the user supplies, e.g. a cycle
+//! counter. To time from interrupts too, keep it behind a critical section.
+
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use core::cell::RefCell;
+use core::mem;
+use core::time::Duration;
+
+use {to_nanos, Clock, Stopwatch, StopwatchConfig};
+
+/// A set of stopwatches for multiple, named events, timed with a
+/// user-supplied `Clock`.
+///
+/// This is the set without the `std` feature. It keeps the same stats per
+/// timer, but none of the std set's reporting, sampling or hierarchy.
+pub struct TimerSet {
+  config: StopwatchConfig,
+  clock: Box<dyn Clock>,
+  timers: RefCell<BTreeMap<String, Stopwatch>>,
+}
+
+impl TimerSet {
+  /// Creates a new set of timers, timed with `clock`.
+  pub fn with_clock<C: Clock + 'static>(clock: C) -> TimerSet {
+    TimerSet::with_clock_and_config(clock, StopwatchConfig::default())
+  }
+
+  /// Creates a new set of timers, timed with `clock`, whose stopwatches keep
+  /// the stats specified by `config`.
+  pub fn with_clock_and_config<C: Clock + 'static>(clock: C, config: StopwatchConfig) -> TimerSet {
+    TimerSet {
+      config,
+      clock: Box::new(clock),
+      timers: RefCell::new(BTreeMap::new()),
+    }
+  }
+
+  /// Times windows with `clock` from now on.
+  pub fn set_clock<C: Clock + 'static>(&mut self, clock: C) {
+    self.clock = Box::new(clock);
+  }
+
+  /// Times the execution of a function, and logs it under a timer with
+  /// the given name. `f` can time under this set too.
+  pub fn time<T, F: FnOnce() -> T>(&self, name: &str, f: F) -> T {
+    if cfg!(feature = "disable") {
+      return f();
+    }
+    let then = self.clock.now();
+    let ret = f();
+    let dt = self.clock.to_nanos(self.clock.now().saturating_sub(then));
+    self.with_stopwatch(name, |sw| sw.add_window(dt));
+    ret
+  }
+
+  /// Logs a time window measured elsewhere under the timer with the given
+  /// name.
+  pub fn record(&self, name: &str, d: Duration) {
+    if cfg!(feature = "disable") {
+      return;
+    }
+    self.with_stopwatch(name, |sw| sw.add_window(to_nanos(d)));
+  }
+
+  /// Runs `f` on the stopwatch with the given name, creating it if need be.
+  fn with_stopwatch<F: FnOnce(&mut Stopwatch)>(&self, name: &str, f: F) {
+    let mut timers = self.timers.borrow_mut();
+    if let Some(sw) = timers.get_mut(name) {
+      return f(sw);
+    }
+    let mut sw = Stopwatch::with_config(self.config);
+    f(&mut sw);
+    timers.insert(name.to_string(), sw);
+  }
+
+  /// Returns a copy of the timer with the given name, or `None` if it never
+  /// ran.
+  pub fn get(&self, name: &str) -> Option<Stopwatch> {
+    self.timers.borrow().get(name).cloned()
+  }
+
+  /// Resets every timer in this set. The timers themselves are kept, and
+  /// count as never having run until they're used again.
+  pub fn reset_all(&self) {
+    for sw in self.timers.borrow_mut().values_mut() {
+      sw.reset();
+    }
+  }
+
+  /// Copies out every timer and resets them, so that no window is lost
+  /// between reading the stats and resetting them.
+  pub fn take_snapshot(&self) -> BTreeMap<String, Stopwatch> {
+    let mut timers = self.timers.borrow_mut();
+    timers.iter_mut().map(|(name, sw)| (name.clone(), mem::replace(sw, Stopwatch::with_config(self.config)))).collect()
+  }
+
+  /// Removes the timer with the given name, returning it if it existed.
+  pub fn remove(&self, name: &str) -> Option<Stopwatch> {
+    self.timers.borrow_mut().remove(name)
+  }
+}
+
+#[cfg(not(feature = "disable"))]
+#[test]
+fn test_timer_set_no_std() {
+  use core::sync::atomic::{AtomicU64, Ordering};
+
+  // A cycle counter at 2 ticks a nanosecond. It's shared with the set
+  // through a `&'static`, as the set owns its clock.
+  struct CycleCounter(&'static AtomicU64);
+  impl Clock for CycleCounter {
+    fn now(&self) -> u64 {
+      self.0.load(Ordering::Relaxed)
+    }
+    fn to_nanos(&self, ticks: u64) -> u64 {
+      ticks / 2
+    }
+  }
+
+  static CYCLES: AtomicU64 = AtomicU64::new(0);
+  let ts = TimerSet::with_clock(CycleCounter(&CYCLES));
+  ts.time("outer", || {
+    CYCLES.fetch_add(200, Ordering::Relaxed);
+    ts.time("inner", || CYCLES.fetch_add(100, Ordering::Relaxed));
+  });
+  ts.time("inner", || CYCLES.fetch_add(300, Ordering::Relaxed));
+  ts.record("inner", Duration::from_nanos(50));
+
+  let inner = ts.get("inner").unwrap();
+  assert_eq!(inner.number_of_windows, 3);
+  assert_eq!(inner.total_time, 250);
+  assert_eq!(ts.get("outer").unwrap().total_time, 150);
+  assert!(ts.get("missing").is_none());
+
+  let snapshot = ts.take_snapshot();
+  assert_eq!(snapshot.keys().map(|name| name.as_str()).collect::<alloc::vec::Vec<_>>(), ["inner", "outer"]);
+  assert_eq!(snapshot["inner"].number_of_windows, 3);
+  assert_eq!(ts.get("inner").unwrap().number_of_windows, 0);
+
+  ts.time("outer", || {});
+  ts.reset_all();
+  assert_eq!(ts.get("outer").unwrap().number_of_windows, 0);
+  assert!(ts.remove("outer").is_some());
+  assert!(ts.get("outer").is_none());
+}
@@ -0,0 +1,96 @@
+//! Combining and comparing stopwatches with the standard operators.
+
+use core::iter::Sum;
+use core::ops::{Add, AddAssign};
+
+use Stopwatch;
+
+/// Merges the stopwatches, like `Stopwatch::merge`.
+impl Add for Stopwatch {
+  type Output = Stopwatch;
+
+  fn add(mut self, other: Stopwatch) -> Stopwatch {
+    self.merge(&other);
+    self
+  }
+}
+
+/// Merges the stopwatches, like `Stopwatch::merge`.
+impl Add<&Stopwatch> for Stopwatch {
+  type Output = Stopwatch;
+
+  fn add(mut self, other: &Stopwatch) -> Stopwatch {
+    self.merge(other);
+    self
+  }
+}
+
+impl AddAssign for Stopwatch {
+  fn add_assign(&mut self, other: Stopwatch) {
+    self.merge(&other);
+  }
+}
+
+impl AddAssign<&Stopwatch> for Stopwatch {
+  fn add_assign(&mut self, other: &Stopwatch) {
+    self.merge(other);
+  }
+}
+
+/// Merges every stopwatch into a new one.
+impl Sum for Stopwatch {
+  fn sum<I: Iterator<Item = Stopwatch>>(iter: I) -> Stopwatch {
+    iter.fold(Stopwatch::new(), Add::add)
+  }
+}
+
+/// Merges every stopwatch into a new one.
+impl<'a> Sum<&'a Stopwatch> for Stopwatch {
+  fn sum<I: Iterator<Item = &'a Stopwatch>>(iter: I) -> Stopwatch {
+    iter.fold(Stopwatch::new(), Add::add)
+  }
+}
+
+/// Stopwatches are equal if they've clocked the same stats: the number of
+/// windows, their total, shortest, longest, mean and variance, how much of
+/// it was spent in nested timers, what they allocated, and how many went
+/// over their timeout. When the windows were clocked, and the distribution
+/// of them, aren't compared.
+impl PartialEq for Stopwatch {
+  fn eq(&self, other: &Stopwatch) -> bool {
+    self.number_of_windows == other.number_of_windows
+      && self.total_time == other.total_time
+      && self.min_time() == other.min_time()
+      && self.max_time() == other.max_time()
+      && self.mean == other.mean
+      && self.variance() == other.variance()
+      && self.allocations == other.allocations
+      && self.allocated_bytes == other.allocated_bytes
+      && self.violations == other.violations
+      && self.child_time == other.child_time
+  }
+}
+
+#[test]
+fn test_ops() {
+  let stopwatch = |windows: &[u64]| {
+    let mut sw = Stopwatch::new();
+    for &dt in windows {
+      sw.add_window(dt);
+    }
+    sw
+  };
+  let (a, b) = (stopwatch(&[1, 2]), stopwatch(&[3, 4]));
+  let all = stopwatch(&[1, 2, 3, 4]);
+  assert_eq!(a.clone() + &b, all);
+  assert!(a.clone() + b.clone() != a);
+  let mut sum = a.clone();
+  sum += b.clone();
+  assert_eq!(sum.total_time, 10);
+
+  let parts = [a, b, Stopwatch::new()];
+  let summed: Stopwatch = parts.iter().sum();
+  assert_eq!(summed, all);
+  assert_eq!(parts.iter().cloned().sum::<Stopwatch>().number_of_windows, 4);
+  assert_eq!(Stopwatch::default(), Stopwatch::new());
+}
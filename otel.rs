@@ -0,0 +1,119 @@
+//! Bridging timer stats into OpenTelemetry metrics.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use opentelemetry::metrics::{Counter, Gauge, Histogram, Meter};
+use opentelemetry::KeyValue;
+
+use {tags, ReporterHandle, Stopwatch, TimerSet};
+
+/// Returns the windows `sw` clocked, as how long they took in seconds and
+/// how many took that long. With a histogram, that's every bucket it has
+/// windows in; without, it's every window at the mean.
+fn windows(sw: &Stopwatch) -> Vec<(f64, u64)> {
+  if sw.number_of_windows == 0 {
+    return Vec::new();
+  }
+  match sw.histogram {
+    Some(ref histogram) => histogram.buckets().into_iter().map(|(dt, n)| (dt as f64 / 1e9, n)).collect(),
+    None => vec![(sw.total_time as f64 / sw.number_of_windows as f64 / 1e9, sw.number_of_windows)],
+  }
+}
+
+/// Records a set's stats with OpenTelemetry instruments, e.g. with
+/// `TimerSet::spawn_otel`, so they're exported by whichever meter provider
+/// the program has set up.
+///
+/// Timers are recorded in the `stopwatch.duration` histogram, in seconds,
+/// counters are added to the `stopwatch.counter` counter, and gauges set
+/// the `stopwatch.gauge` gauge. Each measurement has the timer's, counter's
+/// or gauge's name as its `name` attribute, and a tagged timer's tags as
+/// attributes too.
+///
+/// OpenTelemetry histograms take one measurement at a time, so a timer's
+/// windows are replayed one by one: each at the middle of its histogram
+/// bucket, or at the mean if the timer isn't keeping a histogram. That's a
+/// measurement per window, so busy timers are cheaper to export often.
+pub struct OtelExporter {
+  duration: Histogram<f64>,
+  counter: Counter<u64>,
+  gauge: Gauge<f64>,
+}
+
+impl OtelExporter {
+  /// Creates an exporter recording with instruments from `meter`.
+  pub fn new(meter: &Meter) -> OtelExporter {
+    OtelExporter {
+      duration: meter.f64_histogram("stopwatch.duration").with_unit("s").with_description("How long timed windows took").build(),
+      counter: meter.u64_counter("stopwatch.counter").with_description("Counts from TimerSet::count").build(),
+      gauge: meter.f64_gauge("stopwatch.gauge").with_description("Values from TimerSet::gauge").build(),
+    }
+  }
+
+  /// Records every timer, counter and gauge in `timers`.
+  pub fn export<K>(&self, timers: &TimerSet<K>) {
+    for (name, sw) in timers.sorted_timers().iter() {
+      let (name, tags) = tags::split_tags(name);
+      let mut attributes = vec![KeyValue::new("name", name.to_string())];
+      attributes.extend(tags.iter().map(|&(k, v)| KeyValue::new(k.to_string(), v.to_string())));
+      for (dt, n) in windows(sw) {
+        for _ in 0..n {
+          self.duration.record(dt, &attributes);
+        }
+      }
+    }
+    for (name, n) in timers.counters() {
+      self.counter.add(n, &[KeyValue::new("name", name)]);
+    }
+    for (name, gauge) in timers.gauges() {
+      self.gauge.record(gauge.last, &[KeyValue::new("name", name)]);
+    }
+  }
+}
+
+impl<K: 'static> TimerSet<K> {
+  /// Starts a thread that, every `interval`, takes a snapshot of this set,
+  /// resetting it, and records the snapshot through `exporter`, as
+  /// `spawn_reporter` does with reports.
+  pub fn spawn_otel(self: &Arc<Self>, interval: Duration, exporter: OtelExporter) -> ReporterHandle {
+    let timers = Arc::downgrade(self);
+    ReporterHandle::spawn(interval, move || {
+      match timers.upgrade() {
+        None => false,
+        Some(timers) => {
+          exporter.export(&timers.take_interval());
+          true
+        },
+      }
+    })
+  }
+}
+
+#[test]
+fn test_otel() {
+  use StopwatchConfig;
+
+  let mut sw = Stopwatch::new();
+  sw.add_window(1_000_000);
+  sw.add_window(1_000_000);
+  sw.add_window(8_000_000);
+  let replayed = windows(&sw);
+  assert_eq!(replayed.iter().map(|&(_, n)| n).sum::<u64>(), 3);
+  for &(dt, _) in replayed.iter() {
+    assert!((dt - 0.001).abs() < 0.0001 || (dt - 0.008).abs() < 0.0008, "{}", dt);
+  }
+
+  let mut sw = Stopwatch::with_config(StopwatchConfig { histogram: false, ..StopwatchConfig::default() });
+  assert!(windows(&sw).is_empty());
+  sw.add_window(1_000_000);
+  sw.add_window(3_000_000);
+  assert_eq!(windows(&sw), vec![(0.002, 2)]);
+
+  // Without a meter provider set up, the instruments record nothing.
+  let ts = TimerSet::new();
+  ts.record_with_tags("db", &[("table", "users")], Duration::from_millis(1));
+  ts.count("hits", 3);
+  ts.gauge("depth", 7.0);
+  OtelExporter::new(&::opentelemetry::global::meter("stopwatch")).export(&ts);
+}
@@ -0,0 +1,67 @@
+//! Measuring, and making up for, the cost of timing itself.
+
+use std::hint::black_box;
+use std::time::Duration;
+
+use {new_counters, TimerSet, Timers};
+
+/// How many empty calls `calibrate_overhead` times.
+const CALIBRATION_CALLS: u64 = 10_000;
+
+impl<K> TimerSet<K> {
+  /// Estimates how much time `time` adds to each window it logs, by timing
+  /// an empty closure many times with this set's clock and settings, and
+  /// returns it. This is the cost of the clock reads and the timer lookup
+  /// that fall inside a window, which skews windows of a few microseconds
+  /// and less.
+  ///
+  /// The estimate is then taken off every window in reports, as if it had
+  /// been set with `set_overhead`.
+  pub fn calibrate_overhead(&mut self) -> Duration {
+    let scratch = self.with_timers(Timers::default(), new_counters());
+    scratch.set_enabled(true);
+    for _ in 0..CALIBRATION_CALLS {
+      scratch.time("calibration", || black_box(()));
+    }
+    let sw = &scratch.merged()["calibration"];
+    let overhead = Duration::from_nanos(sw.total_time / sw.number_of_windows.max(1));
+    self.overhead = Some(overhead);
+    overhead
+  }
+
+  /// Sets how much time to take off every window in reports, e.g. an
+  /// estimate from `calibrate_overhead`, or `None` to report windows as
+  /// they were measured. The timers themselves always keep the raw times,
+  /// so `merged`, `snapshot` and the exports are unaffected.
+  pub fn set_overhead(&mut self, overhead: Option<Duration>) {
+    self.overhead = overhead;
+  }
+
+  /// Returns how much time reports take off every window, if any.
+  pub fn overhead(&self) -> Option<Duration> {
+    self.overhead
+  }
+}
+
+#[cfg(not(feature = "disable"))]
+#[test]
+fn test_overhead() {
+  use std::cell::RefCell;
+  use {DurationFormat, TimeUnit};
+
+  let mut ts = TimerSet::new();
+  ts.set_duration_format(DurationFormat::Fixed(TimeUnit::Nanos));
+  for _ in 0..3 {
+    ts.record("a", Duration::from_nanos(5_000));
+  }
+  ts.set_overhead(Some(Duration::from_nanos(1_000)));
+  let lines = RefCell::new(Vec::new());
+  ts.report(&|line: &str| lines.borrow_mut().push(line.to_string()));
+  assert!(lines.borrow()[0].contains("12000ns"), "{}", lines.borrow()[0]);
+  assert_eq!(ts.merged()["a"].total_time, 15_000);
+
+  let overhead = ts.calibrate_overhead();
+  assert!(overhead < Duration::from_millis(1), "{:?}", overhead);
+  assert_eq!(ts.overhead(), Some(overhead));
+  assert!(!ts.merged().contains_key("calibration"));
+}
@@ -0,0 +1,118 @@
+//! Timing windows whose functions panic.
+
+use std::mem;
+
+use {Start, TimerSet};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+/// What to do with a window whose timed function panics, set with
+/// `TimerSet::set_on_panic`.
+pub enum OnPanic {
+  /// Logs it like any other window.
+  #[default]
+  Record,
+  /// Logs it under `name.panicked` instead, so it's counted, and timed,
+  /// separately.
+  RecordPanicked,
+  /// Doesn't log it.
+  Ignore,
+}
+
+/// Logs a window as it unwinds, if the function it's timing panics. Made by
+/// `TimerSet::on_unwind`, and defused with `finish` once the function
+/// returns.
+pub(crate) struct Unwind<'a, K: 'a> {
+  timers: &'a TimerSet<K>,
+  name: &'a str,
+  key: Option<&'static str>,
+  start: Start,
+  weight: u64,
+}
+
+impl<'a, K> Unwind<'a, K> {
+  /// Forgets the window, which returned, so is logged the usual way.
+  #[inline]
+  pub(crate) fn finish(self) {
+    mem::forget(self);
+  }
+}
+
+impl<'a, K> Drop for Unwind<'a, K> {
+  fn drop(&mut self) {
+    self.timers.stop_panicked(self.name, self.key, self.start, self.weight);
+  }
+}
+
+impl<K> TimerSet<K> {
+  /// Sets what to do with windows whose timed functions panic. By default
+  /// they're logged like any other, as they unwind.
+  pub fn set_on_panic(&mut self, on_panic: OnPanic) {
+    self.on_panic = on_panic;
+  }
+
+  /// Guards a window that started at `start`, logging it if the function
+  /// it's timing panics.
+  #[inline]
+  pub(crate) fn on_unwind<'a>(&'a self, name: &'a str, key: Option<&'static str>, start: Start, weight: u64) -> Unwind<'a, K> {
+    Unwind {
+      timers: self,
+      name,
+      key,
+      start,
+      weight,
+    }
+  }
+
+  /// Logs a window whose function panicked, as `on_panic` says to.
+  pub(crate) fn stop_panicked(&self, name: &str, key: Option<&'static str>, start: Start, weight: u64) {
+    match self.on_panic {
+      OnPanic::Record => {
+        self.stop(name, key, start, weight);
+      },
+      OnPanic::RecordPanicked => {
+        self.stop(&format!("{}.panicked", name), None, start, weight);
+      },
+      OnPanic::Ignore => self.discard_window(start),
+    }
+  }
+}
+
+#[cfg(not(feature = "disable"))]
+#[test]
+fn test_on_panic() {
+  use std::panic::{catch_unwind, AssertUnwindSafe};
+  use std::time::Duration;
+
+  let panics = |ts: &TimerSet| {
+    let sleep = || ::std::thread::sleep(Duration::from_millis(1));
+    assert!(catch_unwind(AssertUnwindSafe(|| ts.time("a", || { sleep(); panic!("a") }))).is_err());
+    assert!(catch_unwind(AssertUnwindSafe(|| ts.time_result("b", || -> Result<(), ()> { panic!("b") }))).is_err());
+    assert!(catch_unwind(AssertUnwindSafe(|| {
+      let _guard = ts.start("c");
+      panic!("c");
+    })).is_err());
+    ts.time("a", || {});
+    ts.merged()
+  };
+
+  let timers = panics(&TimerSet::new());
+  assert_eq!(timers["a"].number_of_windows, 2);
+  assert!(timers["a"].max_time().unwrap() >= Duration::from_millis(1));
+  assert_eq!(timers["b"].number_of_windows, 1);
+  assert!(!timers.contains_key("b.ok") && !timers.contains_key("b.err"));
+  assert_eq!(timers["c"].number_of_windows, 1);
+
+  let mut ts = TimerSet::new();
+  ts.set_on_panic(OnPanic::RecordPanicked);
+  let timers = panics(&ts);
+  assert_eq!(timers["a"].number_of_windows, 1);
+  assert_eq!(timers["a.panicked"].number_of_windows, 1);
+  assert_eq!(timers["c.panicked"].number_of_windows, 1);
+  assert!(!timers.contains_key("c"));
+
+  let mut ts = TimerSet::new();
+  ts.set_on_panic(OnPanic::Ignore);
+  let timers = panics(&ts);
+  assert_eq!(timers["a"].number_of_windows, 1);
+  assert!(!timers.contains_key("b") && !timers.contains_key("a.panicked"));
+}
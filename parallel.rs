@@ -0,0 +1,100 @@
+//! Timing rayon's data-parallel sections.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use rayon::iter::ParallelIterator;
+
+use {cputime, Instant, TimerSet};
+
+/// Sums the CPU time of the work done inside a parallel section, on
+/// whichever threads it ran. Made by `TimerSet::time_parallel`.
+pub struct ParallelTimer {
+  /// The CPU time so far, in nanoseconds.
+  cpu: AtomicU64,
+}
+
+impl ParallelTimer {
+  /// Runs `f`, adding the CPU time the calling thread spent in it.
+  pub fn time<T, F: FnOnce() -> T>(&self, f: F) -> T {
+    let start = cputime::thread();
+    let ret = f();
+    self.cpu.fetch_add(cputime::thread().saturating_sub(start), Ordering::Relaxed);
+    ret
+  }
+
+  /// Returns the CPU time summed so far.
+  pub fn cpu_time(&self) -> Duration {
+    Duration::from_nanos(self.cpu.load(Ordering::Relaxed))
+  }
+}
+
+impl<K> TimerSet<K> {
+  /// Times a parallel section, logging two windows when it's done: its wall
+  /// time under `name.wall`, and the CPU time of the work inside it, summed
+  /// across threads, under `name.cpu`. Only work run through the
+  /// `ParallelTimer`, or `timed_map`, counts towards the CPU time:
+  ///
+  /// ```
+  /// # extern crate rayon;
+  /// # extern crate stopwatch;
+  /// use rayon::prelude::*;
+  /// use stopwatch::{TimedParallelIterator, TimerSet};
+  ///
+  /// let ts = TimerSet::new();
+  /// let sum: u64 = ts.time_parallel("sum", |timer| (0..1000u64).into_par_iter().timed_map(timer, |x| x * x).sum());
+  /// assert_eq!(sum, 332_833_500);
+  /// ```
+  ///
+  /// The CPU time over the wall time is the speedup over running the work
+  /// on one thread, and that over `rayon::current_num_threads()` is how
+  /// efficiently the pool was used.
+  pub fn time_parallel<T, F: FnOnce(&ParallelTimer) -> T>(&self, name: &str, f: F) -> T {
+    let timer = ParallelTimer { cpu: AtomicU64::new(0) };
+    if !self.is_enabled() {
+      return f(&timer);
+    }
+    let then = Instant::now();
+    let ret = f(&timer);
+    let wall = then.elapsed();
+    self.record(&format!("{}.wall", name), wall);
+    self.record(&format!("{}.cpu", name), timer.cpu_time());
+    ret
+  }
+}
+
+/// Adds `timed_map` to rayon's parallel iterators.
+pub trait TimedParallelIterator: ParallelIterator {
+  /// Like `map`, but adds the CPU time spent in `f` to `timer`.
+  fn timed_map<'a, R: Send, F: Fn(Self::Item) -> R + Sync + Send + 'a>(self, timer: &'a ParallelTimer, f: F) -> impl ParallelIterator<Item = R> + 'a
+  where
+    Self: 'a,
+  {
+    self.map(move |item| timer.time(|| f(item)))
+  }
+}
+
+impl<I: ParallelIterator> TimedParallelIterator for I {}
+
+#[cfg(not(feature = "disable"))]
+#[test]
+fn test_time_parallel() {
+  use rayon::prelude::*;
+
+  let ts = TimerSet::new();
+  let spin = |ms| {
+    let then = Instant::now();
+    while then.elapsed() < Duration::from_millis(ms) {}
+  };
+  let n = ts.time_parallel("work", |timer| {
+    rayon::join(|| timer.time(|| spin(5)), || timer.time(|| spin(5)));
+    (0..4u64).into_par_iter().timed_map(timer, spin).count()
+  });
+  assert_eq!(n, 4);
+
+  let merged = ts.merged();
+  assert_eq!(merged["work.wall"].number_of_windows, 1);
+  // The spins can be preempted, so they needn't add up to 30ms of CPU time.
+  assert_eq!(merged["work.cpu"].number_of_windows, 1);
+  assert!(merged["work.cpu"].total_time > 0);
+}
@@ -0,0 +1,109 @@
+//! Saving timers to a file and loading them back, so stats can add up
+//! across runs of a program.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use serde_json;
+
+use {merge_timers, new_timers, TimerSet, TimerSetSnapshot};
+
+impl<K> TimerSet<K> {
+  /// Saves a snapshot of every timer to `path`, as JSON. The file is
+  /// replaced whole, so a program reading it never sees half of it.
+  /// Counters and gauges aren't saved.
+  pub fn save_to<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+    let path = path.as_ref();
+    let json = serde_json::to_vec(&self.snapshot()).map_err(io::Error::other)?;
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(format!(".{}.tmp", ::std::process::id()));
+    fs::write(&tmp, json)?;
+    fs::rename(&tmp, path).inspect_err(|_| {
+      let _ = fs::remove_file(&tmp);
+    })
+  }
+
+  /// Merges the timers saved to `path` by `save_to` into this set. Loading
+  /// at startup and saving on exit makes a program that's run many times,
+  /// e.g. by a build, accumulate its stats across runs. A missing file is
+  /// an error of kind `NotFound`, which the first run can ignore.
+  pub fn load_from<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+    let json = fs::read(path)?;
+    let snapshot: TimerSetSnapshot = serde_json::from_slice(&json).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    let mut timers = new_timers();
+    timers.extend(snapshot.timers.into_iter().map(|(name, sw)| (self.new_name(&name), sw)));
+    self.with_shard_timers(|shard| merge_timers(&mut shard.timers, &timers, self.config));
+    Ok(())
+  }
+}
+
+#[cfg(not(feature = "disable"))]
+#[test]
+fn test_save_and_load() {
+  use std::time::Duration;
+
+  let path = ::std::env::temp_dir().join(format!("stopwatch-persist-{}.json", ::std::process::id()));
+  let _ = fs::remove_file(&path);
+  for _ in 0..3 {
+    let ts = TimerSet::new();
+    if let Err(e) = ts.load_from(&path) {
+      assert_eq!(e.kind(), io::ErrorKind::NotFound);
+    }
+    ts.record("build", Duration::from_millis(2));
+    ts.save_to(&path).unwrap();
+  }
+
+  let ts = TimerSet::new();
+  ts.load_from(&path).unwrap();
+  let build = &ts.merged()["build"];
+  assert_eq!(build.number_of_windows, 3);
+  assert_eq!(build.total_time, 6_000_000);
+  let _ = fs::remove_file(&path);
+
+  fs::write(&path, "not json").unwrap();
+  assert_eq!(ts.load_from(&path).unwrap_err().kind(), io::ErrorKind::InvalidData);
+  let _ = fs::remove_file(&path);
+}
+
+#[cfg(not(feature = "disable"))]
+#[test]
+fn test_load_corrupt_histogram() {
+  use serde_json::Value;
+  use std::time::Duration;
+
+  // A histogram with windows in a bucket that doesn't exist, or a digest
+  // with a centroid of no windows, and one that's lost its windows.
+  #[cfg(not(feature = "tdigest"))]
+  let (bad, empty) = ("[[9999, 1]]", "[]");
+  #[cfg(feature = "tdigest")]
+  let (bad, empty) = (
+    r#"{"centroids": [{"mean": 1.0, "count": 0}], "buffer": [], "total": 1, "min": 1, "max": 1}"#,
+    r#"{"centroids": [], "buffer": [], "total": 3, "min": 0, "max": 0}"#,
+  );
+
+  let path = ::std::env::temp_dir().join(format!("stopwatch-corrupt-{}.json", ::std::process::id()));
+  let ts = TimerSet::new();
+  ts.record("build", Duration::from_millis(2));
+  ts.save_to(&path).unwrap();
+  let saved: Value = serde_json::from_slice(&fs::read(&path).unwrap()).unwrap();
+  let with_histogram = |histogram: &str| {
+    let mut json = saved.clone();
+    json["timers"]["build"]["histogram"] = serde_json::from_str(histogram).unwrap();
+    fs::write(&path, serde_json::to_vec(&json).unwrap()).unwrap();
+  };
+
+  with_histogram(bad);
+  let loaded = TimerSet::new();
+  assert_eq!(loaded.load_from(&path).unwrap_err().kind(), io::ErrorKind::InvalidData);
+  assert!(loaded.merged().is_empty());
+
+  with_histogram(empty);
+  loaded.load_from(&path).unwrap();
+  let lines = ::std::cell::RefCell::new(Vec::new());
+  loaded.report(&|line: &str| lines.borrow_mut().push(line.to_string()));
+  assert!(lines.borrow()[0].starts_with("build: "), "{:?}", lines);
+  assert!(!lines.borrow()[0].contains("p50"), "{:?}", lines);
+  assert_eq!(loaded.merged()["build"].quantile(0.5), None);
+  let _ = fs::remove_file(&path);
+}
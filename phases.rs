@@ -0,0 +1,41 @@
+//! Breaking a program's startup, or any run of steps, into phases.
+
+use std::time::Duration;
+
+use {lock, Instant, TimerSet};
+
+impl<K> TimerSet<K> {
+  /// Ends a phase, logging the time since the last phase ended, or since
+  /// the set was created for the first, as a window of the named timer.
+  /// Marking each step as it finishes, e.g. `mark_phase("config")` then
+  /// `mark_phase("connect")`, breaks the run down without wrapping each
+  /// step in a closure. Returns how long the phase took.
+  pub fn mark_phase(&self, name: &str) -> Duration {
+    let now = Instant::now();
+    let then = lock(&self.last_phase).replace(now).unwrap_or(self.epoch);
+    let dt = now.saturating_duration_since(then);
+    self.record(name, dt);
+    dt
+  }
+
+  /// Returns how long ago the set was created. For a set made at startup,
+  /// that's how long the program has been running.
+  pub fn uptime(&self) -> Duration {
+    Instant::now().saturating_duration_since(self.epoch)
+  }
+}
+
+#[cfg(not(feature = "disable"))]
+#[test]
+fn test_mark_phase() {
+  let ts = TimerSet::new();
+  ::std::thread::sleep(Duration::from_millis(2));
+  let config = ts.mark_phase("config");
+  let connect = ts.mark_phase("connect");
+  assert!(config >= Duration::from_millis(2));
+  assert!(ts.uptime() >= config + connect);
+
+  let timers = ts.merged();
+  assert_eq!(timers["config"].number_of_windows, 1);
+  assert_eq!(timers["connect"].total_time as u128, connect.as_nanos());
+}
@@ -0,0 +1,78 @@
+//! Timing subprocesses.
+
+use std::io;
+use std::process::{Command, ExitStatus, Output};
+#[cfg(all(unix, feature = "cputime"))]
+use std::time::Duration;
+
+#[cfg(all(unix, feature = "cputime"))]
+use libc;
+
+use {Instant, TimerSet};
+
+/// The CPU time, user and system, used by every child process that's been
+/// waited for so far, in nanoseconds.
+#[cfg(all(unix, feature = "cputime"))]
+fn children_cpu_time() -> u64 {
+  let mut usage: libc::rusage = unsafe { ::std::mem::zeroed() };
+  // This can only fail for an invalid `who`.
+  let ret = unsafe { libc::getrusage(libc::RUSAGE_CHILDREN, &mut usage) };
+  debug_assert_eq!(ret, 0);
+  let nanos = |t: libc::timeval| (t.tv_sec as u64).saturating_mul(1_000_000_000).saturating_add(t.tv_usec as u64 * 1_000);
+  nanos(usage.ru_utime) + nanos(usage.ru_stime)
+}
+
+/// Runs `run`, which spawns and waits for a subprocess, logging its wall
+/// time under `name`, and its CPU time under `name.cpu` where that's known.
+fn time_child<T, F: FnOnce() -> io::Result<T>>(timers: &TimerSet, name: &str, run: F) -> io::Result<T> {
+  #[cfg(all(unix, feature = "cputime"))]
+  let cpu = children_cpu_time();
+  let then = Instant::now();
+  let ret = run()?;
+  timers.record(name, then.elapsed());
+  #[cfg(all(unix, feature = "cputime"))]
+  timers.record(&format!("{}.cpu", name), Duration::from_nanos(children_cpu_time().saturating_sub(cpu)));
+  Ok(ret)
+}
+
+/// Adds methods to `Command` for timing the subprocesses it runs.
+///
+/// The wall time, from spawning the process until it exits, is logged under
+/// the given name. On unix, with the `cputime` feature, the CPU time it
+/// used, user and system, is logged under `name.cpu` too. That's read from
+/// the time used by every child that's exited, so it also counts any others
+/// that were waited for meanwhile, on any thread.
+pub trait TimedCommand {
+  /// Like `Command::status`, timing the process under `name` in `timers`.
+  fn timed_status(&mut self, name: &str, timers: &TimerSet) -> io::Result<ExitStatus>;
+
+  /// Like `Command::output`, timing the process under `name` in `timers`.
+  fn timed_output(&mut self, name: &str, timers: &TimerSet) -> io::Result<Output>;
+}
+
+impl TimedCommand for Command {
+  fn timed_status(&mut self, name: &str, timers: &TimerSet) -> io::Result<ExitStatus> {
+    time_child(timers, name, || self.status())
+  }
+
+  fn timed_output(&mut self, name: &str, timers: &TimerSet) -> io::Result<Output> {
+    time_child(timers, name, || self.output())
+  }
+}
+
+#[cfg(unix)]
+#[cfg(not(feature = "disable"))]
+#[test]
+fn test_timed_command() {
+  let ts = TimerSet::new();
+  assert!(Command::new("sh").args(["-c", "exit 0"]).timed_status("sh", &ts).unwrap().success());
+  let output = Command::new("echo").arg("hi").timed_output("echo", &ts).unwrap();
+  assert_eq!(output.stdout, b"hi\n");
+  assert!(Command::new("/nonexistent").timed_status("missing", &ts).is_err());
+
+  let timers = ts.merged();
+  assert_eq!(timers["sh"].number_of_windows, 1);
+  assert_eq!(timers["echo"].number_of_windows, 1);
+  assert!(!timers.contains_key("missing"));
+  assert_eq!(timers.contains_key("sh.cpu"), cfg!(feature = "cputime"));
+}
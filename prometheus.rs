@@ -0,0 +1,147 @@
+//! Prometheus text exposition format export of timer statistics.
+
+use std::fmt::Write;
+
+use {tags, to_nanos, TimerSet};
+
+const QUANTILES: [f64; 3] = [0.5, 0.9, 0.99];
+
+/// Makes `name` a valid Prometheus metric name, by replacing any invalid
+/// characters with underscores.
+fn sanitize_metric_name(name: &str) -> String {
+  name
+    .chars()
+    .enumerate()
+    .map(|(i, c)| {
+      let valid = c.is_ascii_alphabetic() || c == '_' || c == ':' || (i > 0 && c.is_ascii_digit());
+      if valid { c } else { '_' }
+    })
+    .collect()
+}
+
+/// The labels the exporter gives timers itself.
+const RESERVED_LABELS: [&str; 2] = ["timer", "quantile"];
+
+/// Makes a tag's key a valid Prometheus label name, by replacing any invalid
+/// characters with underscores. Keys that would clash with a label the
+/// exporter adds, or that start with `__`, which Prometheus keeps for
+/// itself, get a `tag_` prefix.
+fn sanitize_label_name(name: &str) -> String {
+  let name: String =
+    name
+      .chars()
+      .enumerate()
+      .map(|(i, c)| {
+        let valid = c.is_ascii_alphabetic() || c == '_' || (i > 0 && c.is_ascii_digit());
+        if valid { c } else { '_' }
+      })
+      .collect();
+  if RESERVED_LABELS.contains(&name.as_str()) || name.starts_with("__") {
+    format!("tag_{}", name)
+  } else {
+    name
+  }
+}
+
+/// Escapes `value` for use inside a quoted Prometheus label value.
+fn escape_label_value(value: &str) -> String {
+  value
+    .replace('\\', "\\\\")
+    .replace('"', "\\\"")
+    .replace('\n', "\\n")
+}
+
+impl<K> TimerSet<K> {
+  /// Renders every timer as a Prometheus summary, in the text exposition
+  /// format. Timers are distinguished by a `timer` label, plus a label per
+  /// tag, and the metric is named `<prefix>_timer_seconds`, or
+  /// `timer_seconds` if `prefix` is empty.
+  /// Counters follow, as `<prefix>_counter_total` with a `counter` label,
+  /// and then gauges' latest values, as `<prefix>_gauge` with a `gauge` label.
+  pub fn to_prometheus(&self, prefix: &str) -> String {
+    let metric_name = |name: &str| {
+      if prefix.is_empty() {
+        name.to_string()
+      } else {
+        format!("{}_{}", sanitize_metric_name(prefix), name)
+      }
+    };
+    let metric = metric_name("timer_seconds");
+    let seconds = |ns: u64| ns as f64 / 1e9;
+
+    let mut out = String::new();
+    writeln!(out, "# HELP {} Time spent in named timers.", metric).unwrap();
+    writeln!(out, "# TYPE {} summary", metric).unwrap();
+    for (name, sw) in self.sorted_timers().iter() {
+      let (name, tags) = tags::split_tags(name);
+      let mut labels = format!("timer=\"{}\"", escape_label_value(name));
+      for (k, v) in tags {
+        write!(labels, ",{}=\"{}\"", sanitize_label_name(k), escape_label_value(v)).unwrap();
+      }
+      for &q in QUANTILES.iter() {
+        if let Some(t) = sw.quantile(q) {
+          writeln!(out, "{}{{{},quantile=\"{}\"}} {}", metric, labels, q, seconds(to_nanos(t))).unwrap();
+        }
+      }
+      writeln!(out, "{}_sum{{{}}} {}", metric, labels, seconds(sw.total_time)).unwrap();
+      writeln!(out, "{}_count{{{}}} {}", metric, labels, sw.number_of_windows).unwrap();
+    }
+
+    let counters = self.counters();
+    if !counters.is_empty() {
+      let metric = metric_name("counter_total");
+      writeln!(out, "# HELP {} Events counted by named counters.", metric).unwrap();
+      writeln!(out, "# TYPE {} counter", metric).unwrap();
+      for (name, n) in counters.iter() {
+        writeln!(out, "{}{{counter=\"{}\"}} {}", metric, escape_label_value(name), n).unwrap();
+      }
+    }
+
+    let gauges = self.gauges();
+    if !gauges.is_empty() {
+      let metric = metric_name("gauge");
+      writeln!(out, "# HELP {} The latest values of named gauges.", metric).unwrap();
+      writeln!(out, "# TYPE {} gauge", metric).unwrap();
+      for (name, gauge) in gauges.iter() {
+        writeln!(out, "{}{{gauge=\"{}\"}} {}", metric, escape_label_value(name), gauge.last).unwrap();
+      }
+    }
+    out
+  }
+}
+
+#[test]
+fn test_sanitize() {
+  assert_eq!(sanitize_metric_name("my-app.v2"), "my_app_v2");
+  assert_eq!(sanitize_metric_name("2fast"), "_fast");
+  assert_eq!(escape_label_value("a\"b\\c\nd"), "a\\\"b\\\\c\\nd");
+  assert_eq!(sanitize_metric_name("db:query"), "db:query");
+  assert_eq!(sanitize_label_name("db:table"), "db_table");
+  assert_eq!(sanitize_label_name("2nd"), "_nd");
+  assert_eq!(sanitize_label_name("timer"), "tag_timer");
+  assert_eq!(sanitize_label_name("quantile"), "tag_quantile");
+  assert_eq!(sanitize_label_name("__name__"), "tag___name__");
+}
+
+#[cfg(not(feature = "disable"))]
+#[test]
+fn test_to_prometheus() {
+  let ts = TimerSet::new();
+  ts.time("hello", || {});
+  ts.time_with_tags("query", &[("table", "users")], || {});
+  ts.count("hits", 3);
+  ts.gauge("depth", 2.5);
+  let out = ts.to_prometheus("app");
+  assert!(out.contains("# TYPE app_timer_seconds summary\n"));
+  assert!(out.contains("app_timer_seconds{timer=\"hello\",quantile=\"0.99\"} "));
+  assert!(out.contains("app_timer_seconds_count{timer=\"hello\"} 1\n"));
+  assert!(out.contains("app_timer_seconds_count{timer=\"query\",table=\"users\"} 1\n"));
+  assert!(out.contains("# TYPE app_counter_total counter\napp_counter_total{counter=\"hits\"} 3\n"));
+  assert!(out.contains("# TYPE app_gauge gauge\napp_gauge{gauge=\"depth\"} 2.5\n"));
+
+  // Tags can't pass for the exporter's own labels, or use `:`.
+  let ts = TimerSet::new();
+  ts.time_with_tags("query", &[("timer", "a"), ("quantile", "b"), ("db:table", "users")], || {});
+  let out = ts.to_prometheus("");
+  assert!(out.contains("timer_seconds_count{timer=\"query\",db_table=\"users\",tag_quantile=\"b\",tag_timer=\"a\"} 1\n"), "{}", out);
+}
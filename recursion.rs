@@ -0,0 +1,53 @@
+//! Timing recursive functions once, rather than once per level.
+
+use TimerSet;
+
+impl<K> TimerSet<K> {
+  /// Sets whether calls timed under a timer that's already being timed on
+  /// the same thread, e.g. by a recursive function, are windows of their
+  /// own. By default they are. When they aren't, only the outermost call is
+  /// timed, so the timer reports the time spent in it, not that time again
+  /// for every level of recursion.
+  pub fn set_collapse_recursion(&mut self, collapse: bool) {
+    self.collapse_recursion = collapse;
+  }
+
+  /// Whether a call timed under the named timer is nested in a window of
+  /// its own, and so shouldn't be timed.
+  #[inline]
+  pub(crate) fn is_recursive(&self, name: &str) -> bool {
+    self.collapse_recursion && self.is_open(name)
+  }
+}
+
+#[cfg(not(feature = "disable"))]
+#[test]
+fn test_collapse_recursion() {
+  fn fib(ts: &TimerSet, n: u64) -> u64 {
+    ts.time("fib", || if n < 2 { n } else { fib(ts, n - 1) + fib(ts, n - 2) })
+  }
+
+  let mut ts = TimerSet::new();
+  ts.set_collapse_recursion(true);
+  assert_eq!(fib(&ts, 10), 55);
+  ts.time_result("fib", || -> Result<(), ()> { ts.time_result("fib", || Ok(())) }).unwrap();
+  {
+    let _guard = ts.start("fib");
+    let _inner = ts.start("fib");
+  }
+  let timers = ts.merged();
+  assert_eq!(timers["fib"].number_of_windows, 3);
+  assert_eq!(timers["fib"].child_time, 0);
+  assert_eq!(timers["fib.ok"].number_of_windows, 1);
+
+  // Windows open on other threads aren't nested.
+  ::std::thread::scope(|s| {
+    s.spawn(|| fib(&ts, 3));
+  });
+  assert_eq!(ts.merged()["fib"].number_of_windows, 4);
+
+  ts.set_collapse_recursion(false);
+  ts.reset_all();
+  fib(&ts, 10);
+  assert_eq!(ts.merged()["fib"].number_of_windows, 177);
+}
@@ -0,0 +1,77 @@
+//! Every thread's thread-local `TimerSet`, for reports across threads.
+//!
+//! Each thread's set is registered the first time the thread uses it, e.g.
+//! through `stopwatch::time`, and is kept after the thread exits, so pools
+//! that come and go can still be reported on.
+
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use {lock, LogReporter, Report, Reporter, TimerSet};
+
+/// Every thread-local set, and the name of the thread it belongs to.
+static THREADS: Mutex<Vec<(String, Arc<TimerSet>)>> = Mutex::new(Vec::new());
+
+/// Creates the current thread's set and registers it, under the thread's
+/// name, or `thread-<n>` if it doesn't have one, where `n` counts threads
+/// in the order they registered.
+pub(crate) fn register() -> Arc<TimerSet> {
+  let timers = Arc::new(TimerSet::new());
+  let mut threads = lock(&THREADS);
+  let name =
+    match thread::current().name() {
+      Some(name) => name.to_string(),
+      None => format!("thread-{}", threads.len()),
+    };
+  threads.push((name, timers.clone()));
+  timers
+}
+
+/// Returns every thread's thread-local set, named after its thread, in the
+/// order the threads first used them.
+pub fn threads() -> Vec<(String, Arc<TimerSet>)> {
+  lock(&THREADS).clone()
+}
+
+/// Merges every thread's thread-local set into a new one.
+pub fn merge_threads() -> TimerSet {
+  let merged = TimerSet::new();
+  for (_, timers) in threads() {
+    merged.merge(&timers);
+  }
+  merged
+}
+
+/// Sends each thread's report to `reporter`, a thread at a time, with each
+/// line prefixed by the thread's name, e.g. `[worker-3] decode: ...`.
+pub fn report_threads(reporter: &dyn Reporter) {
+  report_threads_with(reporter, &Report::new());
+}
+
+/// Like `report_threads`, with each thread's report configured by `report`.
+pub fn report_threads_with(reporter: &dyn Reporter, report: &Report) {
+  for (name, timers) in threads() {
+    timers.report_with(&|line: &str| reporter.line(&format!("[{}] {}", name, line)), report);
+  }
+}
+
+/// Logs each thread's report at `info` level, like `report_threads`.
+pub fn print_threads() {
+  report_threads(&LogReporter::default());
+}
+
+#[cfg(not(feature = "disable"))]
+#[test]
+fn test_report_threads() {
+  use std::cell::RefCell;
+
+  thread::Builder::new().name("worker-3".to_string()).spawn(|| ::time("decode", || {})).unwrap().join().unwrap();
+  ::std::thread::spawn(|| ::time("decode", || {})).join().unwrap();
+
+  let lines = RefCell::new(Vec::new());
+  report_threads(&|line: &str| lines.borrow_mut().push(line.to_string()));
+  let lines = lines.into_inner();
+  assert!(lines.iter().any(|line| line.starts_with("[worker-3] decode: ")), "{:?}", lines);
+  assert!(lines.iter().any(|line| line.starts_with("[thread-") && line.contains("] decode: ")), "{:?}", lines);
+  assert!(merge_threads().merged()["decode"].number_of_windows >= 2);
+}
@@ -0,0 +1,453 @@
+//! Destinations for `TimerSet::report`'s output.
+
+use log::Level;
+#[cfg(feature = "regex")]
+use regex::Regex;
+use std::cmp::Ordering;
+use std::io::{self, Write};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use {lock, Stopwatch, TimerSet};
+
+#[cfg(all(test, not(feature = "disable")))]
+use std::cell::RefCell;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+/// A statistic to sort reported timers by.
+pub enum SortBy {
+  /// The timer's name.
+  Name,
+  /// The total time spent in the timer.
+  TotalTime,
+  /// The time spent in the timer itself, not in the timers nested inside
+  /// it.
+  SelfTime,
+  /// The mean time per window.
+  Average,
+  /// The number of windows timed.
+  Count,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+/// The order `TimerSet::report_sorted` lists timers in. Ties are broken by
+/// name.
+pub struct SortOrder {
+  /// What to sort by.
+  pub by: SortBy,
+  /// Whether to put the largest values first.
+  pub descending: bool,
+}
+
+impl SortOrder {
+  /// Sorts by `by`, smallest first.
+  pub fn ascending(by: SortBy) -> SortOrder {
+    SortOrder {
+      by,
+      descending: false,
+    }
+  }
+
+  /// Sorts by `by`, largest first.
+  pub fn descending(by: SortBy) -> SortOrder {
+    SortOrder {
+      by,
+      descending: true,
+    }
+  }
+
+  /// Compares two named timers. A missing timer counts as one that never
+  /// ran.
+  pub(crate) fn compare(&self, a: (&str, Option<&Stopwatch>), b: (&str, Option<&Stopwatch>)) -> Ordering {
+    let stat = |sw: Option<&Stopwatch>| {
+      match sw {
+        None => 0,
+        Some(sw) => {
+          match self.by {
+            SortBy::Name => 0,
+            SortBy::TotalTime => sw.total_time,
+            SortBy::SelfTime => sw.total_time.saturating_sub(sw.child_time),
+            SortBy::Average => sw.total_time.checked_div(sw.number_of_windows).unwrap_or(0),
+            SortBy::Count => sw.number_of_windows,
+          }
+        },
+      }
+    };
+    let ord = stat(a.1).cmp(&stat(b.1)).then_with(|| a.0.cmp(b.0));
+    if self.descending { ord.reverse() } else { ord }
+  }
+}
+
+impl Default for SortOrder {
+  /// Alphabetical order.
+  fn default() -> SortOrder {
+    SortOrder::ascending(SortBy::Name)
+  }
+}
+
+#[derive(Debug, Clone, Default)]
+/// Which timers `TimerSet::report_with` reports, and in what order, e.g.
+/// `Report::top(20).filter_prefix("render.")`. By default, every timer is
+/// reported in alphabetical order.
+pub struct Report {
+  pub(crate) order: SortOrder,
+  /// Only report this many timers, those with the most total time.
+  pub(crate) top: Option<usize>,
+  prefix: Option<String>,
+  #[cfg(feature = "regex")]
+  regex: Option<Regex>,
+}
+
+impl Report {
+  /// Reports every timer in alphabetical order.
+  pub fn new() -> Report {
+    Report::default()
+  }
+
+  /// Reports only the `n` timers with the most total time, largest first.
+  pub fn top(n: usize) -> Report {
+    Report {
+      order: SortOrder::descending(SortBy::TotalTime),
+      top: Some(n),
+      ..Report::default()
+    }
+  }
+
+  /// Lists the reported timers in `order`.
+  pub fn sorted(mut self, order: SortOrder) -> Report {
+    self.order = order;
+    self
+  }
+
+  /// Only reports timers, counters and gauges whose names start with
+  /// `prefix`.
+  pub fn filter_prefix<S: Into<String>>(mut self, prefix: S) -> Report {
+    self.prefix = Some(prefix.into());
+    self
+  }
+
+  #[cfg(feature = "regex")]
+  /// Only reports timers, counters and gauges whose names match `regex`
+  /// somewhere.
+  pub fn filter_regex(mut self, regex: Regex) -> Report {
+    self.regex = Some(regex);
+    self
+  }
+
+  /// Whether to report whatever is named `name`, leaving aside `top`.
+  pub(crate) fn matches(&self, name: &str) -> bool {
+    if let Some(ref prefix) = self.prefix {
+      if !name.starts_with(prefix.as_str()) {
+        return false;
+      }
+    }
+    #[cfg(feature = "regex")]
+    {
+      if let Some(ref regex) = self.regex {
+        if !regex.is_match(name) {
+          return false;
+        }
+      }
+    }
+    true
+  }
+}
+
+/// Somewhere to send a timer report, one line at a time.
+pub trait Reporter {
+  /// Emits a single line of the report, without a trailing newline.
+  fn line(&self, line: &str);
+}
+
+/// Any `Fn(&str)` closure can be a reporter, and is called with each line.
+impl<F: Fn(&str)> Reporter for F {
+  fn line(&self, line: &str) {
+    self(line)
+  }
+}
+
+/// Reports through the `log` crate.
+pub struct LogReporter {
+  level: Level,
+}
+
+impl LogReporter {
+  /// Creates a reporter that logs each line at `level`.
+  pub fn new(level: Level) -> LogReporter {
+    LogReporter {
+      level,
+    }
+  }
+}
+
+impl Default for LogReporter {
+  /// Logs at `Info`, like `TimerSet::print`.
+  fn default() -> LogReporter {
+    LogReporter::new(Level::Info)
+  }
+}
+
+impl Reporter for LogReporter {
+  fn line(&self, line: &str) {
+    log!(self.level, "{}", line);
+  }
+}
+
+/// Reports to a `std::io::Write`, such as a file. Write errors are ignored.
+pub struct WriteReporter<W: Write> {
+  w: Mutex<W>,
+}
+
+impl<W: Write> WriteReporter<W> {
+  /// Creates a reporter that writes each line to `w`.
+  pub fn new(w: W) -> WriteReporter<W> {
+    WriteReporter {
+      w: Mutex::new(w),
+    }
+  }
+
+  /// Returns the underlying writer.
+  pub fn into_inner(self) -> W {
+    self.w.into_inner().unwrap_or_else(PoisonError::into_inner)
+  }
+}
+
+impl WriteReporter<io::Stdout> {
+  /// Creates a reporter that writes to stdout.
+  pub fn stdout() -> WriteReporter<io::Stdout> {
+    WriteReporter::new(io::stdout())
+  }
+}
+
+impl WriteReporter<io::Stderr> {
+  /// Creates a reporter that writes to stderr.
+  pub fn stderr() -> WriteReporter<io::Stderr> {
+    WriteReporter::new(io::stderr())
+  }
+}
+
+impl<W: Write> Reporter for WriteReporter<W> {
+  fn line(&self, line: &str) {
+    let mut w = lock(&self.w);
+    let _ = writeln!(w, "{}", line);
+  }
+}
+
+/// A background thread started by `TimerSet::spawn_reporter`. Dropping this
+/// stops the thread.
+pub struct ReporterHandle {
+  stop: Option<Sender<()>>,
+  thread: Option<JoinHandle<()>>,
+}
+
+impl ReporterHandle {
+  /// Starts a thread that calls `report` every `interval` until it returns
+  /// `false` or the handle is dropped.
+  pub(crate) fn spawn<F: FnMut() -> bool + Send + 'static>(interval: Duration, mut report: F) -> ReporterHandle {
+    let (stop, stopped) = mpsc::channel::<()>();
+    let thread = thread::spawn(move || {
+      while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(interval) {
+        if !report() {
+          return;
+        }
+      }
+    });
+    ReporterHandle {
+      stop: Some(stop),
+      thread: Some(thread),
+    }
+  }
+
+  /// Stops the thread, waiting for any report in progress to finish.
+  pub fn stop(self) {}
+}
+
+impl Drop for ReporterHandle {
+  fn drop(&mut self) {
+    // Hanging up wakes the thread, which then exits.
+    drop(self.stop.take());
+    if let Some(thread) = self.thread.take() {
+      let _ = thread.join();
+    }
+  }
+}
+
+impl<K: 'static> TimerSet<K> {
+  /// Starts a thread that, every `interval`, takes a snapshot of this set,
+  /// resetting it, and sends a report of the snapshot to `reporter`. Timers
+  /// that didn't run during an interval are left out, and counters report
+  /// what was counted during the interval.
+  pub fn spawn_reporter<R: Reporter + Send + 'static>(self: &Arc<Self>, interval: Duration, reporter: R) -> ReporterHandle {
+    let timers = Arc::downgrade(self);
+    ReporterHandle::spawn(interval, move || {
+      match timers.upgrade() {
+        None => false,
+        Some(timers) => {
+          timers.report_interval(&reporter);
+          true
+        },
+      }
+    })
+  }
+
+  /// Takes a snapshot of this set, resetting it, and sends a report of the
+  /// snapshot to `reporter`, leaving out timers that didn't run since the
+  /// last one.
+  pub(crate) fn report_interval(&self, reporter: &dyn Reporter) {
+    self.take_interval().report(reporter);
+  }
+
+  /// Takes a snapshot of this set, resetting it, as a set of the timers that
+  /// ran, and what was counted, since the last one.
+  pub(crate) fn take_interval(&self) -> TimerSet<K> {
+    let snapshot =
+      self.take_snapshot()
+        .into_iter()
+        .filter(|(_, sw)| sw.number_of_windows > 0)
+        .map(|(name, sw)| (self.new_name(&name), sw))
+        .collect();
+    self.with_timers(snapshot, self.take_counters())
+  }
+}
+
+#[cfg(not(feature = "disable"))]
+#[test]
+fn test_report() {
+  let ts = TimerSet::new();
+  ts.time("b", || {});
+  ts.time("a", || {});
+
+  let lines = RefCell::new(Vec::new());
+  ts.report(&|line: &str| lines.borrow_mut().push(line.to_string()));
+  let lines = lines.into_inner();
+  assert_eq!(lines.len(), 2);
+  assert!(lines[0].starts_with("a: "), "{}", lines[0]);
+  assert!(lines[1].starts_with("b: "), "{}", lines[1]);
+
+  let w = WriteReporter::new(Vec::new());
+  ts.report(&w);
+  let out = String::from_utf8(w.into_inner()).unwrap();
+  assert_eq!(out.lines().collect::<Vec<_>>(), lines);
+}
+
+#[cfg(not(feature = "disable"))]
+#[test]
+fn test_report_sorted() {
+  let ts = TimerSet::new();
+  ts.time("a", || ::std::thread::sleep(::std::time::Duration::from_millis(2)));
+  for _ in 0..3 {
+    ts.time("b", || {});
+  }
+  ts.time("c", || {});
+
+  let names = |order| {
+    let names = RefCell::new(Vec::new());
+    ts.report_sorted(&|line: &str| names.borrow_mut().push(line.split(':').next().unwrap().to_string()), order);
+    names.into_inner()
+  };
+  assert_eq!(names(SortOrder::default()), vec!["a", "b", "c"]);
+  assert_eq!(names(SortOrder::descending(SortBy::Name)), vec!["c", "b", "a"]);
+  assert_eq!(names(SortOrder::descending(SortBy::Count)), vec!["b", "c", "a"]);
+  assert_eq!(names(SortOrder::descending(SortBy::TotalTime))[0], "a");
+  assert_eq!(names(SortOrder::ascending(SortBy::Average))[2], "a");
+}
+
+#[cfg(not(feature = "disable"))]
+#[test]
+fn test_report_with() {
+  let ts = TimerSet::new();
+  ts.record("render.draw", Duration::from_millis(3));
+  ts.record("render.upload", Duration::from_millis(1));
+  ts.record("render.cull", Duration::from_millis(2));
+  ts.record("update", Duration::from_millis(5));
+  ts.count("render.frames", 1);
+  ts.count("updates", 1);
+
+  let names = |report: Report| {
+    let names = RefCell::new(Vec::new());
+    ts.report_with(&|line: &str| names.borrow_mut().push(line.split(':').next().unwrap().to_string()), &report);
+    names.into_inner()
+  };
+  assert_eq!(names(Report::top(2)), vec!["update", "render.draw", "render.frames", "updates"]);
+  assert_eq!(
+    names(Report::top(2).filter_prefix("render.")),
+    vec!["render.draw", "render.cull", "render.frames"]
+  );
+  assert_eq!(
+    names(Report::new().filter_prefix("render.").sorted(SortOrder::descending(SortBy::Name))),
+    vec!["render.upload", "render.draw", "render.cull", "render.frames"]
+  );
+}
+
+#[cfg(feature = "regex")]
+#[cfg(not(feature = "disable"))]
+#[test]
+fn test_report_filter_regex() {
+  let ts = TimerSet::new();
+  ts.time("db.read", || {});
+  ts.time("db.write", || {});
+  ts.time("cache.read", || {});
+  let names = RefCell::new(Vec::new());
+  let report = Report::new().filter_regex(Regex::new(r"\.read$").unwrap());
+  ts.report_with(&|line: &str| names.borrow_mut().push(line.split(':').next().unwrap().to_string()), &report);
+  assert_eq!(names.into_inner(), vec!["cache.read", "db.read"]);
+}
+
+#[cfg(not(feature = "disable"))]
+#[test]
+fn test_report_shares() {
+  let mut ts = TimerSet::new();
+  ts.set_root_timer(Some("main"));
+  ts.time("main", || {
+    ts.time("a", || ::std::thread::sleep(::std::time::Duration::from_millis(1)));
+  });
+
+  let lines = RefCell::new(Vec::new());
+  ts.report(&|line: &str| lines.borrow_mut().push(line.to_string()));
+  let lines = lines.into_inner();
+  assert!(lines[0].starts_with("a: ") && lines[0].contains("% of total, "), "{}", lines[0]);
+  assert!(lines[0].contains("% of main"), "{}", lines[0]);
+  assert!(lines[1].ends_with("% of total, 100.0% of main"), "{}", lines[1]);
+}
+
+#[cfg(not(feature = "disable"))]
+#[test]
+fn test_spawn_reporter() {
+  let timers = Arc::new(TimerSet::new());
+  let (lines, received) = mpsc::channel();
+  let handle = timers.spawn_reporter(Duration::from_millis(1), move |line: &str| {
+    lines.send(line.to_string()).unwrap();
+  });
+  timers.time("a", || {});
+  let line = received.recv_timeout(Duration::from_secs(10)).unwrap();
+  assert!(line.starts_with("a: "), "{}", line);
+  handle.stop();
+  assert_eq!(timers.merged()["a"].number_of_windows, 0);
+}
+
+#[test]
+fn test_write_reporter_poisoned() {
+  /// Writes to a `Vec`, panicking the first time it's written to.
+  struct Panicky(Vec<u8>, bool);
+
+  impl Write for Panicky {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+      if !self.1 {
+        self.1 = true;
+        panic!("write failed");
+      }
+      self.0.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+      Ok(())
+    }
+  }
+
+  let w = WriteReporter::new(Panicky(Vec::new(), false));
+  assert!(::std::panic::catch_unwind(::std::panic::AssertUnwindSafe(|| w.line("lost"))).is_err());
+  w.line("kept");
+  assert_eq!(w.into_inner().0, b"kept\n");
+}
@@ -0,0 +1,100 @@
+//! Timing the tasks and worker threads of a Tokio runtime.
+
+use std::borrow::Cow;
+use std::cell::Cell;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::runtime::Builder;
+use tokio::task::JoinHandle;
+
+use future::{Target, TimedFuture};
+use {to_nanos, Instant, Reporter, TimerSet};
+
+/// The name worker threads' busy time is logged under.
+pub const BUSY_TIMER: &str = "tokio.busy";
+
+thread_local!(static UNPARKED: Cell<Option<Instant>> = const { Cell::new(None) });
+
+impl TimerSet {
+  /// Hooks into the worker threads of the runtime being built, logging a
+  /// window under `tokio.busy` each time one runs between being woken and
+  /// parking again. Its total against the runtime's uptime times the number
+  /// of workers is how busy they are.
+  pub fn instrument_runtime<'a>(self: &Arc<Self>, builder: &'a mut Builder) -> &'a mut Builder {
+    let timers = self.clone();
+    builder
+      .on_thread_start(|| UNPARKED.with(|unparked| unparked.set(Some(Instant::now()))))
+      .on_thread_unpark(|| UNPARKED.with(|unparked| unparked.set(Some(Instant::now()))))
+      .on_thread_park(move || {
+        if let Some(then) = UNPARKED.with(|unparked| unparked.take()) {
+          timers.add_window(BUSY_TIMER, to_nanos(then.elapsed()));
+        }
+      })
+  }
+
+  /// Spawns a task onto the current Tokio runtime, timing it under `name`
+  /// like `time_async` does: its wall time from its first poll under
+  /// `name`, and the time spent polling it under `name.poll`. Tasks spawned
+  /// under the same name add up, so `print` shows where the runtime spends
+  /// its time.
+  pub fn spawn_timed<F>(self: &Arc<Self>, name: &str, future: F) -> JoinHandle<F::Output>
+  where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+  {
+    let name = Cow::Owned(self.scoped(name).into_owned());
+    tokio::spawn(TimedFuture::new(Target::Shared(self.clone()), name, future))
+  }
+
+  /// Spawns a task onto the current Tokio runtime that reports on this set
+  /// every `interval`, like `spawn_reporter`'s thread does. The runtime needs
+  /// its timer enabled. Abort the task to stop it; it also finishes once the
+  /// set is dropped.
+  pub fn spawn_async_reporter<R: Reporter + Send + 'static>(self: &Arc<Self>, interval: Duration, reporter: R) -> JoinHandle<()> {
+    tokio::spawn(self.async_reporter(interval, reporter, tokio::time::sleep))
+  }
+}
+
+#[cfg(not(feature = "disable"))]
+#[test]
+fn test_spawn_timed() {
+  let ts = Arc::new(TimerSet::new());
+  let runtime = ts.instrument_runtime(&mut Builder::new_multi_thread()).worker_threads(2).build().unwrap();
+  let tasks: Vec<_> = {
+    let _guard = runtime.enter();
+    (0..3).map(|_| ts.spawn_timed("task", tokio::task::yield_now())).collect()
+  };
+  for task in tasks {
+    runtime.block_on(task).unwrap();
+  }
+  drop(runtime);
+
+  let timers = ts.merged();
+  assert_eq!(timers["task"].number_of_windows, 3);
+  assert_eq!(timers["task.poll"].number_of_windows, 3);
+  assert!(timers[BUSY_TIMER].number_of_windows > 0);
+}
+
+#[cfg(not(feature = "disable"))]
+#[test]
+fn test_spawn_async_reporter() {
+  use std::sync::Mutex;
+
+  let ts = Arc::new(TimerSet::new());
+  let runtime = Builder::new_multi_thread().worker_threads(1).enable_time().build().unwrap();
+  let lines = Arc::new(Mutex::new(Vec::new()));
+  let (reporter, sleep) = {
+    let _guard = runtime.enter();
+    let lines = lines.clone();
+    let reporter = ts.spawn_async_reporter(Duration::from_millis(10), move |line: &str| lines.lock().unwrap().push(line.to_string()));
+    (reporter, tokio::time::sleep(Duration::from_millis(50)))
+  };
+  ts.time("a", || {});
+  runtime.block_on(sleep);
+  assert!(lines.lock().unwrap().iter().any(|line| line.starts_with("a: ")), "{:?}", lines);
+
+  drop(ts);
+  runtime.block_on(reporter).unwrap();
+}
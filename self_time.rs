@@ -0,0 +1,166 @@
+//! Telling the time spent in a timer itself from the time spent in the
+//! timers nested inside it.
+
+use std::cell::RefCell;
+use std::ops::Range;
+
+use {Start, TimerSet};
+
+/// A window open on this thread.
+struct Frame {
+  /// The id of the set it's for.
+  set: usize,
+  /// Where the name of the timer it's for is in `Open::names`, if its set
+  /// collapses recursion and it isn't for a registered timer.
+  name: Option<Range<usize>>,
+  /// How long the windows nested in it have taken so far, in nanoseconds.
+  nested: u64,
+}
+
+/// The windows open on a thread, innermost last, and their timers' names,
+/// one after another, so opening a window doesn't allocate.
+struct Open {
+  frames: Vec<Frame>,
+  names: String,
+}
+
+impl Open {
+  /// Closes every window from `depth` in.
+  fn truncate(&mut self, depth: usize) {
+    if let Some(name) = self.frames.iter().skip(depth).find_map(|frame| frame.name.as_ref()) {
+      self.names.truncate(name.start);
+    }
+    self.frames.truncate(depth);
+  }
+}
+
+// The windows open on this thread.
+thread_local!(static OPEN: RefCell<Open> = const { RefCell::new(Open { frames: Vec::new(), names: String::new() }) });
+
+impl<K> TimerSet<K> {
+  /// Opens a window of the named timer on this thread's stack, returning how
+  /// deep it is, or `None` if the thread is exiting.
+  pub(crate) fn push_window(&self, name: Option<&str>) -> Option<usize> {
+    OPEN.try_with(|open| {
+      let mut open = open.borrow_mut();
+      let name =
+        match name {
+          Some(name) if self.collapse_recursion => {
+            let start = open.names.len();
+            open.names.push_str(name);
+            Some(start..open.names.len())
+          },
+          _ => None,
+        };
+      open.frames.push(Frame {
+        set: self.id,
+        name,
+        nested: 0,
+      });
+      open.frames.len() - 1
+    }).ok()
+  }
+
+  /// Whether this thread already has a window of the named timer open. Only
+  /// sets that collapse recursion keep track.
+  pub(crate) fn is_open(&self, name: &str) -> bool {
+    OPEN.try_with(|open| {
+      let open = open.borrow();
+      open.frames.iter().any(|frame| frame.set == self.id && frame.name.as_ref().is_some_and(|range| open.names[range.clone()] == *name))
+    }).unwrap_or(false)
+  }
+
+  /// Closes the window that began at `start`, which took `dt` nanoseconds,
+  /// counting it against the window of this set it's nested in, if any.
+  /// Returns how long the windows nested in it took. Any opened inside it
+  /// and never closed are closed with it.
+  pub(crate) fn pop_window(&self, start: Start, dt: u64) -> u64 {
+    let depth =
+      match start.depth {
+        None => return 0,
+        Some(depth) => depth,
+      };
+    OPEN.try_with(|open| {
+      let mut open = open.borrow_mut();
+      let nested =
+        match open.frames.get(depth) {
+          Some(frame) if frame.set == self.id => {
+            let nested = frame.nested;
+            open.truncate(depth);
+            nested
+          },
+          // Closed already, with a window it was nested in.
+          _ => 0,
+        };
+      if let Some(parent) = open.frames.iter_mut().rev().find(|parent| parent.set == self.id) {
+        parent.nested += dt;
+      }
+      nested
+    }).unwrap_or(0)
+  }
+
+  /// Closes the window that began at `start` without logging it, e.g. as it
+  /// wasn't sampled.
+  pub(crate) fn discard_window(&self, start: Start) {
+    if start.depth.is_some() {
+      self.pop_window(start, self.clock.to_nanos(self.clock.now().saturating_sub(start.ticks)));
+    }
+  }
+}
+
+#[cfg(not(feature = "disable"))]
+#[test]
+fn test_self_time() {
+  use std::thread::sleep;
+  use std::time::Duration;
+
+  let ts = TimerSet::new();
+  let other = TimerSet::new();
+  ts.time("a", || {
+    sleep(Duration::from_millis(2));
+    ts.time("b", || {
+      sleep(Duration::from_millis(5));
+      ts.time("c", || sleep(Duration::from_millis(5)));
+    });
+    other.time("d", || sleep(Duration::from_millis(5)));
+    let _guard = ts.start("e");
+    sleep(Duration::from_millis(1));
+  });
+
+  let timers = ts.merged();
+  let (a, b, c) = (&timers["a"], &timers["b"], &timers["c"]);
+  // `a` excludes `b` and `e`, not `c`, which `b` excludes, nor `d`, which
+  // is another set's.
+  assert!(a.self_time() >= Duration::from_millis(7), "{:?}", a.self_time());
+  assert_eq!(a.child_time, b.total_time + timers["e"].total_time);
+  assert_eq!(b.child_time, c.total_time);
+  assert_eq!(b.self_time(), Duration::from_nanos(b.total_time - c.total_time));
+  assert_eq!(c.self_time(), Duration::from_nanos(c.total_time));
+  assert_eq!(other.merged()["d"].child_time, 0);
+  assert!(a.summary(::DurationFormat::default()).contains(", self "));
+  assert!(!c.summary(::DurationFormat::default()).contains(", self "));
+  OPEN.with(|open| assert!(open.borrow().frames.is_empty()));
+}
+
+#[cfg(not(feature = "disable"))]
+#[test]
+fn test_is_open() {
+  let mut ts = TimerSet::new();
+  ts.set_collapse_recursion(true);
+  let other = TimerSet::new();
+  ts.time("parse", || {
+    let _guard = ts.start("parse.args");
+    other.time("lex", || {
+      assert!(ts.is_open("parse") && ts.is_open("parse.args"));
+      // Names are compared whole, not by prefix or by hash.
+      assert!(!ts.is_open("pars") && !ts.is_open("parse.arg") && !ts.is_open("lex"));
+      // `other` doesn't collapse recursion, so it doesn't keep names.
+      assert!(!other.is_open("lex"));
+    });
+    OPEN.with(|open| assert_eq!(open.borrow().names, "parseparse.args"));
+  });
+  OPEN.with(|open| {
+    let open = open.borrow();
+    assert!(open.frames.is_empty() && open.names.is_empty());
+  });
+}
@@ -0,0 +1,196 @@
+//! A set of timers in shared memory, which every process forked from the
+//! one that made it times into.
+
+use std::cell::UnsafeCell;
+use std::hash::Hasher;
+use std::io;
+use std::ptr;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::Duration;
+
+use fnv::FnvHasher;
+use libc;
+
+use {merge_timers, new_timers, to_nanos, AtomicStopwatch, Name, TimerSet, ENABLED};
+
+/// The longest name a slot holds, in bytes. Longer names are cut short.
+pub const MAX_SHARED_NAME_LEN: usize = 55;
+
+/// A slot no timer has claimed.
+const EMPTY: u32 = 0;
+/// A slot whose name is being written.
+const CLAIMING: u32 = 1;
+/// A slot with a name, which is safe to read.
+const READY: u32 = 2;
+
+/// One timer in the shared table.
+struct Slot {
+  state: AtomicU32,
+  /// The name, as its length followed by its bytes. Written once, while
+  /// the slot is being claimed.
+  name: UnsafeCell<(u8, [u8; MAX_SHARED_NAME_LEN])>,
+  stopwatch: AtomicStopwatch,
+}
+
+impl Slot {
+  /// Returns the slot's name, if it's been claimed.
+  fn name(&self) -> Option<&[u8]> {
+    loop {
+      match self.state.load(Ordering::Acquire) {
+        EMPTY => return None,
+        // Another process is halfway through claiming it.
+        CLAIMING => ::std::hint::spin_loop(),
+        _ => {
+          let name = unsafe { &*self.name.get() };
+          return Some(&name.1[..name.0 as usize]);
+        },
+      }
+    }
+  }
+}
+
+/// A set of timers kept in a region of memory shared with every process
+/// forked after it's made, so pre-forked workers can time into one set, and
+/// their supervisor can report on all of them while they run.
+///
+/// The timers live in a table with a fixed number of slots, which is made
+/// up front. Like `AtomicStopwatch`, they only keep totals and extremes, not
+/// histograms or variances. Once every slot is taken, windows of new timers
+/// are dropped.
+pub struct SharedTimerSet {
+  slots: *mut Slot,
+  len: usize,
+}
+
+// Slots are only written through atomics, besides names, which are
+// published by their slot's state.
+unsafe impl Send for SharedTimerSet {}
+unsafe impl Sync for SharedTimerSet {}
+
+impl SharedTimerSet {
+  /// Maps a shared table with room for `slots` timers.
+  pub fn new(slots: usize) -> io::Result<SharedTimerSet> {
+    let size = slots.max(1) * ::std::mem::size_of::<Slot>();
+    let ptr = unsafe {
+      libc::mmap(ptr::null_mut(), size, libc::PROT_READ | libc::PROT_WRITE, libc::MAP_SHARED | libc::MAP_ANONYMOUS, -1, 0)
+    };
+    if ptr == libc::MAP_FAILED {
+      return Err(io::Error::last_os_error());
+    }
+    // The mapping starts zeroed, so every slot is empty.
+    Ok(SharedTimerSet {
+      slots: ptr as *mut Slot,
+      len: slots.max(1),
+    })
+  }
+
+  fn slots(&self) -> &[Slot] {
+    unsafe { ::std::slice::from_raw_parts(self.slots, self.len) }
+  }
+
+  /// Finds the named timer's slot, claiming one for it if it hasn't got
+  /// one, or returns `None` if the table is full.
+  fn slot(&self, name: &str) -> Option<&AtomicStopwatch> {
+    let mut end = name.len().min(MAX_SHARED_NAME_LEN);
+    while !name.is_char_boundary(end) {
+      end -= 1;
+    }
+    let name = &name.as_bytes()[..end];
+    let mut hasher = FnvHasher::default();
+    hasher.write(name);
+    let slots = self.slots();
+    let start = hasher.finish() as usize % slots.len();
+    for i in 0..slots.len() {
+      let slot = &slots[(start + i) % slots.len()];
+      if slot.state.compare_exchange(EMPTY, CLAIMING, Ordering::Acquire, Ordering::Relaxed).is_ok() {
+        unsafe {
+          let slot_name = &mut *slot.name.get();
+          slot_name.0 = name.len() as u8;
+          slot_name.1[..name.len()].copy_from_slice(name);
+        }
+        slot.stopwatch.reset();
+        slot.state.store(READY, Ordering::Release);
+        return Some(&slot.stopwatch);
+      }
+      if slot.name() == Some(name) {
+        return Some(&slot.stopwatch);
+      }
+    }
+    None
+  }
+
+  /// Times a function under the given name.
+  pub fn time<T, F: FnOnce() -> T>(&self, name: &str, f: F) -> T {
+    if cfg!(feature = "disable") || !ENABLED.load(Ordering::Relaxed) {
+      return f();
+    }
+    match self.slot(name) {
+      Some(stopwatch) => stopwatch.timed(f),
+      None => f(),
+    }
+  }
+
+  /// Logs a window timed some other way under the given name.
+  pub fn record(&self, name: &str, d: Duration) {
+    if cfg!(feature = "disable") || !ENABLED.load(Ordering::Relaxed) {
+      return;
+    }
+    if let Some(stopwatch) = self.slot(name) {
+      stopwatch.add_window(to_nanos(d));
+    }
+  }
+
+  /// Copies every process's timers into a plain `TimerSet`, to report on.
+  pub fn to_timer_set(&self) -> TimerSet {
+    let mut timers = new_timers();
+    for slot in self.slots() {
+      if let Some(name) = slot.name() {
+        timers.insert(Name::new(&String::from_utf8_lossy(name)), slot.stopwatch.to_stopwatch());
+      }
+    }
+    let set = TimerSet::new();
+    set.with_shard_timers(|shard| merge_timers(&mut shard.timers, &timers, set.config));
+    set
+  }
+}
+
+impl Drop for SharedTimerSet {
+  fn drop(&mut self) {
+    unsafe {
+      libc::munmap(self.slots as *mut libc::c_void, self.len * ::std::mem::size_of::<Slot>());
+    }
+  }
+}
+
+#[cfg(not(feature = "disable"))]
+#[test]
+fn test_shared_timer_set() {
+  let timers = SharedTimerSet::new(4).unwrap();
+  timers.record("parent", Duration::from_millis(1));
+  match unsafe { libc::fork() } {
+    0 => {
+      timers.record("child", Duration::from_millis(2));
+      timers.record("parent", Duration::from_millis(3));
+      unsafe { libc::_exit(0) };
+    },
+    pid => {
+      assert!(pid > 0);
+      let mut status = 0;
+      unsafe { libc::waitpid(pid, &mut status, 0) };
+    },
+  }
+  // Names past the limit share a slot.
+  let long = "x".repeat(MAX_SHARED_NAME_LEN);
+  timers.time(&format!("{}a", long), || {});
+  timers.time(&format!("{}b", long), || {});
+  // The table is full.
+  timers.time("a", || {});
+  timers.time("b", || {});
+
+  let merged = timers.to_timer_set().merged();
+  assert_eq!(merged["parent"].number_of_windows, 2);
+  assert_eq!(merged["parent"].total_time, 4_000_000);
+  assert_eq!(merged["child"].number_of_windows, 1);
+  assert_eq!(merged[long.as_str()].number_of_windows, 2);
+  assert_eq!(merged.len(), 4);
+}
@@ -0,0 +1,172 @@
+//! Dumping reports when the process gets `SIGUSR1`.
+
+use libc;
+use std::fs::File;
+use std::io;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicI32, Ordering};
+use std::sync::{Arc, Mutex, Once, Weak};
+use std::thread;
+
+use {lock, merge_threads, TimerSet, WriteReporter};
+
+/// What to dump on a signal.
+enum Target {
+  /// A set, for as long as it's alive.
+  Set(Weak<TimerSet>),
+  /// Every thread's thread-local set, merged.
+  Threads,
+}
+
+/// Everything to dump on a signal, and the file to dump it to.
+static TARGETS: Mutex<Vec<(Target, PathBuf)>> = Mutex::new(Vec::new());
+/// The end of the pipe the signal handler writes to.
+static PIPE: AtomicI32 = AtomicI32::new(-1);
+static INSTALL: Once = Once::new();
+/// Whether the handler was installed successfully.
+static INSTALLED: AtomicBool = AtomicBool::new(false);
+
+extern "C" fn on_signal(_: libc::c_int) {
+  // Only async-signal-safe calls are allowed here, so just wake the thread.
+  let byte = 0u8;
+  unsafe {
+    libc::write(PIPE.load(Ordering::Relaxed), &byte as *const u8 as *const libc::c_void, 1);
+  }
+}
+
+/// Writes every live target's report to its file.
+fn dump() {
+  let mut targets = lock(&TARGETS);
+  targets.retain(|(target, _)| if let Target::Set(ref timers) = *target { timers.strong_count() > 0 } else { true });
+  for (target, path) in targets.iter() {
+    let timers =
+      match *target {
+        Target::Set(ref timers) => timers.upgrade(),
+        Target::Threads => Some(Arc::new(merge_threads())),
+      };
+    if let Some(timers) = timers {
+      match File::create(path) {
+        Ok(file) => timers.report(&WriteReporter::new(file)),
+        Err(e) => warn!("Couldn't dump timers to {:?}: {}", path, e),
+      }
+    }
+  }
+}
+
+/// Creates the pipe, starts the thread that dumps reports, and installs the
+/// signal handler.
+fn install() -> io::Result<()> {
+  let mut fds = [0; 2];
+  if unsafe { libc::pipe(fds.as_mut_ptr()) } != 0 {
+    return Err(io::Error::last_os_error());
+  }
+  let (read, write) = (fds[0], fds[1]);
+  PIPE.store(write, Ordering::Relaxed);
+
+  thread::Builder::new().name("stopwatch-signal".to_string()).spawn(move || {
+    let mut byte = 0u8;
+    loop {
+      let n = unsafe { libc::read(read, &mut byte as *mut u8 as *mut libc::c_void, 1) };
+      if n == 1 {
+        dump();
+      } else if n < 0 && io::Error::last_os_error().kind() == io::ErrorKind::Interrupted {
+        continue;
+      } else {
+        return;
+      }
+    }
+  })?;
+
+  unsafe {
+    let mut action: libc::sigaction = ::std::mem::zeroed();
+    action.sa_sigaction = on_signal as *const () as libc::sighandler_t;
+    action.sa_flags = libc::SA_RESTART;
+    libc::sigemptyset(&mut action.sa_mask);
+    if libc::sigaction(libc::SIGUSR1, &action, ::std::ptr::null_mut()) != 0 {
+      return Err(io::Error::last_os_error());
+    }
+  }
+  INSTALLED.store(true, Ordering::Relaxed);
+  Ok(())
+}
+
+/// Installs the handler, if it hasn't been, and dumps `target` to `path` on
+/// every signal from now on.
+fn watch(target: Target, path: PathBuf) -> io::Result<()> {
+  let mut result = Ok(());
+  INSTALL.call_once(|| result = install());
+  result?;
+  if !INSTALLED.load(Ordering::Relaxed) {
+    return Err(io::Error::other("the SIGUSR1 handler couldn't be installed"));
+  }
+  lock(&TARGETS).push((target, path));
+  Ok(())
+}
+
+/// Overwrites `path` with the report of every thread's thread-local set,
+/// the ones behind `stopwatch::time`, merged into one, whenever the process
+/// gets `SIGUSR1`, e.g. from `kill -USR1 <pid>`. The first call here or to
+/// `TimerSet::dump_on_signal` installs a handler for the signal, replacing
+/// any other one.
+pub fn dump_on_signal<P: Into<PathBuf>>(path: P) -> io::Result<()> {
+  watch(Target::Threads, path.into())
+}
+
+impl TimerSet {
+  /// Like `stopwatch::dump_on_signal`, but dumps this set's report, for as
+  /// long as the set is alive.
+  pub fn dump_on_signal<P: Into<PathBuf>>(self: &Arc<Self>, path: P) -> io::Result<()> {
+    watch(Target::Set(Arc::downgrade(self)), path.into())
+  }
+}
+
+#[cfg(not(feature = "disable"))]
+#[test]
+fn test_dump_on_signal() {
+  use std::time::{Duration, Instant};
+
+  let path = ::std::env::temp_dir().join(format!("stopwatch-signal-{}.txt", ::std::process::id()));
+  let timers = Arc::new(TimerSet::new());
+  timers.time("a", || {});
+  timers.dump_on_signal(&path).unwrap();
+  unsafe {
+    libc::raise(libc::SIGUSR1);
+  }
+
+  let deadline = Instant::now() + Duration::from_secs(10);
+  loop {
+    if let Ok(report) = ::std::fs::read_to_string(&path) {
+      if report.starts_with("a: ") {
+        break;
+      }
+    }
+    assert!(Instant::now() < deadline, "no report was dumped");
+    thread::sleep(Duration::from_millis(1));
+  }
+  let _ = ::std::fs::remove_file(&path);
+}
+
+#[cfg(not(feature = "disable"))]
+#[test]
+fn test_dump_threads_on_signal() {
+  use std::time::{Duration, Instant};
+
+  thread::spawn(|| ::time("test_dump_threads_on_signal", || {})).join().unwrap();
+  let path = ::std::env::temp_dir().join(format!("stopwatch-signal-threads-{}.txt", ::std::process::id()));
+  dump_on_signal(&path).unwrap();
+  unsafe {
+    libc::raise(libc::SIGUSR1);
+  }
+
+  let deadline = Instant::now() + Duration::from_secs(10);
+  loop {
+    if let Ok(report) = ::std::fs::read_to_string(&path) {
+      if report.lines().any(|line| line.starts_with("test_dump_threads_on_signal: ")) {
+        break;
+      }
+    }
+    assert!(Instant::now() < deadline, "no report was dumped");
+    thread::sleep(Duration::from_millis(1));
+  }
+  let _ = ::std::fs::remove_file(&path);
+}
@@ -0,0 +1,122 @@
+//! Keeping a timer's slowest windows, with what they were doing.
+
+use std::time::Duration;
+
+use {lock, Extras, TimerSet};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// One of a timer's slowest windows, kept by `TimerSet::set_slowest`.
+pub struct SlowWindow {
+  /// How long the window took.
+  pub duration: Duration,
+  /// What the window was doing, from `time_with_context`, or `None` if it
+  /// was timed some other way.
+  pub context: Option<String>,
+}
+
+/// Adds a window to `slowest`, sorted slowest first, if it's among the `n`
+/// slowest.
+fn insert(slowest: &mut Vec<SlowWindow>, n: usize, window: SlowWindow) {
+  let i = slowest.iter().position(|w| w.duration < window.duration).unwrap_or(slowest.len());
+  if i < n {
+    slowest.insert(i, window);
+    slowest.truncate(n);
+  }
+}
+
+/// Whether a window of `dt` would be among the `n` slowest in `slowest`.
+fn admits(slowest: Option<&Vec<SlowWindow>>, n: usize, dt: Duration) -> bool {
+  match slowest {
+    None => n > 0,
+    Some(slowest) => slowest.len() < n || slowest.last().is_some_and(|w| dt > w.duration),
+  }
+}
+
+impl<K> TimerSet<K> {
+  /// Keeps the `n` slowest windows of the named timer, so they can be
+  /// looked into with `slowest`. `name` is the full name the timer reports
+  /// under. An `n` of 0 stops keeping them.
+  pub fn set_slowest(&mut self, name: &str, n: usize) {
+    if n > 0 {
+      self.slowest_settings.insert(name.to_string(), n);
+    } else {
+      self.slowest_settings.remove(name);
+    }
+  }
+
+  /// Like `time`, but if this turns out to be one of the timer's slowest
+  /// windows, describes it with `context`, e.g. the file being parsed.
+  /// `context` is only called then, so it can be expensive.
+  pub fn time_with_context<T, C: FnOnce() -> String, F: FnOnce() -> T>(&self, name: &str, context: C, f: F) -> T {
+    self.time_window(name, None, f, |name, key, start, weight, _| {
+      let mut context = Some(context);
+      let context = Extras {
+        context: Some(&mut || context.take().map_or_else(String::new, |c| c())),
+        ..Extras::default()
+      };
+      self.stop_with_extras(name, key, start, weight, context);
+    }).0
+  }
+
+  /// Keeps a window of the named timer if it's one of its slowest.
+  pub(crate) fn add_slowest(&self, name: &str, dt: u64, context: Option<&mut dyn FnMut() -> String>) {
+    let n =
+      match self.slowest_settings.get(name) {
+        None => return,
+        Some(&n) => n,
+      };
+    let duration = Duration::from_nanos(dt);
+    if !self.with_shard_timers(|shard| admits(shard.slowest.get(name), n, duration)) {
+      return;
+    }
+    // Describe the window without the shard locked, in case that times
+    // something too.
+    let window = SlowWindow {
+      duration,
+      context: context.map(|context| context()),
+    };
+    self.with_shard_timers(|shard| insert(shard.slowest.entry(name.to_string()).or_default(), n, window));
+  }
+
+  /// Returns the named timer's slowest windows across every thread, slowest
+  /// first, if it's keeping them.
+  pub fn slowest(&self, name: &str) -> Vec<SlowWindow> {
+    let n = self.slowest_settings.get(name).cloned().unwrap_or(0);
+    let mut slowest = Vec::new();
+    for shard in lock(&self.shards).iter() {
+      if let Some(windows) = lock(shard).slowest.get(name) {
+        for window in windows.iter() {
+          insert(&mut slowest, n, window.clone());
+        }
+      }
+    }
+    slowest
+  }
+}
+
+#[cfg(not(feature = "disable"))]
+#[test]
+fn test_slowest() {
+  let mut ts = TimerSet::new();
+  ts.set_slowest("parse", 2);
+  for (file, ms) in [("a.rs", 1), ("b.rs", 30), ("c.rs", 2), ("d.rs", 3)].iter() {
+    ts.time_with_context("parse", || file.to_string(), || ::std::thread::sleep(Duration::from_millis(*ms)));
+  }
+  ::std::thread::scope(|s| {
+    s.spawn(|| ts.record("parse", Duration::from_millis(50)));
+  });
+
+  let slowest = ts.slowest("parse");
+  assert_eq!(slowest.len(), 2);
+  assert_eq!(slowest[0], SlowWindow {
+    duration: Duration::from_millis(50),
+    context: None,
+  });
+  assert_eq!(slowest[1].context.as_deref(), Some("b.rs"));
+  assert!(ts.slowest("other").is_empty());
+
+  // The context is only worked out for windows that are kept.
+  let called = ::std::cell::Cell::new(false);
+  ts.time_with_context("parse", || { called.set(true); String::new() }, || {});
+  assert!(!called.get());
+}
@@ -0,0 +1,136 @@
+//! Point-in-time copies of a `TimerSet`, which can be saved and reloaded
+//! with the `serde` feature.
+
+use std::collections::BTreeMap;
+#[cfg(feature = "serde")]
+use std::time::Duration;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+#[cfg(feature = "serde")]
+use {to_nanos, Histogram, Instant, StopwatchConfig};
+use {Stopwatch, TimerSet};
+
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+/// A copy of every timer in a `TimerSet`, keyed by name.
+pub struct TimerSetSnapshot {
+  /// The timers, sorted by name.
+  pub timers: BTreeMap<String, Stopwatch>,
+}
+
+impl TimerSetSnapshot {
+  /// Returns the named timer, if it was in the set.
+  pub fn get(&self, name: &str) -> Option<&Stopwatch> {
+    self.timers.get(name)
+  }
+}
+
+impl<K> TimerSet<K> {
+  /// Copies every timer in this set, without resetting them.
+  pub fn snapshot(&self) -> TimerSetSnapshot {
+    TimerSetSnapshot {
+      timers: self.merged().into_iter().map(|(name, sw)| (name.to_string(), sw)).collect(),
+    }
+  }
+}
+
+#[cfg(feature = "serde")]
+#[derive(Serialize, Deserialize)]
+/// How a `Stopwatch` is serialized. Durations are in nanoseconds.
+struct StopwatchRepr {
+  total_time: u64,
+  number_of_windows: u64,
+  min_time: u64,
+  max_time: u64,
+  mean: f64,
+  /// `None` if the variance isn't known.
+  m2: Option<f64>,
+  /// `None` in snapshots from before the moving average was kept.
+  #[serde(default)]
+  ewma: Option<f64>,
+  /// `Instant`s can't be serialized, so keep the time they span instead. A
+  /// reloaded stopwatch's windows are taken to have ended when it's loaded.
+  observed_interval: Option<u64>,
+  histogram: Option<Box<Histogram>>,
+  #[serde(default)]
+  allocations: u64,
+  #[serde(default)]
+  allocated_bytes: u64,
+  #[serde(default)]
+  violations: u64,
+  #[serde(default)]
+  child_time: u64,
+}
+
+#[cfg(feature = "serde")]
+impl Serialize for Stopwatch {
+  fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+    StopwatchRepr {
+      total_time: self.total_time,
+      number_of_windows: self.number_of_windows,
+      min_time: self.min_time,
+      max_time: self.max_time,
+      mean: self.mean,
+      m2: if self.m2.is_nan() { None } else { Some(self.m2) },
+      ewma: Some(self.ewma),
+      observed_interval: self.observed_interval().map(to_nanos),
+      histogram: self.histogram.clone(),
+      allocations: self.allocations,
+      allocated_bytes: self.allocated_bytes,
+      violations: self.violations,
+      child_time: self.child_time,
+    }.serialize(s)
+  }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for Stopwatch {
+  fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Stopwatch, D::Error> {
+    let repr = StopwatchRepr::deserialize(d)?;
+    let now = Instant::now();
+    let first_start = repr.observed_interval.map(|ns| now.checked_sub(Duration::from_nanos(ns)).unwrap_or(now));
+    Ok(Stopwatch {
+      total_time: repr.total_time,
+      number_of_windows: repr.number_of_windows,
+      min_time: repr.min_time,
+      max_time: repr.max_time,
+      mean: repr.mean,
+      m2: repr.m2.unwrap_or(f64::NAN),
+      ewma: repr.ewma.unwrap_or(repr.mean),
+      ewma_alpha: StopwatchConfig::default().ewma_alpha,
+      first_start,
+      last_end: first_start.map(|_| now),
+      histogram: repr.histogram,
+      unsampled: 0,
+      warmup_windows: 0,
+      open_window: None,
+      resumed_at: None,
+      allocations: repr.allocations,
+      allocated_bytes: repr.allocated_bytes,
+      violations: repr.violations,
+      child_time: repr.child_time,
+    })
+  }
+}
+
+#[cfg(feature = "serde")]
+#[cfg(not(feature = "disable"))]
+#[test]
+fn test_snapshot_round_trip() {
+  let ts = TimerSet::new();
+  ts.time("a", || ::std::thread::sleep(Duration::from_millis(1)));
+  ts.time("a", || {});
+  let snapshot = ts.snapshot();
+
+  let json = ::serde_json::to_string(&snapshot).unwrap();
+  let loaded: TimerSetSnapshot = ::serde_json::from_str(&json).unwrap();
+  let (before, after) = (snapshot.get("a").unwrap().stats(), loaded.get("a").unwrap().stats());
+  assert_eq!(after.count, 2);
+  assert_eq!(after.total, before.total);
+  assert_eq!(after.max, before.max);
+  assert_eq!(after.stddev, before.stddev);
+  assert_eq!(after.p99, before.p99);
+  assert!(after.rate.is_some());
+}
@@ -0,0 +1,61 @@
+//! Recording windows that were timed by something else.
+
+#[cfg(any(test, target_arch = "x86", target_arch = "x86_64"))]
+use std::time::Duration;
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+use tsc;
+use {to_nanos, Allocations, Extras, Instant, TimerSet};
+
+impl<K> TimerSet<K> {
+  /// Logs a window that was timed by something else, e.g. from a GPU's or a
+  /// driver's timestamps, under the named timer, so it reports alongside
+  /// the ones timed here. `end` is when the window ended, for working out
+  /// throughput and history. An `end` before `start` is logged as taking no
+  /// time. The instants are `stopwatch::Instant`s, which are
+  /// `std::time::Instant`s except in browsers with the `wasm` feature.
+  pub fn record_span(&self, name: &str, start: Instant, end: Instant) {
+    if !self.is_enabled() {
+      return;
+    }
+    let (name, scope) = self.enter(name);
+    drop(scope);
+    let dt = to_nanos(end.saturating_duration_since(start));
+    self.add_windows_with_extras(&name, None, dt, 1, Allocations::default(), 0, end, Extras::default());
+  }
+
+  #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+  /// Like `record_span`, for readings of the time stamp counter, e.g. from
+  /// `TscClock`, taken on this machine. The window is taken to have just
+  /// ended, since ticks can't be lined up with `Instant`s.
+  pub fn record_tsc_span(&self, name: &str, start: u64, end: u64) {
+    tsc::calibrated_ticks_per_second();
+    self.record(name, Duration::from_nanos(tsc::to_ns(end.saturating_sub(start))));
+  }
+}
+
+#[cfg(not(feature = "disable"))]
+#[test]
+fn test_record_span() {
+  let ts = TimerSet::new();
+  let start = Instant::now();
+  let end = start + Duration::from_millis(3);
+  ts.record_span("gpu", start, end);
+  ts.record_span("gpu", end, start);
+
+  let gpu = &ts.merged()["gpu"];
+  assert_eq!(gpu.number_of_windows, 2);
+  assert_eq!(gpu.total_time, 3_000_000);
+  assert_eq!(gpu.last_end, Some(end));
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+#[cfg(not(feature = "disable"))]
+#[test]
+fn test_record_tsc_span() {
+  let ts = TimerSet::new();
+  let ticks = tsc::calibrated_ticks_per_second();
+  ts.record_tsc_span("gpu", ticks, ticks * 3 / 2);
+  let total = ts.merged()["gpu"].total_time;
+  assert!(total > 490_000_000 && total < 510_000_000, "{}", total);
+}
@@ -0,0 +1,138 @@
+//! Structured access to computed timer statistics.
+
+use core::time::Duration;
+
+use Stopwatch;
+#[cfg(feature = "std")]
+use {lock, TimerSet};
+
+#[derive(Debug, Clone, PartialEq)]
+/// Every statistic a stopwatch reports, computed at one point in time.
+pub struct StopwatchStats {
+  /// The number of windows timed.
+  pub count: u64,
+  /// The total time across all windows.
+  pub total: Duration,
+  /// The mean window, or `None` if no windows were timed.
+  pub mean: Option<Duration>,
+  /// The exponential moving average of the windows, or `None` if no
+  /// windows were timed.
+  pub ewma: Option<Duration>,
+  /// The shortest window, or `None` if no windows were timed.
+  pub min: Option<Duration>,
+  /// The longest window, or `None` if no windows were timed.
+  pub max: Option<Duration>,
+  /// The sample standard deviation of the windows, or `None` if no windows
+  /// were timed or it wasn't tracked.
+  pub stddev: Option<Duration>,
+  /// The median window, or `None` if no histogram is kept.
+  pub p50: Option<Duration>,
+  /// The 90th percentile window, or `None` if no histogram is kept.
+  pub p90: Option<Duration>,
+  /// The 95th percentile window, or `None` if no histogram is kept.
+  pub p95: Option<Duration>,
+  /// The 99th percentile window, or `None` if no histogram is kept.
+  pub p99: Option<Duration>,
+  /// Windows per second over the observed interval, if there is one.
+  pub rate: Option<f64>,
+  /// The number of allocations made in the windows, if a
+  /// `CountingAllocator` is counting them, or zero.
+  pub allocations: u64,
+  /// The total size of those allocations, in bytes.
+  pub allocated_bytes: u64,
+  /// The number of windows over their timeout, of those timed with
+  /// `TimerSet::time_with_timeout`.
+  pub violations: u64,
+  /// The time spent in the timer itself, rather than in the timers nested
+  /// inside it.
+  pub self_time: Duration,
+}
+
+impl Stopwatch {
+  /// Computes every statistic this stopwatch reports.
+  pub fn stats(&self) -> StopwatchStats {
+    StopwatchStats {
+      count: self.number_of_windows,
+      total: Duration::from_nanos(self.total_time),
+      mean: self.total_time.checked_div(self.number_of_windows).map(Duration::from_nanos),
+      ewma: self.ewma(),
+      min: self.min_time(),
+      max: self.max_time(),
+      stddev: self.stddev(),
+      p50: self.quantile(0.5),
+      p90: self.quantile(0.9),
+      p95: self.quantile(0.95),
+      p99: self.quantile(0.99),
+      rate: self.rate(),
+      allocations: self.allocations,
+      allocated_bytes: self.allocated_bytes,
+      violations: self.violations,
+      self_time: self.self_time(),
+    }
+  }
+}
+
+#[cfg(feature = "std")]
+impl<K> TimerSet<K> {
+  /// Computes the statistics of every timer that has run, sorted by name.
+  pub fn stats(&self) -> Vec<(String, StopwatchStats)> {
+    self.sorted_timers()
+      .into_iter()
+      .map(|(name, sw)| (name, sw.stats()))
+      .collect()
+  }
+
+  /// Iterates over the statistics of every timer that has run, sorted by
+  /// name. They're computed up front, so the set can be timed with while
+  /// iterating.
+  pub fn iter(&self) -> impl Iterator<Item = (String, StopwatchStats)> {
+    self.stats().into_iter()
+  }
+
+  /// Computes the statistics of the named timer, or returns `None` if it
+  /// hasn't run. This only merges the one timer, so it's cheaper than
+  /// `stats` for sets with many timers.
+  pub fn get(&self, name: &str) -> Option<StopwatchStats> {
+    let mut sw = Stopwatch::with_config(self.config);
+    let handle = lock(&self.handle_names).iter().position(|n| n == name);
+    for shard in lock(&self.shards).iter() {
+      let shard = lock(shard);
+      if let Some(timer) = shard.timers.get(name) {
+        sw.merge(timer);
+      }
+      if let Some(timer) = handle.and_then(|i| shard.handles.get(i)) {
+        sw.merge(timer);
+      }
+    }
+    if sw.number_of_windows == 0 { None } else { Some(sw.stats()) }
+  }
+}
+
+#[cfg(all(feature = "std", not(feature = "disable")))]
+#[test]
+fn test_stats() {
+  let ts = TimerSet::new();
+  assert!(ts.stats().is_empty());
+  ts.time("b", || {});
+  ts.time("a", || ::std::thread::sleep(Duration::from_millis(1)));
+  ts.time("a", || {});
+
+  let stats = ts.stats();
+  let names: Vec<&str> = stats.iter().map(|s| s.0.as_ref()).collect();
+  assert_eq!(names, vec!["a", "b"]);
+  let a = &stats[0].1;
+  assert_eq!(a.count, 2);
+  assert!(a.total >= Duration::from_millis(1));
+  assert_eq!(a.mean, Some(a.total / 2));
+  assert!(a.max.unwrap() >= Duration::from_millis(1));
+  assert!(a.stddev.is_some());
+  assert!(a.p50.is_some());
+  assert_eq!(stats[1].1.stddev, Some(Duration::from_nanos(0)));
+
+  assert_eq!(ts.iter().map(|(name, _)| name).collect::<Vec<_>>(), vec!["a", "b"]);
+  assert_eq!(ts.get("a").as_ref(), Some(a));
+  assert_eq!(ts.get("c"), None);
+  let handle = ts.register("c");
+  ts.time_handle(handle, || {});
+  assert_eq!(ts.get("c").unwrap().count, 1);
+}
@@ -0,0 +1,173 @@
+//! Shipping timer stats to a statsd or DogStatsD server over UDP.
+
+use std::io;
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+use std::sync::Arc;
+use std::time::Duration;
+
+use {tags, ReporterHandle, TimerSet};
+
+/// The most to put in one datagram, to stay under a typical MTU.
+const MAX_PACKET: usize = 1432;
+
+/// Makes `name` safe to use in a statsd metric name, by replacing the
+/// characters that delimit the rest of the line with underscores.
+fn sanitize(name: &str) -> String {
+  name.chars().map(|c| if c == ':' || c == '|' || c == '@' || c == '#' || c == '\n' { '_' } else { c }).collect()
+}
+
+/// Sends a set's stats to a statsd server, e.g. with
+/// `TimerSet::spawn_statsd`.
+///
+/// Each timer is sent as a timing of its mean window, sampled at one in
+/// however many windows it had, so the server counts every window. Counters
+/// are sent as counts and gauges as their latest values.
+///
+/// Tags, both those given with `tag` and a tagged timer's own, are sent the
+/// DogStatsD way, e.g. `|#table:users`, which plain statsd servers don't
+/// understand.
+pub struct StatsdExporter {
+  socket: UdpSocket,
+  prefix: String,
+  tags: Vec<(String, String)>,
+}
+
+impl StatsdExporter {
+  /// Creates an exporter sending to the statsd server at `addr`, e.g.
+  /// `"127.0.0.1:8125"`.
+  pub fn new<A: ToSocketAddrs>(addr: A) -> io::Result<StatsdExporter> {
+    let addr = addr.to_socket_addrs()?.next().ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "no address to send to"))?;
+    let local: SocketAddr =
+      match addr {
+        SocketAddr::V4(_) => ([0, 0, 0, 0], 0).into(),
+        SocketAddr::V6(_) => ([0u16; 8], 0).into(),
+      };
+    let socket = UdpSocket::bind(local)?;
+    socket.connect(addr)?;
+    Ok(StatsdExporter {
+      socket,
+      prefix: String::new(),
+      tags: Vec::new(),
+    })
+  }
+
+  /// Prefixes every metric's name with `prefix` and a dot, e.g. `myapp`.
+  pub fn prefix(mut self, prefix: &str) -> StatsdExporter {
+    self.prefix = if prefix.is_empty() { String::new() } else { format!("{}.", sanitize(prefix)) };
+    self
+  }
+
+  /// Tags every metric with `key:value`, e.g. the host or environment.
+  pub fn tag(mut self, key: &str, value: &str) -> StatsdExporter {
+    self.tags.push((sanitize(key), sanitize(value)));
+    self
+  }
+
+  /// Renders a metric's line, with the given value and type, e.g. `ms|@0.5`,
+  /// and its tags.
+  fn line(&self, name: &str, value: &str, tags: &[(&str, &str)]) -> String {
+    let mut line = format!("{}{}:{}", self.prefix, sanitize(name), value);
+    let tags: Vec<String> =
+      self.tags
+        .iter()
+        .map(|(k, v)| format!("{}:{}", k, v))
+        .chain(tags.iter().map(|&(k, v)| format!("{}:{}", sanitize(k), sanitize(v))))
+        .collect();
+    if !tags.is_empty() {
+      line.push_str("|#");
+      line.push_str(&tags.join(","));
+    }
+    line
+  }
+
+  /// Renders every timer, counter and gauge in `timers` as statsd lines.
+  fn lines<K>(&self, timers: &TimerSet<K>) -> Vec<String> {
+    let mut lines = Vec::new();
+    for (name, sw) in timers.sorted_timers().iter() {
+      let (name, tags) = tags::split_tags(name);
+      let mean_ms = sw.total_time as f64 / sw.number_of_windows as f64 / 1e6;
+      let value =
+        if sw.number_of_windows == 1 {
+          format!("{}|ms", mean_ms)
+        } else {
+          format!("{}|ms|@{}", mean_ms, 1.0 / sw.number_of_windows as f64)
+        };
+      lines.push(self.line(name, &value, &tags));
+    }
+    for (name, n) in timers.counters() {
+      lines.push(self.line(&name, &format!("{}|c", n), &[]));
+    }
+    for (name, gauge) in timers.gauges() {
+      lines.push(self.line(&name, &format!("{}|g", gauge.last), &[]));
+    }
+    lines
+  }
+
+  /// Sends every timer, counter and gauge in `timers`, as many lines to a
+  /// datagram as fit.
+  pub fn send<K>(&self, timers: &TimerSet<K>) -> io::Result<()> {
+    let mut packet = String::new();
+    for line in self.lines(timers) {
+      if !packet.is_empty() && packet.len() + 1 + line.len() > MAX_PACKET {
+        self.socket.send(packet.as_bytes())?;
+        packet.clear();
+      }
+      if !packet.is_empty() {
+        packet.push('\n');
+      }
+      packet.push_str(&line);
+    }
+    if !packet.is_empty() {
+      self.socket.send(packet.as_bytes())?;
+    }
+    Ok(())
+  }
+}
+
+impl<K: 'static> TimerSet<K> {
+  /// Starts a thread that, every `interval`, takes a snapshot of this set,
+  /// resetting it, and sends the snapshot through `exporter`, as
+  /// `spawn_reporter` does with reports. Failures to send are logged, and
+  /// the next interval is sent regardless.
+  pub fn spawn_statsd(self: &Arc<Self>, interval: Duration, exporter: StatsdExporter) -> ReporterHandle {
+    let timers = Arc::downgrade(self);
+    ReporterHandle::spawn(interval, move || {
+      match timers.upgrade() {
+        None => false,
+        Some(timers) => {
+          if let Err(e) = exporter.send(&timers.take_interval()) {
+            warn!("Couldn't send timers to statsd: {}", e);
+          }
+          true
+        },
+      }
+    })
+  }
+}
+
+#[cfg(not(feature = "disable"))]
+#[test]
+fn test_statsd() {
+  let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+  server.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+  let exporter = StatsdExporter::new(server.local_addr().unwrap()).unwrap().prefix("app").tag("env", "test");
+
+  let ts = TimerSet::new();
+  ts.record("a", Duration::from_millis(2));
+  ts.record("a", Duration::from_millis(4));
+  ts.record_with_tags("db|query", &[("table", "users")], Duration::from_millis(1));
+  ts.count("hits", 3);
+  ts.gauge("depth", 7.0);
+  exporter.send(&ts).unwrap();
+
+  let mut buf = [0; 2048];
+  let n = server.recv(&mut buf).unwrap();
+  let packet = ::std::str::from_utf8(&buf[..n]).unwrap();
+  let lines: Vec<&str> = packet.lines().collect();
+  assert_eq!(lines, vec![
+    "app.a:3|ms|@0.5|#env:test",
+    "app.db_query:1|ms|#env:test,table:users",
+    "app.hits:3|c|#env:test",
+    "app.depth:7|g|#env:test",
+  ]);
+}
@@ -0,0 +1,67 @@
+//! Attribute macros for `stopwatch`. Use these through the `stopwatch`
+//! crate's `macros` feature, rather than depending on this crate directly.
+
+extern crate proc_macro;
+extern crate proc_macro2;
+#[macro_use]
+extern crate quote;
+extern crate syn;
+
+use proc_macro::TokenStream;
+use syn::{ItemFn, LitStr, ReturnType, Type};
+
+/// Times every call to the function with the thread-local `TimerSet`.
+///
+/// The timer is named `module::path::fn_name`, unless a name is given, as in
+/// `#[timed("parse")]`.
+#[proc_macro_attribute]
+pub fn timed(attr: TokenStream, item: TokenStream) -> TokenStream {
+  let name: Option<LitStr> =
+    if attr.is_empty() {
+      None
+    } else {
+      match syn::parse(attr) {
+        Ok(name) => Some(name),
+        Err(e) => return e.to_compile_error().into(),
+      }
+    };
+  let mut f: ItemFn =
+    match syn::parse(item) {
+      Ok(f) => f,
+      Err(e) => return e.to_compile_error().into(),
+    };
+  if let Some(asyncness) = f.sig.asyncness {
+    return syn::Error::new_spanned(asyncness, "#[timed] doesn't support async functions")
+      .to_compile_error()
+      .into();
+  }
+
+  let name =
+    match name {
+      Some(name) => quote!(#name),
+      None => {
+        let ident = f.sig.ident.to_string();
+        quote!(concat!(module_path!(), "::", #ident))
+      },
+    };
+  // Closures can't return `impl Trait`, so leave those to inference.
+  let output =
+    match f.sig.output {
+      ReturnType::Type(_, ref ty) if !is_impl_trait(ty) => quote!(-> #ty),
+      _ => quote!(),
+    };
+  let body = f.block;
+  f.block = Box::new(syn::parse_quote!({
+    ::stopwatch::time_static(#name, || #output #body)
+  }));
+  quote!(#f).into()
+}
+
+fn is_impl_trait(ty: &Type) -> bool {
+  match *ty {
+    Type::ImplTrait(_) => true,
+    Type::Paren(ref ty) => is_impl_trait(&ty.elem),
+    Type::Group(ref ty) => is_impl_trait(&ty.elem),
+    _ => false,
+  }
+}
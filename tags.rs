@@ -0,0 +1,121 @@
+//! Timers split by dimensional tags, e.g. a query timer per table.
+//!
+//! A tagged timer is kept under its name followed by its tags, sorted by
+//! key, e.g. `db.query{table=users}`, so each set of tags aggregates
+//! separately.
+
+use TimerSet;
+
+/// The characters that delimit tags in a timer's name.
+const RESERVED: [char; 4] = ['{', '}', ',', '='];
+
+/// Returns whether none of `tags`' keys or values contain a character that
+/// delimits tags.
+fn valid_tags(tags: &[(&str, &str)]) -> bool {
+  tags.iter().all(|(k, v)| !k.contains(&RESERVED[..]) && !v.contains(&RESERVED[..]))
+}
+
+/// Names the timer for `name` with `tags`, e.g. `db.query{table=users}`.
+/// Any of `{},=` in a tag's key or value becomes `_`, so the name always
+/// splits back into the same number of tags.
+pub(crate) fn tagged_name<K: AsRef<str>, V: AsRef<str>>(name: &str, tags: &[(K, V)]) -> String {
+  if tags.is_empty() {
+    return name.to_string();
+  }
+  let mut tags: Vec<(String, String)> =
+    tags
+      .iter()
+      .map(|(k, v)| (k.as_ref().replace(&RESERVED[..], "_"), v.as_ref().replace(&RESERVED[..], "_")))
+      .collect();
+  tags.sort();
+  let tags: Vec<String> = tags.iter().map(|(k, v)| format!("{}={}", k, v)).collect();
+  format!("{}{{{}}}", name, tags.join(","))
+}
+
+/// Splits a timer's name into the name it was tagged under, and its tags.
+pub(crate) fn split_tags(name: &str) -> (&str, Vec<(&str, &str)>) {
+  let untagged = untagged(name);
+  if untagged.len() == name.len() {
+    return (name, Vec::new());
+  }
+  let tags = name[untagged.len() + 1..name.len() - 1].split(',').filter_map(|tag| tag.split_once('=')).collect();
+  (untagged, tags)
+}
+
+/// Returns the name a timer was tagged under, or its whole name if it has
+/// no tags.
+pub(crate) fn untagged(name: &str) -> &str {
+  match name.find('{') {
+    Some(open) if name.ends_with('}') => &name[..open],
+    _ => name,
+  }
+}
+
+impl<K> TimerSet<K> {
+  /// Like `time`, but logs under a timer for this combination of tags, e.g.
+  /// `time_with_tags("db.query", &[("table", "users")], f)`. Timers with the
+  /// same name are listed together in reports, and exported to Prometheus
+  /// with their tags as labels.
+  ///
+  /// Tags mustn't contain any of `{},=`: debug builds panic if they do, and
+  /// release builds replace them with `_`.
+  pub fn time_with_tags<T, F: FnOnce() -> T>(&self, name: &str, tags: &[(&str, &str)], f: F) -> T {
+    debug_assert!(valid_tags(tags), "tags mustn't contain any of {{}},=: {:?}", tags);
+    if !self.is_enabled() {
+      return f();
+    }
+    self.time(&tagged_name(name, tags), f)
+  }
+
+  /// Like `record`, but logs under a timer for this combination of tags; see
+  /// `time_with_tags`.
+  pub fn record_with_tags(&self, name: &str, tags: &[(&str, &str)], d: ::std::time::Duration) {
+    debug_assert!(valid_tags(tags), "tags mustn't contain any of {{}},=: {:?}", tags);
+    if !self.is_enabled() {
+      return;
+    }
+    self.record(&tagged_name(name, tags), d);
+  }
+}
+
+#[cfg(not(feature = "disable"))]
+#[test]
+fn test_time_with_tags() {
+  use std::time::Duration;
+  use SortBy;
+  use SortOrder;
+
+  let ts = TimerSet::new();
+  ts.record_with_tags("query", &[("table", "users"), ("op", "select")], Duration::from_millis(1));
+  ts.record_with_tags("query", &[("op", "select"), ("table", "users")], Duration::from_millis(1));
+  ts.time_with_tags("query", &[("table", "posts")], || {});
+  ts.record("other", Duration::from_millis(1));
+  let timers = ts.merged();
+  assert_eq!(timers["query{op=select,table=users}"].number_of_windows, 2);
+  assert_eq!(split_tags("query{op=select,table=users}"), ("query", vec![("op", "select"), ("table", "users")]));
+  assert_eq!(split_tags("query"), ("query", vec![]));
+
+  // The "query" timers stay together, even though "other" took longer than
+  // one of them.
+  let lines = ::std::cell::RefCell::new(Vec::new());
+  ts.report_sorted(&|line: &str| lines.borrow_mut().push(line.to_string()), SortOrder::descending(SortBy::TotalTime));
+  let names: Vec<String> = lines.borrow().iter().map(|line| line.split(':').next().unwrap().to_string()).collect();
+  assert_eq!(names, vec!["query{op=select,table=users}", "query{table=posts}", "other"]);
+}
+
+#[test]
+fn test_reserved_tags() {
+  assert!(valid_tags(&[("table", "users")]));
+  assert!(!valid_tags(&[("table", "a,b")]));
+  assert!(!valid_tags(&[("k=v", "users")]));
+  let name = tagged_name("query", &[("table", "a,b=c"), ("op{}", "select")]);
+  assert_eq!(name, "query{op__=select,table=a_b_c}");
+  assert_eq!(split_tags(&name), ("query", vec![("op__", "select"), ("table", "a_b_c")]));
+}
+
+#[cfg(debug_assertions)]
+#[test]
+#[should_panic(expected = "tags mustn't contain")]
+fn test_time_with_reserved_tags() {
+  TimerSet::new().time_with_tags("query", &[("table", "a,b")], || {});
+}
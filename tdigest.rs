@@ -0,0 +1,265 @@
+#![deny(missing_docs)]
+
+//! A merging t-digest, for estimating quantiles of time windows more
+//! accurately than `Histogram` does, especially at the tails.
+//!
+//! See Dunning & Ertl, "Computing Extremely Accurate Quantiles Using
+//! t-Digests".
+
+#[cfg(feature = "serde")]
+use serde::{de, Deserialize, Deserializer};
+use std::f64::consts::PI;
+
+/// Bounds the number of centroids kept, to about `COMPRESSION / 2`.
+const COMPRESSION: f64 = 100.0;
+/// How many windows to buffer before merging them into the centroids.
+const BUFFER_SIZE: usize = 256;
+
+#[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+struct Centroid {
+  mean: f64,
+  count: u64,
+}
+
+impl Centroid {
+  fn absorb(&mut self, other: &Centroid) {
+    let count = self.count + other.count;
+    self.mean += (other.mean - self.mean) * other.count as f64 / count as f64;
+    self.count = count;
+  }
+}
+
+/// The scale function, mapping a quantile to the index of the centroid
+/// that covers it.
+fn k(q: f64) -> f64 {
+  COMPRESSION / (2.0 * PI) * (2.0 * q - 1.0).asin()
+}
+
+/// The inverse of `k`.
+fn k_inv(k: f64) -> f64 {
+  if k >= COMPRESSION / 4.0 {
+    1.0
+  } else {
+    ((k * 2.0 * PI / COMPRESSION).sin() + 1.0) / 2.0
+  }
+}
+
+/// Merges sorted centroids, as far as the scale function allows.
+fn compress(sorted: Vec<Centroid>, total: u64) -> Vec<Centroid> {
+  let total = total as f64;
+  let mut merged = Vec::new();
+  let mut iter = sorted.into_iter();
+  let mut current =
+    match iter.next() {
+      None => return merged,
+      Some(c) => c,
+    };
+  let mut seen = 0;
+  let mut limit = total * k_inv(k(0.0) + 1.0);
+  for c in iter {
+    if (seen + current.count + c.count) as f64 <= limit {
+      current.absorb(&c);
+    } else {
+      seen += current.count;
+      merged.push(current);
+      limit = total * k_inv(k(seen as f64 / total) + 1.0);
+      current = c;
+    }
+  }
+  merged.push(current);
+  merged
+}
+
+/// A t-digest of time windows.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct TDigest {
+  centroids: Vec<Centroid>,
+  buffer: Vec<Centroid>,
+  total: u64,
+  min: u64,
+  max: u64,
+}
+
+impl TDigest {
+  /// Creates an empty digest.
+  pub fn new() -> TDigest {
+    TDigest {
+      centroids: Vec::new(),
+      buffer: Vec::with_capacity(BUFFER_SIZE),
+      total: 0,
+      min: u64::MAX,
+      max: 0,
+    }
+  }
+
+  /// Records `n` time windows of `x` nanoseconds.
+  pub fn add(&mut self, x: u64, n: u64) {
+    self.push(Centroid { mean: x as f64, count: n });
+    self.min = self.min.min(x);
+    self.max = self.max.max(x);
+  }
+
+  fn push(&mut self, c: Centroid) {
+    self.buffer.push(c);
+    self.total += c.count;
+    if self.buffer.len() >= BUFFER_SIZE {
+      self.flush();
+    }
+  }
+
+  /// Merges the buffered windows into the centroids.
+  fn flush(&mut self) {
+    let centroids = self.sorted();
+    self.centroids = compress(centroids, self.total);
+    self.buffer.clear();
+  }
+
+  /// Returns every centroid, including buffered ones, sorted by mean.
+  fn sorted(&self) -> Vec<Centroid> {
+    let mut all: Vec<Centroid> = self.centroids.iter().chain(self.buffer.iter()).cloned().collect();
+    all.sort_by(|a, b| a.mean.partial_cmp(&b.mean).unwrap());
+    all
+  }
+
+  /// Forgets every recorded window.
+  pub fn clear(&mut self) {
+    *self = TDigest::new();
+  }
+
+  /// Adds all the windows recorded in `other` to this digest.
+  pub fn merge(&mut self, other: &TDigest) {
+    for c in other.centroids.iter().chain(other.buffer.iter()) {
+      self.push(*c);
+    }
+    self.min = self.min.min(other.min);
+    self.max = self.max.max(other.max);
+  }
+
+  /// Estimates the value below which a fraction `q` of the recorded windows
+  /// fall, interpolating between centroids.
+  pub fn quantile(&self, q: f64) -> Option<u64> {
+    if self.total == 0 {
+      return None;
+    }
+    let centroids = compress(self.sorted(), self.total);
+    let rank = q.clamp(0.0, 1.0) * self.total as f64;
+
+    // Each centroid's windows are taken to be spread evenly around its mean,
+    // so interpolate between the midpoints of neighbouring centroids.
+    let mut prev_rank = 0.0;
+    let mut prev_mean = self.min as f64;
+    let mut seen = 0.0;
+    for c in centroids.iter() {
+      let mid_rank = seen + c.count as f64 / 2.0;
+      if rank < mid_rank {
+        let frac = (rank - prev_rank) / (mid_rank - prev_rank);
+        return Some((prev_mean + (c.mean - prev_mean) * frac) as u64);
+      }
+      prev_rank = mid_rank;
+      prev_mean = c.mean;
+      seen += c.count as f64;
+    }
+    let frac = (rank - prev_rank) / (seen - prev_rank);
+    Some((prev_mean + (self.max as f64 - prev_mean) * frac) as u64)
+  }
+
+  /// Returns every centroid, as its mean and how many windows, shortest
+  /// first.
+  #[cfg(feature = "otel")]
+  pub fn buckets(&self) -> Vec<(u64, u64)> {
+    compress(self.sorted(), self.total).iter().map(|c| (c.mean as u64, c.count)).collect()
+  }
+}
+
+#[cfg(feature = "serde")]
+#[derive(Deserialize)]
+/// A `TDigest` as it's serialized, to check before it's used. Its total is
+/// counted again from its centroids.
+struct SerializedTDigest {
+  centroids: Vec<Centroid>,
+  buffer: Vec<Centroid>,
+  min: u64,
+  max: u64,
+}
+
+#[cfg(feature = "serde")]
+impl SerializedTDigest {
+  /// Makes the digest, unless a centroid hasn't got a finite mean or any
+  /// windows, which would break sorting and merging them.
+  fn into_digest(self) -> Result<TDigest, String> {
+    let mut total: u64 = 0;
+    for c in self.centroids.iter().chain(self.buffer.iter()) {
+      if !c.mean.is_finite() || c.count == 0 {
+        return Err(format!("invalid t-digest centroid {:?}", c));
+      }
+      total = total.checked_add(c.count).ok_or("t-digest window count overflows")?;
+    }
+    Ok(TDigest {
+      centroids: self.centroids,
+      buffer: self.buffer,
+      total,
+      min: self.min,
+      max: self.max,
+    })
+  }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for TDigest {
+  fn deserialize<D: Deserializer<'de>>(d: D) -> Result<TDigest, D::Error> {
+    SerializedTDigest::deserialize(d)?.into_digest().map_err(de::Error::custom)
+  }
+}
+
+#[test]
+fn test_quantile() {
+  let mut d = TDigest::new();
+  assert_eq!(d.quantile(0.5), None);
+  for x in 1..10_001 {
+    d.add(x, 1);
+  }
+  assert!(d.centroids.len() < COMPRESSION as usize);
+  for &(q, expected) in [(0.0, 1), (0.5, 5_000), (0.95, 9_500), (0.99, 9_900), (1.0, 10_000)].iter() {
+    let actual = d.quantile(q).unwrap();
+    assert!((actual as f64 - expected as f64).abs() <= 50.0, "q{} = {}", q, actual);
+  }
+}
+
+#[test]
+fn test_merge() {
+  let mut a = TDigest::new();
+  let mut b = TDigest::new();
+  for x in 0..1_000 {
+    a.add(x, 1);
+    b.add(x + 1_000, 1);
+  }
+  a.merge(&b);
+  let p50 = a.quantile(0.5).unwrap();
+  assert!(p50 > 980 && p50 < 1_020, "p50 = {}", p50);
+  assert_eq!(a.quantile(1.0), Some(1_999));
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_deserialize() {
+  let mut d = TDigest::new();
+  for x in 1..1_001 {
+    d.add(x, 1);
+  }
+  let json = ::serde_json::to_string(&d).unwrap();
+  let loaded: TDigest = ::serde_json::from_str(&json.replace(&format!("\"total\":{}", d.total), "\"total\":7")).unwrap();
+  assert_eq!(loaded.total, 1_000);
+  assert_eq!(loaded.quantile(0.5), d.quantile(0.5));
+
+  let bad = |centroid: &str| format!("{{\"centroids\":[{}],\"buffer\":[],\"total\":1,\"min\":1,\"max\":1}}", centroid);
+  assert!(::serde_json::from_str::<TDigest>(&bad("{\"mean\":1.0,\"count\":1}")).is_ok());
+  assert!(::serde_json::from_str::<TDigest>(&bad("{\"mean\":1.0,\"count\":0}")).is_err());
+  let overflow = format!("{{\"mean\":1.0,\"count\":{}}},{{\"mean\":2.0,\"count\":1}}", u64::MAX);
+  assert!(::serde_json::from_str::<TDigest>(&bad(&overflow)).is_err());
+
+  // JSON can't spell out a NaN, but other formats can.
+  let nan = SerializedTDigest { centroids: vec![Centroid { mean: f64::NAN, count: 1 }], buffer: Vec::new(), min: 1, max: 1 };
+  assert!(nan.into_digest().is_err());
+}
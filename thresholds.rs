@@ -0,0 +1,82 @@
+//! Alerts for single windows that take too long.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use TimerSet;
+
+/// Called with a timer's name and a window over its threshold.
+type Alert = Arc<dyn Fn(&str, Duration) + Send + Sync>;
+
+/// What to do with a window over a timer's threshold.
+#[derive(Clone)]
+pub(crate) struct Threshold {
+  /// The longest a window can take without alerting, in nanoseconds.
+  limit: u64,
+  /// Called with the timer's name and the window, or `None` to log a
+  /// warning instead.
+  alert: Option<Alert>,
+}
+
+impl<K> TimerSet<K> {
+  /// Calls `alert` with the timer's name and the window whenever a single
+  /// window of the named timer takes longer than `limit`, as soon as it's
+  /// logged, on the thread that logged it. `name` is the full name the timer
+  /// reports under.
+  pub fn set_threshold<F: Fn(&str, Duration) + Send + Sync + 'static>(&mut self, name: &str, limit: Duration, alert: F) {
+    self.thresholds.insert(name.to_string(), Threshold {
+      limit: limit.as_nanos() as u64,
+      alert: Some(Arc::new(alert)),
+    });
+  }
+
+  /// Like `set_threshold`, but logs a warning instead of calling anything.
+  pub fn warn_above(&mut self, name: &str, limit: Duration) {
+    self.thresholds.insert(name.to_string(), Threshold {
+      limit: limit.as_nanos() as u64,
+      alert: None,
+    });
+  }
+
+  /// Stops alerting on the named timer's windows.
+  pub fn clear_threshold(&mut self, name: &str) {
+    self.thresholds.remove(name);
+  }
+
+  /// Alerts if a window of `dt` nanoseconds is over the named timer's
+  /// threshold.
+  pub(crate) fn check_threshold(&self, name: &str, dt: u64) {
+    let threshold =
+      match self.thresholds.get(name) {
+        Some(threshold) if dt > threshold.limit => threshold,
+        _ => return,
+      };
+    let dt = Duration::from_nanos(dt);
+    match threshold.alert {
+      Some(ref alert) => alert(name, dt),
+      None => warn!("{} took {:?}, over its threshold of {:?}", name, dt, Duration::from_nanos(threshold.limit)),
+    }
+  }
+}
+
+#[cfg(not(feature = "disable"))]
+#[test]
+fn test_set_threshold() {
+  use std::sync::Mutex;
+
+  let alerts = Arc::new(Mutex::new(Vec::new()));
+  let mut ts = TimerSet::new();
+  {
+    let alerts = alerts.clone();
+    ts.set_threshold("parse", Duration::from_millis(5), move |name, d| alerts.lock().unwrap().push((name.to_string(), d)));
+  }
+  ts.warn_above("other", Duration::from_millis(1));
+  ts.record("parse", Duration::from_millis(2));
+  ts.record("parse", Duration::from_millis(7));
+  ts.record("other", Duration::from_millis(7));
+  assert_eq!(*alerts.lock().unwrap(), vec![("parse".to_string(), Duration::from_millis(7))]);
+
+  ts.clear_threshold("parse");
+  ts.record("parse", Duration::from_millis(9));
+  assert_eq!(alerts.lock().unwrap().len(), 1);
+}
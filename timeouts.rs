@@ -0,0 +1,49 @@
+//! Counting the windows that go over a latency budget.
+
+use std::time::Duration;
+
+use {to_nanos, Extras, TimerSet};
+
+impl<K> TimerSet<K> {
+  /// Like `time`, but also counts the window as a violation if it takes
+  /// longer than `timeout`. `f` isn't cut short; the count shows up in
+  /// reports as `N over timeout`, and in `Stopwatch::violations`, to check
+  /// latency budgets are met, e.g. in soak tests.
+  pub fn time_with_timeout<T, F: FnOnce() -> T>(&self, name: &str, timeout: Duration, f: F) -> T {
+    let timeout = Extras {
+      timeout: Some(to_nanos(timeout)),
+      ..Extras::default()
+    };
+    self.time_window(name, None, f, |name, key, start, weight, _| {
+      self.stop_with_extras(name, key, start, weight, timeout);
+    }).0
+  }
+}
+
+#[cfg(not(feature = "disable"))]
+#[test]
+fn test_time_with_timeout() {
+  let ts = TimerSet::new();
+  ts.time_with_timeout("a", Duration::from_secs(60), || {});
+  ts.time_with_timeout("a", Duration::ZERO, || ::std::thread::sleep(Duration::from_millis(1)));
+  ts.time("a", || ::std::thread::sleep(Duration::from_millis(1)));
+
+  let a = &ts.merged()["a"];
+  assert_eq!(a.number_of_windows, 3);
+  assert_eq!(a.violations(), 1);
+  assert!(a.summary(::DurationFormat::default()).contains(", 1 over timeout)"));
+  assert_eq!(a.stats().violations, 1);
+
+  // Windows set aside during warmup are only violations where they're kept.
+  let mut ts = TimerSet::new();
+  ts.set_warmup("dropped", 1, ::Warmup::Discard);
+  ts.set_warmup("cold", 1, ::Warmup::Separate);
+  for _ in 0..2 {
+    ts.time_with_timeout("dropped", Duration::ZERO, || ::std::thread::sleep(Duration::from_millis(1)));
+    ts.time_with_timeout("cold", Duration::ZERO, || ::std::thread::sleep(Duration::from_millis(1)));
+  }
+  let timers = ts.merged();
+  assert_eq!((timers["dropped"].number_of_windows, timers["dropped"].violations()), (1, 1));
+  assert_eq!((timers["cold"].number_of_windows, timers["cold"].violations()), (1, 1));
+  assert_eq!((timers["cold.warmup"].number_of_windows, timers["cold.warmup"].violations()), (1, 1));
+}
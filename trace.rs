@@ -0,0 +1,102 @@
+//! Recording of individual timed calls, for Chrome's trace viewer.
+
+use std::cell::Cell;
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use {lock, TimerSet};
+
+/// A single timed call.
+pub(crate) struct Event {
+  name: String,
+  thread: usize,
+  /// When the call started, in nanoseconds on the set's clock.
+  start: u64,
+  /// How long the call took, in nanoseconds.
+  duration: u64,
+}
+
+static NEXT_THREAD_ID: AtomicUsize = AtomicUsize::new(0);
+
+thread_local!(static THREAD_ID: Cell<usize> = Cell::new(NEXT_THREAD_ID.fetch_add(1, Ordering::Relaxed)));
+
+/// Quotes a string for JSON.
+fn quote(s: &str) -> String {
+  let mut quoted = String::with_capacity(s.len() + 2);
+  quoted.push('"');
+  for c in s.chars() {
+    match c {
+      '"' => quoted.push_str("\\\""),
+      '\\' => quoted.push_str("\\\\"),
+      c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+      c => quoted.push(c),
+    }
+  }
+  quoted.push('"');
+  quoted
+}
+
+impl<K> TimerSet<K> {
+  /// Turns event recording on or off. While it's on, every call timed with
+  /// `time` or `start` is also recorded individually, for
+  /// `write_chrome_trace`. Events are kept until `reset_all`, so this uses
+  /// memory in proportion to the number of calls.
+  pub fn set_recording_events(&mut self, recording: bool) {
+    self.events = if recording { Some(Default::default()) } else { None };
+  }
+
+  /// Records a call to the named timer, if we're recording events.
+  pub(crate) fn record_event(&self, name: &str, start: u64, duration: u64) {
+    if let Some(ref events) = self.events {
+      let event = Event {
+        name: name.to_string(),
+        thread: THREAD_ID.with(|id| id.get()),
+        start,
+        duration,
+      };
+      lock(events).push(event);
+    }
+  }
+
+  /// Writes every recorded event in Chrome's Trace Event JSON format, which
+  /// chrome://tracing and Perfetto can open as a timeline.
+  pub fn write_chrome_trace<W: Write>(&self, mut w: W) -> io::Result<()> {
+    write!(w, "{{\"traceEvents\":[")?;
+    if let Some(ref events) = self.events {
+      let events = lock(events);
+      let epoch = events.iter().map(|e| e.start).min().unwrap_or(0);
+      for (i, e) in events.iter().enumerate() {
+        if i > 0 {
+          write!(w, ",")?;
+        }
+        write!(
+          w,
+          "{{\"name\":{},\"ph\":\"X\",\"pid\":0,\"tid\":{},\"ts\":{:.3},\"dur\":{:.3}}}",
+          quote(&e.name),
+          e.thread,
+          (e.start - epoch) as f64 / 1_000.0,
+          e.duration as f64 / 1_000.0
+        )?;
+      }
+    }
+    writeln!(w, "]}}")
+  }
+}
+
+#[cfg(not(feature = "disable"))]
+#[test]
+fn test_write_chrome_trace() {
+  let mut ts = TimerSet::new();
+  ts.time("before", || {});
+  ts.set_recording_events(true);
+  ts.time("a \"quoted\" name", || {
+    ts.time("b", || {});
+  });
+  let mut out = Vec::new();
+  ts.write_chrome_trace(&mut out).unwrap();
+  let out = String::from_utf8(out).unwrap();
+  assert!(out.starts_with("{\"traceEvents\":[{\"name\":\"b\",\"ph\":\"X\""), "{}", out);
+  assert!(out.contains("\"name\":\"a \\\"quoted\\\" name\""), "{}", out);
+  assert!(!out.contains("before"), "{}", out);
+  assert_eq!(out.matches("\"ph\"").count(), 2);
+}
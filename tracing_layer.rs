@@ -0,0 +1,82 @@
+//! Aggregating `tracing` spans into timers.
+
+use std::sync::Arc;
+use tracing::span::{Attributes, Id};
+use tracing::Subscriber;
+use tracing_subscriber::layer::{Context, Layer};
+use tracing_subscriber::registry::LookupSpan;
+
+use TimerSet;
+
+/// When a span was created, on the set's clock.
+struct SpanStart(u64);
+
+impl<K> TimerSet<K> {
+  fn on_span_new<S: Subscriber + for<'a> LookupSpan<'a>>(&self, id: &Id, ctx: Context<S>) {
+    if let Some(span) = ctx.span(id) {
+      span.extensions_mut().insert(SpanStart(self.clock.now()));
+    }
+  }
+
+  fn on_span_close<S: Subscriber + for<'a> LookupSpan<'a>>(&self, id: &Id, ctx: Context<S>) {
+    if let Some(span) = ctx.span(id) {
+      if let Some(&SpanStart(then)) = span.extensions().get::<SpanStart>() {
+        let dt = self.clock.to_nanos(self.clock.now().saturating_sub(then));
+        self.add_window(span.name(), dt);
+      }
+    }
+  }
+}
+
+/// Times every span, from its creation until it closes, under a timer named
+/// after the span.
+impl<S: Subscriber + for<'a> LookupSpan<'a>> Layer<S> for TimerSet {
+  fn on_new_span(&self, _attrs: &Attributes, id: &Id, ctx: Context<S>) {
+    self.on_span_new(id, ctx);
+  }
+
+  fn on_close(&self, id: Id, ctx: Context<S>) {
+    self.on_span_close(&id, ctx);
+  }
+}
+
+/// A `tracing_subscriber::Layer` that times spans into a shared `TimerSet`,
+/// so the set can still be read once the subscriber owns the layer.
+pub struct TimerLayer {
+  timers: Arc<TimerSet>,
+}
+
+impl TimerLayer {
+  /// Creates a layer that times spans into `timers`, like `TimerSet`'s own
+  /// `Layer` impl does.
+  pub fn new(timers: Arc<TimerSet>) -> TimerLayer {
+    TimerLayer {
+      timers,
+    }
+  }
+}
+
+impl<S: Subscriber + for<'a> LookupSpan<'a>> Layer<S> for TimerLayer {
+  fn on_new_span(&self, _attrs: &Attributes, id: &Id, ctx: Context<S>) {
+    self.timers.on_span_new(id, ctx);
+  }
+
+  fn on_close(&self, id: Id, ctx: Context<S>) {
+    self.timers.on_span_close(&id, ctx);
+  }
+}
+
+#[cfg(not(feature = "disable"))]
+#[test]
+fn test_timer_layer() {
+  use tracing_subscriber::layer::SubscriberExt;
+
+  let timers = Arc::new(TimerSet::new());
+  let subscriber = tracing_subscriber::registry().with(TimerLayer::new(timers.clone()));
+  tracing::subscriber::with_default(subscriber, || {
+    for _ in 0..2 {
+      let _span = tracing::info_span!("work").entered();
+    }
+  });
+  assert_eq!(timers.merged()["work"].number_of_windows, 2);
+}
@@ -1,9 +1,12 @@
 #![deny(missing_docs)]
 
+#[cfg(target_arch = "x86")]
+use std::arch::x86::_rdtsc;
+#[cfg(target_arch = "x86_64")]
+use std::arch::x86_64::_rdtsc;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::thread::sleep;
-use std::time::Duration;
-use time::precise_time_ns;
-use x86::bits64::time::rdtsc;
+use std::time::{Duration, Instant};
 
 /// A timestamp.
 pub type T = u64;
@@ -11,56 +14,190 @@ pub type T = u64;
 /// Reads the x86 time stamp counter.
 #[inline(always)]
 pub fn read() -> T {
-  unsafe { rdtsc() }
+  unsafe { _rdtsc() }
 }
 
-fn bexp(x: f64, sigdigs: f64) -> f64 {
-  (10.0f64).powf(x.log10().floor() - (sigdigs - 1.0))
+/// Measures how many ticks `read` counts over a short sleep, and how many
+/// nanoseconds that took.
+fn measure() -> (u64, u64) {
+  let t0 = Instant::now();
+  let c0 = read();
+  sleep(Duration::new(0, 100_000));
+  let c1 = read();
+  let dt = t0.elapsed();
+  (c1 - c0, dt.as_secs() * 1_000_000_000 + dt.subsec_nanos() as u64)
 }
 
-fn sround(x: f64, nearest: f64) -> f64 {
-  (nearest * (x / nearest).round())
+/// The tick rate of `dc` ticks in `dt` nanoseconds.
+fn calc_tps(dc: u64, dt: u64) -> u64 {
+  (1_000_000_000 * dc as u128 / dt as u128) as u64
 }
 
-fn round_keeping_top_sigdigs(x: u64, sigdigs: u32) -> u64 {
-  let x = x as f64;
-  sround(x, bexp(x, sigdigs as f64)) as u64
+/// Nanoseconds per tick of `dc` ticks in `dt` nanoseconds, as 32.32 fixed
+/// point.
+fn calc_ns_per_tick(dc: u64, dt: u64) -> u64 {
+  (((dt as u128) << 32) / dc.max(1) as u128) as u64
 }
 
-fn calc_tps(dc: u64, dt: u64) -> u64 {
-  let ticks_per_second = (1_000_000_000 * dc) / dt;
-  round_keeping_top_sigdigs(ticks_per_second, 2)
-}
+// The calibrated tick rate, or 0 if we haven't calibrated yet.
+static TICKS_PER_SECOND: AtomicU64 = AtomicU64::new(0);
+// Nanoseconds per tick, as 32.32 fixed point. Only valid once calibrated.
+static NS_PER_TICK: AtomicU64 = AtomicU64::new(0);
 
-/// Returns the number of ticks per second that `read`'s tick count runs at.
-pub fn ticks_per_second() -> T {
-  let t0 = precise_time_ns();
-  let c0 = read();
-  sleep(Duration::new(0, 100_000));
-  let c1 = read();
-  let t1 = precise_time_ns();
-
-  let dc = c1 - c0;
-  let dt = t1 - t0;
+/// Measures the tick rate, and caches it for `to_ns`.
+pub fn recalibrate() -> T {
+  // Keep the ratio exactly as measured: rounding the rate would skew every
+  // reading by as much as it was rounded.
+  let (dc, dt) = measure();
+  let tps = calc_tps(dc, dt);
+  NS_PER_TICK.store(calc_ns_per_tick(dc, dt), Ordering::Relaxed);
+  TICKS_PER_SECOND.store(tps, Ordering::Relaxed);
+  tps
+}
 
-  calc_tps(dc, dt)
+/// Returns the cached tick rate, calibrating it if that hasn't happened yet.
+pub fn calibrated_ticks_per_second() -> T {
+  match TICKS_PER_SECOND.load(Ordering::Relaxed) {
+    0 => recalibrate(),
+    tps => tps,
+  }
 }
 
-pub fn to_ns(dt: T, tps: T) -> u64 {
-  (dt * 1_000_000_000) / tps
+/// Converts a tick count to nanoseconds at the cached tick rate, saturating
+/// on overflow.
+#[inline]
+pub fn to_ns(dt: T) -> u64 {
+  let ns = (dt as u128 * NS_PER_TICK.load(Ordering::Relaxed) as u128) >> 32;
+  if ns > u64::MAX as u128 { u64::MAX } else { ns as u64 }
 }
 
-pub fn to_us(dt: T, tps: T) -> u64 {
-  to_ns(dt, tps) / 1000
+/// A span of time stamp counter ticks, at a known tick rate.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct TscDuration {
+  ticks: T,
+  ticks_per_second: T,
 }
 
-pub fn to_ms(dt: T, tps: T) -> u64 {
-  to_us(dt, tps) / 1009
+impl TscDuration {
+  /// Creates a span of `ticks` ticks, at `ticks_per_second`.
+  ///
+  /// Panics if `ticks_per_second` is zero.
+  pub fn new(ticks: T, ticks_per_second: T) -> TscDuration {
+    assert!(ticks_per_second > 0, "TSC tick rate must be nonzero");
+    TscDuration {
+      ticks,
+      ticks_per_second,
+    }
+  }
+
+  /// Creates a span of `ticks` ticks, at the calibrated tick rate.
+  pub fn from_ticks(ticks: T) -> TscDuration {
+    TscDuration::new(ticks, calibrated_ticks_per_second())
+  }
+
+  /// Returns the number of ticks in this span.
+  pub fn ticks(&self) -> T {
+    self.ticks
+  }
+
+  /// Returns the length of this span in units of `1 / units_per_second`
+  /// seconds, or `None` on overflow.
+  fn checked_in(&self, units_per_second: u64) -> Option<u64> {
+    let units = self.ticks as u128 * units_per_second as u128 / self.ticks_per_second as u128;
+    if units > u64::MAX as u128 { None } else { Some(units as u64) }
+  }
+
+  /// Returns the length of this span in nanoseconds, or `None` on overflow.
+  pub fn checked_nanos(&self) -> Option<u64> {
+    self.checked_in(1_000_000_000)
+  }
+
+  /// Returns the length of this span in microseconds, or `None` on overflow.
+  pub fn checked_micros(&self) -> Option<u64> {
+    self.checked_in(1_000_000)
+  }
+
+  /// Returns the length of this span in milliseconds, or `None` on overflow.
+  pub fn checked_millis(&self) -> Option<u64> {
+    self.checked_in(1_000)
+  }
+
+  /// Returns the length of this span in seconds, or `None` on overflow.
+  pub fn checked_secs(&self) -> Option<u64> {
+    self.checked_in(1)
+  }
+
+  /// Returns the length of this span in nanoseconds, saturating on overflow.
+  pub fn saturating_nanos(&self) -> u64 {
+    self.checked_nanos().unwrap_or(u64::MAX)
+  }
+
+  /// Returns the length of this span in microseconds, saturating on overflow.
+  pub fn saturating_micros(&self) -> u64 {
+    self.checked_micros().unwrap_or(u64::MAX)
+  }
+
+  /// Returns the length of this span in milliseconds, saturating on overflow.
+  pub fn saturating_millis(&self) -> u64 {
+    self.checked_millis().unwrap_or(u64::MAX)
+  }
+
+  /// Returns the length of this span in seconds, saturating on overflow.
+  pub fn saturating_secs(&self) -> u64 {
+    self.checked_secs().unwrap_or(u64::MAX)
+  }
+
+  /// Converts this span to a `Duration`.
+  pub fn to_duration(&self) -> Duration {
+    let secs = self.ticks / self.ticks_per_second;
+    let rem = self.ticks % self.ticks_per_second;
+    let nanos = rem as u128 * 1_000_000_000 / self.ticks_per_second as u128;
+    Duration::new(secs, nanos as u32)
+  }
 }
 
 #[test]
 fn test_calc_tps() {
   assert_eq!(calc_tps(10_000_000, 1_000_000), 10_000_000_000);
-  assert_eq!(calc_tps(12_400_000, 1_000_000), 12_000_000_000);
-  assert_eq!(calc_tps(12_600_000, 1_000_000), 13_000_000_000);
+  assert_eq!(calc_tps(12_400_000, 1_000_000), 12_400_000_000);
+  assert_eq!(calc_tps(2_893_417, 1_000_000), 2_893_417_000);
+  // A 2.893417GHz TSC converts at its measured rate, not a rounded 2.9GHz,
+  // which would be 0.2% off.
+  let ns_per_tick = calc_ns_per_tick(2_893_417, 1_000_000);
+  assert_eq!(((2_893_417_000u128 * ns_per_tick as u128) >> 32) as u64, 999_999_999);
+  assert_eq!(calc_ns_per_tick(1_000, 1_000), 1 << 32);
+}
+
+#[test]
+fn test_to_ns() {
+  let tps = calibrated_ticks_per_second();
+  assert_eq!(calibrated_ticks_per_second(), tps);
+  let ns = to_ns(tps);
+  assert!(ns > 999_000_000 && ns <= 1_000_000_000, "ns = {}", ns);
+}
+
+#[test]
+fn test_tsc_duration() {
+  let d = TscDuration::new(3_000_000_000, 3_000_000_000);
+  assert_eq!(d.checked_nanos(), Some(1_000_000_000));
+  assert_eq!(d.checked_micros(), Some(1_000_000));
+  assert_eq!(d.checked_millis(), Some(1_000));
+  assert_eq!(d.checked_secs(), Some(1));
+  assert_eq!(d.to_duration(), Duration::from_secs(1));
+}
+
+#[test]
+fn test_tsc_duration_large() {
+  // Too many nanoseconds for a u64, and too many ticks to multiply in one.
+  let d = TscDuration::new(u64::MAX, 1_000_000);
+  assert_eq!(d.checked_nanos(), None);
+  assert_eq!(d.saturating_nanos(), u64::MAX);
+  assert_eq!(d.checked_micros(), Some(u64::MAX));
+  assert_eq!(d.checked_secs(), Some(18_446_744_073_709));
+  assert_eq!(d.to_duration().as_secs(), 18_446_744_073_709);
+
+  let d = TscDuration::new(u64::MAX, 1);
+  assert_eq!(d.checked_millis(), None);
+  assert_eq!(d.saturating_millis(), u64::MAX);
+  assert_eq!(d.checked_secs(), Some(u64::MAX));
 }